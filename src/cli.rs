@@ -1,9 +1,14 @@
 use std::path::PathBuf;
 
 use clap::builder::styling::{AnsiColor, Effects, Styles};
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 
+use crate::config::ConfigFormat;
+use crate::naming::NameRewrite;
+use crate::order::RepoOrder;
+use crate::sync::ActionKind;
+
 const STYLES: Styles = Styles::styled()
     .header(AnsiColor::Yellow.on_default().effects(Effects::BOLD))
     .usage(AnsiColor::Yellow.on_default().effects(Effects::BOLD))
@@ -13,6 +18,10 @@ const STYLES: Styles = Styles::styled()
 #[derive(Parser)]
 #[command(name = "gemote", version, about = "Declarative git remote management.", styles = STYLES)]
 pub struct Cli {
+    /// Run as if gemote was started in this directory
+    #[arg(short = 'C', long, global = true, value_name = "DIR")]
+    pub chdir: Option<PathBuf>,
+
     /// Path to the .gemote config file
     #[arg(long, global = true)]
     pub config: Option<PathBuf>,
@@ -21,6 +30,19 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub repo: Option<PathBuf>,
 
+    /// Also search for .gemote in parent directories of the repo, up to this one
+    #[arg(long, global = true, value_name = "DIR")]
+    pub config_search_stop: Option<PathBuf>,
+
+    /// Base directory for relative paths such as --remotes-from [default: the
+    /// config file's directory]
+    #[arg(long, global = true, value_name = "DIR")]
+    pub config_root: Option<PathBuf>,
+
+    /// Parse (and write) the config file in this format, whatever its extension
+    #[arg(long, global = true, value_enum, value_name = "FORMAT")]
+    pub config_format: Option<ConfigFormat>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -32,9 +54,131 @@ pub enum Commands {
         /// Preview changes without applying them
         #[arg(long)]
         dry_run: bool,
+        /// With --dry-run, also print the git commands that would make each
+        /// change
+        #[arg(long, short = 'v', requires = "dry_run")]
+        verbose: bool,
         /// Also process submodules and nested repos
         #[arg(long, short = 'r')]
         recursive: bool,
+        /// Fail if a submodule config section has no matching repo on disk
+        #[arg(long, requires = "recursive")]
+        strict_paths: bool,
+        /// Fail on config problems that are otherwise only warned about
+        #[arg(long)]
+        strict: bool,
+        /// Remove submodule config sections with no matching repo on disk
+        /// from the config file after syncing
+        #[arg(long, requires = "recursive", conflicts_with = "strict_paths")]
+        prune_config_sections: bool,
+        /// Only apply changes of this kind (repeatable)
+        #[arg(long, value_enum, value_name = "KIND")]
+        action_kind: Vec<ActionKind>,
+        /// Only change push URLs; leave fetch URLs and everything else alone
+        #[arg(long, conflicts_with = "only_fetch_url")]
+        only_push_url: bool,
+        /// Only change fetch URLs; leave push URLs and everything else alone
+        #[arg(long)]
+        only_fetch_url: bool,
+        /// Only touch remotes whose names start with this prefix
+        #[arg(long, value_name = "PREFIX")]
+        prefix: Option<String>,
+        /// List remotes missing from the config that sync leaves alone, next
+        /// to the planned changes
+        #[arg(long)]
+        show_unmanaged: bool,
+        /// List remotes missing from the config as unmanaged and never
+        /// remove them, whatever `extra_remotes` says
+        #[arg(long)]
+        preserve_unknown_remotes: bool,
+        /// Ask about each change before applying it, like `git add -p`
+        #[arg(long, conflicts_with_all = ["dry_run", "jobs"])]
+        select: bool,
+        /// Fail instead of warning when a repo is in the middle of a merge,
+        /// rebase, cherry-pick or bisect
+        #[arg(long)]
+        refuse_during_operation: bool,
+        /// Fail, before changing it, if any one repo would lose more than
+        /// this many remotes
+        #[arg(long, value_name = "N")]
+        max_removals_per_repo: Option<usize>,
+        /// Only process submodules from .gitmodules; skip scanning for nested repos
+        #[arg(long, requires = "recursive")]
+        no_discover: bool,
+        /// Don't warn about submodules that aren't checked out
+        #[arg(long, requires = "recursive")]
+        no_submodule_init_warn: bool,
+        /// Order in which the root and its sub-repos are processed
+        #[arg(
+            long,
+            value_enum,
+            value_name = "ORDER",
+            default_value_t,
+            requires = "recursive"
+        )]
+        repo_order: RepoOrder,
+        /// Sync against a plain list of URLs (one per line) instead of the config
+        #[arg(long, value_name = "FILE", conflicts_with = "recursive")]
+        remotes_from: Option<PathBuf>,
+        /// Name remotes from --remotes-from by their URL [default: {org}]
+        #[arg(long, value_name = "TEMPLATE", requires = "remotes_from")]
+        remote_name_template: Option<String>,
+        /// Move remotes off old URLs using a file of `old_url,new_url` lines,
+        /// in addition to the config
+        #[arg(long, value_name = "FILE")]
+        remote_url_map: Option<PathBuf>,
+        /// Rename remotes whose whole name matches the regex FROM to TO, where
+        /// TO can use $1, ${name} and so on from the match
+        #[arg(
+            long,
+            value_name = "FROM=TO",
+            value_parser = |rule: &str| rule.parse::<NameRewrite>().map(Box::new)
+        )]
+        remote_name_rewrite: Option<Box<NameRewrite>>,
+        /// Prefix the top-level repo's output lines with `[LABEL] `, as
+        /// sub-repos are with their path
+        #[arg(long, value_name = "LABEL")]
+        repo_label: Option<String>,
+        /// Name used for {repo_name} in config URLs [default: the repo's directory name]
+        #[arg(long, value_name = "NAME")]
+        repo_name: Option<String>,
+        /// After changing a remote's fetch URL, ask the new URL for its
+        /// default branch and point <remote>/HEAD at it (needs network)
+        #[arg(long)]
+        refresh_head: bool,
+        /// Write URL lists and remote options with the `git` CLI instead of libgit2
+        #[arg(long)]
+        use_git_cli: bool,
+        /// Rename a local remote whose URLs match a new config entry instead
+        /// of removing and re-adding it
+        #[arg(long)]
+        rename_detect: bool,
+        /// Sync up to this many repos at once; output is printed in path order
+        #[arg(
+            long,
+            short = 'j',
+            value_name = "N",
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u16).range(1..),
+            requires = "recursive"
+        )]
+        jobs: u16,
+        /// Output format; json prints the plan on stdout and a summary on stderr
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
+        /// Also print the warnings as a JSON array on stderr
+        #[arg(long)]
+        warnings_as_json: bool,
+        /// Print one JSON line per repo on stdout as soon as it is done,
+        /// instead of the usual output
+        #[arg(long, conflicts_with = "format")]
+        ndjson: bool,
+        /// Format of the per-repo report written to --report
+        #[arg(long, value_enum, value_name = "FORMAT", requires = "report")]
+        report_format: Option<ReportFormat>,
+        /// Write a per-repo result report to this file
+        #[arg(long, value_name = "PATH", requires = "report_format")]
+        report: Option<PathBuf>,
     },
     /// Save current local remotes into .gemote
     Save {
@@ -44,24 +188,231 @@ pub enum Commands {
         /// Also save remotes for submodules and nested repos
         #[arg(long, short = 'r')]
         recursive: bool,
+        /// Name saved remotes from their URL, e.g. "{org}" or "{host}-{org}"
+        #[arg(long, value_name = "TEMPLATE")]
+        remote_name_template: Option<String>,
+        /// Only process submodules from .gitmodules; skip scanning for nested repos
+        #[arg(long, requires = "recursive")]
+        no_discover: bool,
+        /// Don't warn about submodules that aren't checked out
+        #[arg(long, requires = "recursive")]
+        no_submodule_init_warn: bool,
+        /// Order in which the root and its sub-repos are processed
+        #[arg(
+            long,
+            value_enum,
+            value_name = "ORDER",
+            default_value_t,
+            requires = "recursive"
+        )]
+        repo_order: RepoOrder,
+        /// Only update this remote's entry in the existing config (repeatable)
+        #[arg(
+            long,
+            value_name = "NAME",
+            conflicts_with_all = ["force", "recursive", "remote_name_template"]
+        )]
+        remote: Vec<String>,
+        /// Print the config that would be saved instead of writing it
+        #[arg(long)]
+        dry_run: bool,
+        /// Output format for --dry-run; json prints the config as JSON
+        #[arg(long, value_enum, default_value_t, requires = "dry_run")]
+        format: Format,
+    },
+    /// Restore the remotes that were in place before the last sync
+    Undo {
+        /// Preview changes without applying them
+        #[arg(long)]
+        dry_run: bool,
+        /// Read the snapshot from this file instead of .git/gemote-last-applied
+        #[arg(long, value_name = "PATH")]
+        from_backup: Option<PathBuf>,
+    },
+    /// Show each remote's state against the config; fails if a sync would
+    /// change anything
+    Status {
+        /// Exit 0 even when remotes have drifted, for reporting only
+        #[arg(long)]
+        exit_zero: bool,
+        /// Only report remotes whose names start with this prefix
+        #[arg(long, value_name = "PREFIX")]
+        prefix: Option<String>,
+        /// Compare the config against a repo with no remotes, e.g. to preview
+        /// a fresh clone; needs no repo
+        #[arg(long, visible_alias = "no-repo")]
+        only_config: bool,
+    },
+    /// Fail unless the repo's remotes match the config exactly, extras included
+    Verify,
+    /// Check the repo for problems, such as a sync that stopped halfway
+    Doctor {
+        /// Repair the problems that can be repaired safely
+        #[arg(long)]
+        fix: bool,
+        /// Apply every fix without asking first
+        #[arg(long, short = 'y', requires = "fix")]
+        yes: bool,
+    },
+    /// Clone a repo, then sync its remotes to the config
+    Bootstrap {
+        /// URL or path to clone from
+        url: String,
+        /// Directory to clone into [default: the repo name from the URL]
+        dir: Option<PathBuf>,
+        /// Show what would be done without cloning
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show the full state of a single remote
+    Inspect {
+        /// Name of the remote to inspect
+        name: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
     },
     /// Generate shell completions
     Completions {
         /// The shell to generate completions for (bash, zsh, fish, powershell, elvish)
         shell: Shell,
+        /// Write the script to the shell's per-user completions directory
+        /// instead of stdout (bash, zsh and fish)
+        #[arg(long)]
+        install: bool,
+        /// Replace an existing installed script
+        #[arg(long, requires = "install")]
+        force: bool,
     },
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Formats for `sync --report`.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ReportFormat {
+    /// JUnit XML, one test case per repo
+    Junit,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use clap::CommandFactory;
+    use std::path::Path;
 
     #[test]
     fn verify_cli() {
         Cli::command().debug_assert();
     }
 
+    #[test]
+    fn parse_chdir() {
+        let cli = Cli::try_parse_from(["gemote", "-C", "/tmp/repo", "sync"]).unwrap();
+        assert_eq!(cli.chdir.as_deref(), Some(Path::new("/tmp/repo")));
+        let cli = Cli::try_parse_from(["gemote", "sync", "--chdir", "/tmp/repo"]).unwrap();
+        assert_eq!(cli.chdir.as_deref(), Some(Path::new("/tmp/repo")));
+    }
+
+    #[test]
+    fn parse_sync_prune_config_sections() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "-r", "--prune-config-sections"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                prune_config_sections: true,
+                ..
+            }
+        ));
+
+        assert!(Cli::try_parse_from(["gemote", "sync", "--prune-config-sections"]).is_err());
+        assert!(
+            Cli::try_parse_from([
+                "gemote",
+                "sync",
+                "-r",
+                "--strict-paths",
+                "--prune-config-sections"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_sync_strict() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--strict"]).unwrap();
+        assert!(matches!(cli.command, Commands::Sync { strict: true, .. }));
+    }
+
+    #[test]
+    fn parse_sync_use_git_cli() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--use-git-cli"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                use_git_cli: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_sync_rename_detect() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--rename-detect"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                rename_detect: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_sync_remote_name_template_requires_remotes_from() {
+        assert!(
+            Cli::try_parse_from(["gemote", "sync", "--remote-name-template", "{org}"]).is_err()
+        );
+        assert!(
+            Cli::try_parse_from([
+                "gemote",
+                "sync",
+                "--remotes-from",
+                "urls.txt",
+                "--remote-name-template",
+                "{org}"
+            ])
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn parse_save_format_requires_dry_run() {
+        assert!(Cli::try_parse_from(["gemote", "save", "--format", "json"]).is_err());
+        let cli = Cli::try_parse_from(["gemote", "save", "--dry-run", "--format", "json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Save {
+                dry_run: true,
+                format: Format::Json,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_sync_jobs() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "-r", "-j", "4"]).unwrap();
+        assert!(matches!(cli.command, Commands::Sync { jobs: 4, .. }));
+        assert!(Cli::try_parse_from(["gemote", "sync", "-r", "--jobs", "0"]).is_err());
+        assert!(Cli::try_parse_from(["gemote", "sync", "--jobs", "2"]).is_err());
+    }
+
     #[test]
     fn parse_sync() {
         let cli = Cli::try_parse_from(["gemote", "sync"]).unwrap();
@@ -69,7 +420,8 @@ mod tests {
             cli.command,
             Commands::Sync {
                 dry_run: false,
-                recursive: false
+                recursive: false,
+                ..
             }
         ));
     }
@@ -81,7 +433,8 @@ mod tests {
             cli.command,
             Commands::Sync {
                 dry_run: true,
-                recursive: false
+                recursive: false,
+                ..
             }
         ));
     }
@@ -93,7 +446,8 @@ mod tests {
             cli.command,
             Commands::Sync {
                 dry_run: false,
-                recursive: true
+                recursive: true,
+                ..
             }
         ));
     }
@@ -105,11 +459,88 @@ mod tests {
             cli.command,
             Commands::Sync {
                 dry_run: false,
-                recursive: true
+                recursive: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_sync_strict_paths() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "-r", "--strict-paths"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                recursive: true,
+                strict_paths: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_sync_strict_paths_requires_recursive() {
+        assert!(Cli::try_parse_from(["gemote", "sync", "--strict-paths"]).is_err());
+    }
+
+    #[test]
+    fn parse_sync_action_kind() {
+        let cli = Cli::try_parse_from([
+            "gemote",
+            "sync",
+            "--action-kind",
+            "add",
+            "--action-kind",
+            "update",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync { action_kind, .. }
+            if action_kind == vec![ActionKind::Add, ActionKind::Update]
+        ));
+    }
+
+    #[test]
+    fn parse_sync_only_url_scope() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--only-push-url"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                only_push_url: true,
+                only_fetch_url: false,
+                ..
+            }
+        ));
+
+        assert!(
+            Cli::try_parse_from(["gemote", "sync", "--only-push-url", "--only-fetch-url"]).is_err()
+        );
+    }
+
+    #[test]
+    fn parse_sync_action_kind_invalid() {
+        assert!(Cli::try_parse_from(["gemote", "sync", "--action-kind", "rename"]).is_err());
+    }
+
+    #[test]
+    fn parse_sync_no_discover() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "-r", "--no-discover"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                no_discover: true,
+                ..
             }
         ));
     }
 
+    #[test]
+    fn parse_no_discover_requires_recursive() {
+        assert!(Cli::try_parse_from(["gemote", "sync", "--no-discover"]).is_err());
+        assert!(Cli::try_parse_from(["gemote", "save", "--no-discover"]).is_err());
+    }
+
     #[test]
     fn parse_save() {
         let cli = Cli::try_parse_from(["gemote", "save"]).unwrap();
@@ -117,11 +548,29 @@ mod tests {
             cli.command,
             Commands::Save {
                 force: false,
-                recursive: false
+                recursive: false,
+                ..
             }
         ));
     }
 
+    #[test]
+    fn parse_save_remote_repeatable() {
+        let cli = Cli::try_parse_from([
+            "gemote", "save", "--remote", "origin", "--remote", "upstream",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Save { remote, .. } => assert_eq!(remote, ["origin", "upstream"]),
+            _ => panic!("expected save"),
+        }
+    }
+
+    #[test]
+    fn parse_save_remote_conflicts_with_force() {
+        assert!(Cli::try_parse_from(["gemote", "save", "--remote", "origin", "-f"]).is_err());
+    }
+
     #[test]
     fn parse_save_force() {
         let cli = Cli::try_parse_from(["gemote", "save", "--force"]).unwrap();
@@ -129,7 +578,8 @@ mod tests {
             cli.command,
             Commands::Save {
                 force: true,
-                recursive: false
+                recursive: false,
+                ..
             }
         ));
     }
@@ -141,7 +591,8 @@ mod tests {
             cli.command,
             Commands::Save {
                 force: true,
-                recursive: false
+                recursive: false,
+                ..
             }
         ));
     }
@@ -153,7 +604,8 @@ mod tests {
             cli.command,
             Commands::Save {
                 force: false,
-                recursive: true
+                recursive: true,
+                ..
             }
         ));
     }
@@ -165,11 +617,87 @@ mod tests {
             cli.command,
             Commands::Save {
                 force: false,
-                recursive: true
+                recursive: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_save_remote_name_template() {
+        let cli =
+            Cli::try_parse_from(["gemote", "save", "--remote-name-template", "{org}"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Save { remote_name_template: Some(t), .. } if t == "{org}"
+        ));
+    }
+
+    #[test]
+    fn parse_undo() {
+        let cli = Cli::try_parse_from(["gemote", "undo", "--dry-run"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Undo {
+                dry_run: true,
+                from_backup: None
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_undo_from_backup() {
+        let cli = Cli::try_parse_from(["gemote", "undo", "--from-backup", "/tmp/snap"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Undo { from_backup: Some(p), .. } if p == Path::new("/tmp/snap")
+        ));
+    }
+
+    #[test]
+    fn parse_bootstrap() {
+        let cli = Cli::try_parse_from([
+            "gemote",
+            "bootstrap",
+            "https://example.com/repo.git",
+            "work/repo",
+            "--dry-run",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Bootstrap { url, dir: Some(d), dry_run: true }
+                if url == "https://example.com/repo.git" && d == Path::new("work/repo")
+        ));
+        assert!(Cli::try_parse_from(["gemote", "bootstrap"]).is_err());
+    }
+
+    #[test]
+    fn parse_inspect() {
+        let cli = Cli::try_parse_from(["gemote", "inspect", "origin"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Inspect { name, format: Format::Text } if name == "origin"
+        ));
+    }
+
+    #[test]
+    fn parse_inspect_json() {
+        let cli = Cli::try_parse_from(["gemote", "inspect", "origin", "--format", "json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Inspect {
+                format: Format::Json,
+                ..
             }
         ));
     }
 
+    #[test]
+    fn parse_inspect_requires_name() {
+        assert!(Cli::try_parse_from(["gemote", "inspect"]).is_err());
+    }
+
     #[test]
     fn parse_global_flags() {
         let cli = Cli::try_parse_from([
@@ -185,21 +713,254 @@ mod tests {
         assert_eq!(cli.repo.unwrap(), PathBuf::from("/tmp/repo"));
     }
 
+    #[test]
+    fn parse_config_search_stop() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--config-search-stop", "/tmp"]).unwrap();
+        assert_eq!(cli.config_search_stop.unwrap(), PathBuf::from("/tmp"));
+    }
+
+    #[test]
+    fn parse_status_exit_zero() {
+        let cli = Cli::try_parse_from(["gemote", "status", "--exit-zero"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Status {
+                exit_zero: true,
+                ..
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["gemote", "status"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Status {
+                exit_zero: false,
+                only_config: false,
+                ..
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["gemote", "status", "--no-repo"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Status {
+                only_config: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_prefix() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--prefix", "pr-"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync { prefix: Some(p), .. } if p == "pr-"
+        ));
+
+        let cli = Cli::try_parse_from(["gemote", "status", "--prefix", "pr-"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Status { prefix: Some(p), .. } if p == "pr-"
+        ));
+    }
+
+    #[test]
+    fn parse_sync_show_unmanaged() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--show-unmanaged"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                show_unmanaged: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_sync_preserve_unknown_remotes() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--preserve-unknown-remotes"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                preserve_unknown_remotes: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_sync_refuse_during_operation() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--refuse-during-operation"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                refuse_during_operation: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_sync_select() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--select"]).unwrap();
+        assert!(matches!(cli.command, Commands::Sync { select: true, .. }));
+        assert!(Cli::try_parse_from(["gemote", "sync", "--select", "--dry-run"]).is_err());
+        assert!(Cli::try_parse_from(["gemote", "sync", "-r", "--select", "-j", "2"]).is_err());
+    }
+
+    #[test]
+    fn parse_sync_remote_name_rewrite() {
+        let cli =
+            Cli::try_parse_from(["gemote", "sync", "--remote-name-rewrite", "old-(.*)=new-$1"])
+                .unwrap();
+        let Commands::Sync {
+            remote_name_rewrite: Some(rule),
+            ..
+        } = cli.command
+        else {
+            panic!("expected a rewrite rule");
+        };
+        assert_eq!(rule.apply("old-fork").as_deref(), Some("new-fork"));
+        assert!(Cli::try_parse_from(["gemote", "sync", "--remote-name-rewrite", "old-*"]).is_err());
+    }
+
+    #[test]
+    fn parse_sync_verbose_requires_dry_run() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--dry-run", "-v"]).unwrap();
+        assert!(matches!(cli.command, Commands::Sync { verbose: true, .. }));
+        assert!(Cli::try_parse_from(["gemote", "sync", "--verbose"]).is_err());
+    }
+
+    #[test]
+    fn parse_sync_ndjson() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "-r", "--ndjson"]).unwrap();
+        assert!(matches!(cli.command, Commands::Sync { ndjson: true, .. }));
+        assert!(Cli::try_parse_from(["gemote", "sync", "--ndjson", "--format", "json"]).is_err());
+    }
+
+    #[test]
+    fn parse_max_removals_per_repo() {
+        let cli =
+            Cli::try_parse_from(["gemote", "sync", "-r", "--max-removals-per-repo", "2"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                max_removals_per_repo: Some(2),
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from(["gemote", "sync", "--max-removals-per-repo", "x"]).is_err());
+    }
+
+    #[test]
+    fn parse_sync_warnings_as_json() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--warnings-as-json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                warnings_as_json: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_sync_report() {
+        let cli = Cli::try_parse_from([
+            "gemote",
+            "sync",
+            "--report-format",
+            "junit",
+            "--report",
+            "out.xml",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                report_format: Some(ReportFormat::Junit),
+                report: Some(p),
+                ..
+            } if p == Path::new("out.xml")
+        ));
+        assert!(Cli::try_parse_from(["gemote", "sync", "--report", "out.xml"]).is_err());
+        assert!(Cli::try_parse_from(["gemote", "sync", "--report-format", "junit"]).is_err());
+    }
+
+    #[test]
+    fn parse_doctor_fix() {
+        let cli = Cli::try_parse_from(["gemote", "doctor", "--fix"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Doctor {
+                fix: true,
+                yes: false
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["gemote", "doctor"]).unwrap();
+        assert!(matches!(cli.command, Commands::Doctor { fix: false, .. }));
+
+        let cli = Cli::try_parse_from(["gemote", "doctor", "--fix", "-y"]).unwrap();
+        assert!(matches!(cli.command, Commands::Doctor { yes: true, .. }));
+        assert!(Cli::try_parse_from(["gemote", "doctor", "--yes"]).is_err());
+    }
+
+    #[test]
+    fn parse_config_root() {
+        let cli = Cli::try_parse_from(["gemote", "sync", "--config-root", "/tmp/team"]).unwrap();
+        assert_eq!(cli.config_root.unwrap(), PathBuf::from("/tmp/team"));
+    }
+
+    #[test]
+    fn parse_config_format() {
+        let cli = Cli::try_parse_from(["gemote", "verify", "--config-format", "json"]).unwrap();
+        assert_eq!(cli.config_format, Some(ConfigFormat::Json));
+
+        let cli = Cli::try_parse_from(["gemote", "sync"]).unwrap();
+        assert_eq!(cli.config_format, None);
+
+        assert!(Cli::try_parse_from(["gemote", "sync", "--config-format", "ini"]).is_err());
+    }
+
     #[test]
     fn parse_completions_bash() {
         let cli = Cli::try_parse_from(["gemote", "completions", "bash"]).unwrap();
         assert!(matches!(
             cli.command,
-            Commands::Completions { shell: Shell::Bash }
+            Commands::Completions {
+                shell: Shell::Bash,
+                ..
+            }
         ));
     }
 
+    #[test]
+    fn parse_completions_install() {
+        let cli =
+            Cli::try_parse_from(["gemote", "completions", "fish", "--install", "--force"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Completions {
+                shell: Shell::Fish,
+                install: true,
+                force: true,
+            }
+        ));
+
+        assert!(Cli::try_parse_from(["gemote", "completions", "fish", "--force"]).is_err());
+    }
+
     #[test]
     fn parse_completions_zsh() {
         let cli = Cli::try_parse_from(["gemote", "completions", "zsh"]).unwrap();
         assert!(matches!(
             cli.command,
-            Commands::Completions { shell: Shell::Zsh }
+            Commands::Completions {
+                shell: Shell::Zsh,
+                ..
+            }
         ));
     }
 
@@ -208,7 +969,10 @@ mod tests {
         let cli = Cli::try_parse_from(["gemote", "completions", "fish"]).unwrap();
         assert!(matches!(
             cli.command,
-            Commands::Completions { shell: Shell::Fish }
+            Commands::Completions {
+                shell: Shell::Fish,
+                ..
+            }
         ));
     }
 
@@ -1,24 +1,195 @@
-use std::collections::BTreeMap;
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize, Serializer};
 
 use crate::error::GemoteError;
+use crate::git;
+use crate::naming;
+use crate::warnings::{WarningKind, Warnings};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GemoteConfig {
+    /// JSON Schema reference that editors add for validation. gemote
+    /// doesn't use it, but keeps it when writing the config back.
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
     #[serde(default)]
     pub settings: Settings,
     #[serde(default)]
     pub remotes: BTreeMap<String, RemoteConfig>,
+    /// Remotes given as a `[[remote]]` array of tables. Merged into `remotes`
+    /// by `load_config`, so this is always empty after loading.
+    #[serde(default, rename = "remote", skip_serializing)]
+    pub remote_list: Vec<NamedRemoteConfig>,
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub submodules: BTreeMap<String, GemoteConfig>,
 }
 
+impl GemoteConfig {
+    /// Capture `repo`'s remotes as they are, with default settings and no
+    /// submodule sections. `from_repo_recursive` calls this once per repo.
+    pub fn from_repo(repo: &git2::Repository) -> Result<Self, GemoteError> {
+        let remotes = git::list_remotes(repo)?
+            .into_iter()
+            .map(|(name, info)| (name, info.to_config()))
+            .collect();
+        // A base with several prefixes can't be expressed in the config;
+        // keep the first
+        let insteadof = git::list_insteadof(repo)?
+            .into_iter()
+            .filter_map(|(base, prefixes)| Some((base, prefixes.into_iter().next()?)))
+            .collect();
+        Ok(GemoteConfig {
+            settings: Settings {
+                insteadof,
+                ..Default::default()
+            },
+            remotes,
+            ..Default::default()
+        })
+    }
+
+    /// Capture `repo` and every sub-repo `git::collect_all_repos` finds
+    /// under `root`, each nested under its parent's `submodules` by path.
+    /// Sub-repos that can't be walked are added to `warnings`.
+    pub fn from_repo_recursive(
+        repo: &git2::Repository,
+        root: &Path,
+        discovery: git::Discovery,
+        warnings: &mut Warnings,
+    ) -> Result<Self, GemoteError> {
+        let mut guard = git::RecursionGuard::default();
+        Self::capture_tree(repo, root, discovery, &mut guard, warnings)
+    }
+
+    fn capture_tree(
+        repo: &git2::Repository,
+        root: &Path,
+        discovery: git::Discovery,
+        guard: &mut git::RecursionGuard,
+        warnings: &mut Warnings,
+    ) -> Result<Self, GemoteError> {
+        let mut cfg = Self::from_repo(repo)?;
+        for sub in git::collect_all_repos(repo, root, discovery, guard, warnings)? {
+            let sub_cfg = match sub.repo.workdir() {
+                Some(sub_root) => {
+                    Self::capture_tree(&sub.repo, sub_root, discovery, guard, warnings)
+                }
+                None => Self::from_repo(&sub.repo),
+            }
+            .map_err(|e| GemoteError::InSubmodule {
+                path: sub.path.clone(),
+                source: Box::new(e),
+            })?;
+            cfg.submodules.insert(sub.path, sub_cfg);
+        }
+        Ok(cfg)
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Settings {
-    #[serde(default)]
-    pub extra_remotes: ExtraRemotes,
+    /// What to do with local remotes missing from the config. Unset in a
+    /// submodule section means `submodule_defaults.extra_remotes`, and
+    /// `ignore` after that.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_remotes: Option<ExtraRemotes>,
+    /// Treat `repo` and `repo.git` as different URLs when comparing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict_suffix: bool,
+    /// Ignore case in the path part of URLs when comparing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub url_path_case_insensitive: bool,
+    /// Whether the order of a remote's URLs matters when comparing
+    /// (default: true, since git fetches from the first)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_order_significant: Option<bool>,
+    /// Rename a local remote whose URLs match a new config entry instead
+    /// of removing it and adding the new one (only with
+    /// `extra_remotes = "remove"`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rename_detection: bool,
+    /// Refuse to change remotes while a merge, rebase, cherry-pick or
+    /// bisect is in progress, instead of warning
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub refuse_during_operation: bool,
+    /// Set to false to stop warning about submodules that aren't checked out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warn_uninitialized_submodules: Option<bool>,
+    /// URL prefix rewrites (prefix -> replacement) for fetch URLs
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rewrites: BTreeMap<String, String>,
+    /// URL prefix rewrites for push URLs, tried before `rewrites`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub push_rewrites: BTreeMap<String, String>,
+    /// `url.<base>.insteadOf` rules (base -> prefix it replaces) that sync
+    /// writes to the repo's git config
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub insteadof: BTreeMap<String, String>,
+    /// How recursive mode looks for nested repos
+    #[serde(default, skip_serializing_if = "DiscoverySettings::is_empty")]
+    pub discovery: DiscoverySettings,
+    /// Options applied to every remote that doesn't set its own
+    #[serde(default, skip_serializing_if = "RemoteDefaults::is_empty")]
+    pub remote_defaults: RemoteDefaults,
+    /// Settings for submodule sections that don't set their own
+    #[serde(default, skip_serializing_if = "SubmoduleDefaults::is_empty")]
+    pub submodule_defaults: SubmoduleDefaults,
+}
+
+impl Settings {
+    /// `extra_remotes`, or the default mode when it's unset.
+    pub fn extra_remotes(&self) -> ExtraRemotes {
+        self.extra_remotes.clone().unwrap_or_default()
+    }
+}
+
+/// Values for `[settings.discovery]`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiscoverySettings {
+    /// Skip directories ignored by the repo's `.gitignore` when walking the
+    /// working tree (default: false)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub respect_gitignore: bool,
+}
+
+impl DiscoverySettings {
+    pub fn is_empty(&self) -> bool {
+        *self == DiscoverySettings::default()
+    }
+}
+
+/// Values for `[settings.remote_defaults]`, used by sync for each remote
+/// that leaves an option unset. Submodule sections use their own.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RemoteDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prune: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tagopt: Option<String>,
+}
+
+impl RemoteDefaults {
+    pub fn is_empty(&self) -> bool {
+        *self == RemoteDefaults::default()
+    }
+}
+
+/// Values for `[settings.submodule_defaults]`, used by recursive sync for
+/// each submodule section that leaves a setting unset. Nested sections
+/// inherit them unless the section they are in sets its own.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubmoduleDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_remotes: Option<ExtraRemotes>,
+}
+
+impl SubmoduleDefaults {
+    pub fn is_empty(&self) -> bool {
+        *self == SubmoduleDefaults::default()
+    }
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -30,23 +201,498 @@ pub enum ExtraRemotes {
     Remove,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct RemoteConfig {
     pub url: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub push_url: Option<String>,
+    /// More `remote.<name>.url` values after `url`; git pushes to all of them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_urls: Vec<String>,
+    /// More `remote.<name>.pushurl` values after `push_url`; git pushes to
+    /// all of them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_push_urls: Vec<String>,
+    /// `remote.<name>.prune`; left alone when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prune: Option<bool>,
+    /// `remote.<name>.tagopt`, e.g. `--no-tags`; left alone when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tagopt: Option<String>,
+    /// Where this remote is listed in output (lower first); display only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedRemoteConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub remote: RemoteConfig,
+}
+
+/// Walk upward from `start` looking for a `.gemote` file, never searching
+/// above `stop`. `stop` should be `start` or one of its ancestors.
+pub fn find_config(start: &Path, stop: &Path) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        let candidate = dir.join(".gemote");
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if dir == stop {
+            break;
+        }
+    }
+    None
+}
+
+/// File format of a config file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConfigFormat {
+    #[default]
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// The format to use for `path`: `forced` if given, otherwise JSON for a
+    /// `.json` extension and TOML for anything else (including `.gemote`).
+    pub fn for_path(path: &Path, forced: Option<ConfigFormat>) -> Self {
+        forced.unwrap_or_else(|| match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        })
+    }
 }
 
-pub fn load_config(path: &Path) -> Result<GemoteConfig, GemoteError> {
+/// Load the config at `path`, parsed as `format` if given and otherwise as
+/// `ConfigFormat::for_path` guesses.
+pub fn load_config(path: &Path, format: Option<ConfigFormat>) -> Result<GemoteConfig, GemoteError> {
     if !path.exists() {
         return Err(GemoteError::ConfigNotFound(path.to_path_buf()));
     }
     let contents = std::fs::read_to_string(path)?;
-    toml::from_str(&contents).map_err(GemoteError::ConfigParse)
+    let mut cfg: GemoteConfig = match ConfigFormat::for_path(path, format) {
+        ConfigFormat::Toml => toml::from_str(&contents).map_err(GemoteError::ConfigParse)?,
+        ConfigFormat::Json => {
+            serde_json::from_str(&contents).map_err(GemoteError::ConfigParseJson)?
+        }
+    };
+    merge_remote_list(&mut cfg)?;
+    validate_remote_names(&cfg)?;
+    Ok(cfg)
+}
+
+/// Fix up and report config mistakes that load fine but wouldn't do what
+/// was meant. Submodule keys written with backslashes (as on Windows) are
+/// rewritten with forward slashes so they match discovered paths, and remote
+/// names that differ only by case are reported, since git would create both.
+pub fn lint_config(cfg: &mut GemoteConfig) -> Warnings {
+    let mut warnings = Warnings::default();
+    lint_section(cfg, "", &mut warnings);
+    warnings
+}
+
+fn lint_section(cfg: &mut GemoteConfig, path: &str, warnings: &mut Warnings) {
+    let mut by_lowercase: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for name in cfg.remotes.keys() {
+        by_lowercase
+            .entry(name.to_lowercase())
+            .or_default()
+            .push(name);
+    }
+    for names in by_lowercase.values().filter(|names| names.len() > 1) {
+        let list = names
+            .iter()
+            .map(|name| format!("'{name}'"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        warnings.push(
+            WarningKind::RemoteNameCase,
+            path,
+            format!("remotes {list} differ only by case; git will create each of them"),
+        );
+    }
+
+    let keys: Vec<String> = cfg
+        .submodules
+        .keys()
+        .filter(|key| key.contains('\\'))
+        .cloned()
+        .collect();
+    for key in keys {
+        let fixed = submodule_key(&key);
+        if cfg.submodules.contains_key(&fixed) {
+            warnings.push(
+                WarningKind::SubmodulePath,
+                path,
+                format!("submodule section '{key}' duplicates '{fixed}' and is ignored"),
+            );
+            continue;
+        }
+        let section = cfg.submodules.remove(&key).unwrap_or_default();
+        cfg.submodules.insert(fixed.clone(), section);
+        warnings.push(
+            WarningKind::SubmodulePath,
+            path,
+            format!("submodule section '{key}' uses backslashes; reading it as '{fixed}'"),
+        );
+    }
+    for (key, sub_cfg) in &mut cfg.submodules {
+        let sub_path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{path}/{key}")
+        };
+        lint_section(sub_cfg, &sub_path, warnings);
+    }
+}
+
+/// A submodule section key as `lint_config` reads it, with backslashes
+/// turned into forward slashes.
+fn submodule_key(key: &str) -> String {
+    key.replace('\\', "/")
+}
+
+/// Remove the `[submodules."<key>"]` sections named in `keys` from the
+/// config file at `path`, leaving everything else as it was. `keys` are
+/// spelled as `lint_config` reads them, so a section written as `libs\\x`
+/// goes for `libs/x`. A TOML file is edited in place, keeping its comments
+/// and layout; a JSON file is rewritten.
+pub fn remove_submodule_sections(
+    path: &Path,
+    format: Option<ConfigFormat>,
+    keys: &[String],
+) -> Result<(), GemoteError> {
+    let contents = std::fs::read_to_string(path)?;
+    let orphaned = |key: &str| keys.contains(&submodule_key(key));
+    let content = match ConfigFormat::for_path(path, format) {
+        ConfigFormat::Toml => {
+            let mut doc: toml_edit::DocumentMut =
+                contents.parse().map_err(GemoteError::ConfigEdit)?;
+            if let Some(subs) = doc
+                .get_mut("submodules")
+                .and_then(|item| item.as_table_like_mut())
+            {
+                let gone: Vec<String> = subs
+                    .iter()
+                    .map(|(key, _)| key.to_string())
+                    .filter(|key| orphaned(key))
+                    .collect();
+                for key in gone {
+                    subs.remove(&key);
+                }
+                if subs.is_empty() {
+                    doc.remove("submodules");
+                }
+            }
+            doc.to_string()
+        }
+        ConfigFormat::Json => {
+            let mut doc: serde_json::Value =
+                serde_json::from_str(&contents).map_err(GemoteError::ConfigParseJson)?;
+            if let Some(doc) = doc.as_object_mut()
+                && let Some(subs) = doc.get_mut("submodules").and_then(|v| v.as_object_mut())
+            {
+                subs.retain(|key, _| !orphaned(key));
+                if subs.is_empty() {
+                    doc.remove("submodules");
+                }
+            }
+            let body =
+                serde_json::to_string_pretty(&doc).map_err(GemoteError::ConfigSerializeJson)?;
+            format!("{body}\n")
+        }
+    };
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// Write `remotes` into the config file at `path`, replacing each one's
+/// entry where it is, whether a `[remotes.<name>]` table or a `[[remote]]`
+/// entry, and adding a `[remotes.<name>]` table for the others. Everything
+/// else in a TOML file, comments included, stays as it was; a JSON file is
+/// rewritten.
+pub fn update_remote_entries(
+    path: &Path,
+    format: Option<ConfigFormat>,
+    remotes: &BTreeMap<String, RemoteConfig>,
+) -> Result<(), GemoteError> {
+    let contents = std::fs::read_to_string(path)?;
+    let content = match ConfigFormat::for_path(path, format) {
+        ConfigFormat::Toml => {
+            let mut doc: toml_edit::DocumentMut =
+                contents.parse().map_err(GemoteError::ConfigEdit)?;
+            for (name, remote) in remotes {
+                update_remote_table(&mut doc, name, remote_table(remote)?);
+            }
+            doc.to_string()
+        }
+        ConfigFormat::Json => {
+            let mut doc: serde_json::Value =
+                serde_json::from_str(&contents).map_err(GemoteError::ConfigParseJson)?;
+            for (name, remote) in remotes {
+                let mut value =
+                    serde_json::to_value(remote).map_err(GemoteError::ConfigSerializeJson)?;
+                let listed = doc
+                    .get_mut("remote")
+                    .and_then(|list| list.as_array_mut())
+                    .and_then(|list| list.iter_mut().find(|entry| entry["name"] == **name));
+                match listed {
+                    Some(entry) => {
+                        value["name"] = name.as_str().into();
+                        *entry = value;
+                    }
+                    None => doc["remotes"][name] = value,
+                }
+            }
+            let body =
+                serde_json::to_string_pretty(&doc).map_err(GemoteError::ConfigSerializeJson)?;
+            format!("{body}\n")
+        }
+    };
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// `remote`'s fields as a TOML table, in the order `RemoteConfig` declares
+/// them.
+fn remote_table(remote: &RemoteConfig) -> Result<toml_edit::Table, GemoteError> {
+    let fields = toml::to_string(remote).map_err(GemoteError::ConfigSerialize)?;
+    let fields: toml_edit::DocumentMut = fields.parse().map_err(GemoteError::ConfigEdit)?;
+    Ok(fields.as_table().iter().collect())
+}
+
+/// Replace the fields of remote `name` in `doc` with `fields`, keeping the
+/// entry's place and the comments around it.
+fn update_remote_table(doc: &mut toml_edit::DocumentMut, name: &str, fields: toml_edit::Table) {
+    let listed = doc
+        .get_mut("remote")
+        .and_then(|item| item.as_array_of_tables_mut())
+        .and_then(|list| {
+            list.iter_mut()
+                .find(|entry| entry.get("name").and_then(|n| n.as_str()) == Some(name))
+        });
+    if let Some(entry) = listed {
+        entry.retain(|key, _| key == "name");
+        entry.extend(fields);
+        return;
+    }
+    let remotes = doc.entry("remotes").or_insert_with(|| {
+        let mut table = toml_edit::Table::new();
+        table.set_implicit(true);
+        toml_edit::Item::Table(table)
+    });
+    match remotes.get_mut(name).and_then(|item| item.as_table_mut()) {
+        Some(table) => {
+            table.clear();
+            table.extend(fields);
+        }
+        None => remotes[name] = toml_edit::Item::Table(fields),
+    }
+}
+
+/// Placeholder in remote URLs that is replaced by the name of the repo
+/// being synced.
+pub const REPO_NAME_VAR: &str = "{repo_name}";
+
+/// `cfg`'s settings and remotes with `{repo_name}` in their URLs replaced
+/// by `repo_name`. Submodule sections are left out, since each one is
+/// expanded for its own repo.
+pub fn expand_repo_name(
+    cfg: &GemoteConfig,
+    repo_name: Option<&str>,
+) -> Result<GemoteConfig, GemoteError> {
+    let expand = |url: &str| match repo_name {
+        _ if !url.contains(REPO_NAME_VAR) => Ok(url.to_string()),
+        Some(name) => Ok(url.replace(REPO_NAME_VAR, name)),
+        None => Err(GemoteError::RepoNameUnknown(url.to_string())),
+    };
+    let mut remotes = BTreeMap::new();
+    for (name, rc) in &cfg.remotes {
+        let remote = RemoteConfig {
+            url: expand(&rc.url)?,
+            push_url: rc.push_url.as_deref().map(expand).transpose()?,
+            extra_urls: rc
+                .extra_urls
+                .iter()
+                .map(|u| expand(u))
+                .collect::<Result<_, _>>()?,
+            extra_push_urls: rc
+                .extra_push_urls
+                .iter()
+                .map(|u| expand(u))
+                .collect::<Result<_, _>>()?,
+            ..rc.clone()
+        };
+        remotes.insert(name.clone(), remote);
+    }
+    Ok(GemoteConfig {
+        settings: cfg.settings.clone(),
+        remotes,
+        ..Default::default()
+    })
+}
+
+/// Read a plain list of URLs, one per line, as a config with one remote per
+/// URL named by `template`. Blank lines and `#` comments are skipped.
+pub fn load_url_list(path: &Path, template: &str) -> Result<GemoteConfig, GemoteError> {
+    if !path.exists() {
+        return Err(GemoteError::ConfigNotFound(path.to_path_buf()));
+    }
+    parse_url_list(&std::fs::read_to_string(path)?, template)
+}
+
+pub fn parse_url_list(contents: &str, template: &str) -> Result<GemoteConfig, GemoteError> {
+    let mut cfg = GemoteConfig::default();
+    for line in contents.lines() {
+        let url = line.trim();
+        if url.is_empty() || url.starts_with('#') {
+            continue;
+        }
+        let taken: BTreeSet<String> = cfg.remotes.keys().cloned().collect();
+        let name = naming::unique_name(&naming::render_name_template(template, url)?, &taken);
+        cfg.remotes.insert(
+            name,
+            RemoteConfig {
+                url: url.to_string(),
+                ..Default::default()
+            },
+        );
+    }
+    Ok(cfg)
+}
+
+/// Read `old_url,new_url` pairs, one per line, for a one-off URL
+/// migration. Blank lines and `#` comments are skipped.
+pub fn load_url_map(path: &Path) -> Result<BTreeMap<String, String>, GemoteError> {
+    if !path.exists() {
+        return Err(GemoteError::ConfigNotFound(path.to_path_buf()));
+    }
+    parse_url_map(&std::fs::read_to_string(path)?)
+}
+
+pub fn parse_url_map(contents: &str) -> Result<BTreeMap<String, String>, GemoteError> {
+    let mut map = BTreeMap::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |reason| GemoteError::UrlMap {
+            line: index + 1,
+            reason,
+        };
+        let (old, new) = line
+            .split_once(',')
+            .ok_or_else(|| error("expected `old_url,new_url`"))?;
+        let (old, new) = (old.trim(), new.trim());
+        if old.is_empty() || new.is_empty() || new.contains(',') {
+            return Err(error("expected `old_url,new_url`"));
+        }
+        if map.insert(old.to_string(), new.to_string()).is_some() {
+            return Err(error("old URL is listed more than once"));
+        }
+    }
+    Ok(map)
+}
+
+/// Fold `[[remote]]` entries into the `remotes` map, recursing into submodule
+/// sections. A name may only be defined once across both forms.
+fn merge_remote_list(cfg: &mut GemoteConfig) -> Result<(), GemoteError> {
+    for entry in std::mem::take(&mut cfg.remote_list) {
+        if cfg.remotes.contains_key(&entry.name) {
+            return Err(GemoteError::DuplicateRemote(entry.name));
+        }
+        cfg.remotes.insert(entry.name, entry.remote);
+    }
+    for sub_cfg in cfg.submodules.values_mut() {
+        merge_remote_list(sub_cfg)?;
+    }
+    Ok(())
+}
+
+/// Special refs git keeps at the top of `.git`; a remote with one of these
+/// names makes `refs/remotes/<name>/...` ambiguous with them.
+const RESERVED_REMOTE_NAMES: &[&str] = &[
+    "HEAD",
+    "FETCH_HEAD",
+    "ORIG_HEAD",
+    "MERGE_HEAD",
+    "CHERRY_PICK_HEAD",
+    "REVERT_HEAD",
+    "BISECT_HEAD",
+    "AUTO_MERGE",
+];
+
+/// Why `name` is unsafe as a remote name even though git accepts it.
+pub fn reserved_name_reason(name: &str) -> Option<&'static str> {
+    if RESERVED_REMOTE_NAMES.contains(&name) {
+        Some("is reserved by git for a special ref")
+    } else if name.starts_with("refs/") {
+        Some("looks like a ref path")
+    } else {
+        None
+    }
+}
+
+/// Reject remote names git itself would refuse, or that it accepts but
+/// would confuse with its own refs, so a bad name fails at load instead of
+/// halfway through a sync.
+fn validate_remote_names(cfg: &GemoteConfig) -> Result<(), GemoteError> {
+    for name in cfg.remotes.keys() {
+        if !git2::Remote::is_valid_name(name) {
+            return Err(GemoteError::InvalidRemoteName(name.clone()));
+        }
+        if let Some(reason) = reserved_name_reason(name) {
+            return Err(GemoteError::ReservedRemoteName {
+                name: name.clone(),
+                reason,
+            });
+        }
+    }
+    for sub_cfg in cfg.submodules.values() {
+        validate_remote_names(sub_cfg)?;
+    }
+    Ok(())
+}
+
+/// Options for `serialize_config_with_options`.
+#[derive(Debug, Clone, Copy)]
+pub struct SerializeOptions {
+    /// Start with the `# Gemote configuration file` comment block (TOML only)
+    pub header: bool,
+    pub format: ConfigFormat,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        SerializeOptions {
+            header: true,
+            format: ConfigFormat::Toml,
+        }
+    }
 }
 
 pub fn serialize_config(config: &GemoteConfig) -> Result<String, GemoteError> {
-    let body = toml::to_string_pretty(config).map_err(GemoteError::ConfigSerialize)?;
+    serialize_config_with_options(config, SerializeOptions::default())
+}
+
+pub fn serialize_config_with_options(
+    config: &GemoteConfig,
+    options: SerializeOptions,
+) -> Result<String, GemoteError> {
+    if options.format == ConfigFormat::Json {
+        let body =
+            serde_json::to_string_pretty(config).map_err(GemoteError::ConfigSerializeJson)?;
+        return Ok(format!("{body}\n"));
+    }
+    let mut sections = Vec::new();
+    push_toml_sections(config, &[], &mut sections)?;
+    let body = sections.join("\n");
+    if !options.header {
+        return Ok(body);
+    }
     Ok(format!(
         "\
 # Gemote configuration file
@@ -59,6 +705,70 @@ pub fn serialize_config(config: &GemoteConfig) -> Result<String, GemoteError> {
     ))
 }
 
+/// Render `config` as one TOML snippet per table, in a fixed order so
+/// configs edited by hand and by gemote don't churn: `$schema` if set,
+/// `[settings]` (left out when empty), then each remote by name, then each
+/// submodule section laid out the same way, by path. `path` is the key path
+/// of `config`.
+fn push_toml_sections(
+    config: &GemoteConfig,
+    path: &[&str],
+    sections: &mut Vec<String>,
+) -> Result<(), GemoteError> {
+    if let Some(schema) = &config.schema {
+        sections.push(toml_section(path, &["$schema"], schema)?);
+    }
+    let settings = toml::Table::try_from(&config.settings).map_err(GemoteError::ConfigSerialize)?;
+    if !settings.is_empty() {
+        sections.push(toml_section(path, &["settings"], &config.settings)?);
+    }
+    for (name, remote) in &config.remotes {
+        sections.push(toml_section(path, &["remotes", name], remote)?);
+    }
+    for (key, sub_cfg) in &config.submodules {
+        let sub_path: Vec<&str> = path.iter().copied().chain(["submodules", key]).collect();
+        push_toml_sections(sub_cfg, &sub_path, sections)?;
+    }
+    Ok(())
+}
+
+/// `value` as a TOML table under the keys `path` then `keys`.
+fn toml_section(
+    path: &[&str],
+    keys: &[&str],
+    value: &impl Serialize,
+) -> Result<String, GemoteError> {
+    let keys: Vec<&str> = path.iter().chain(keys).copied().collect();
+    toml::to_string_pretty(&Nested { keys: &keys, value }).map_err(GemoteError::ConfigSerialize)
+}
+
+/// `value` nested under one single-key table per key. Serialized directly
+/// rather than through a `toml::Table`, which would sort the fields, so
+/// they come out in the order the struct declares them.
+struct Nested<'a, T> {
+    keys: &'a [&'a str],
+    value: &'a T,
+}
+
+impl<T: Serialize> Serialize for Nested<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.keys.split_first() {
+            Some((key, keys)) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(
+                    key,
+                    &Nested {
+                        keys,
+                        value: self.value,
+                    },
+                )?;
+                map.end()
+            }
+            None => self.value.serialize(serializer),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -78,11 +788,12 @@ mod tests {
             ExtraRemotes::Remove,
         ] {
             let settings = Settings {
-                extra_remotes: variant.clone(),
+                extra_remotes: Some(variant.clone()),
+                ..Default::default()
             };
             let serialized = toml::to_string(&settings).unwrap();
             let deserialized: Settings = toml::from_str(&serialized).unwrap();
-            assert_eq!(deserialized.extra_remotes, variant);
+            assert_eq!(deserialized.extra_remotes, Some(variant));
         }
     }
 
@@ -102,8 +813,8 @@ push_url = "https://github.com/org/repo.git"
         )
         .unwrap();
 
-        let cfg = load_config(f.path()).unwrap();
-        assert_eq!(cfg.settings.extra_remotes, ExtraRemotes::Warn);
+        let cfg = load_config(f.path(), None).unwrap();
+        assert_eq!(cfg.settings.extra_remotes(), ExtraRemotes::Warn);
         assert_eq!(cfg.remotes.len(), 1);
         let origin = &cfg.remotes["origin"];
         assert_eq!(origin.url, "git@github.com:org/repo.git");
@@ -114,69 +825,1104 @@ push_url = "https://github.com/org/repo.git"
     }
 
     #[test]
-    fn load_config_file_not_found() {
-        let result = load_config(Path::new("/nonexistent/.gemote"));
-        assert!(matches!(result, Err(GemoteError::ConfigNotFound(_))));
+    fn find_config_in_start_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join(".gemote"), "").unwrap();
+        let found = find_config(dir.path(), dir.path()).unwrap();
+        assert_eq!(found, dir.path().join(".gemote"));
     }
 
     #[test]
-    fn load_config_invalid_toml() {
+    fn find_config_stops_at_boundary() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join(".gemote"), "").unwrap();
+        let start = dir.path().join("repo");
+        std::fs::create_dir(&start).unwrap();
+
+        assert!(find_config(&start, &start).is_none());
+        assert_eq!(
+            find_config(&start, dir.path()).unwrap(),
+            dir.path().join(".gemote")
+        );
+    }
+
+    #[test]
+    fn load_config_strict_suffix() {
         let mut f = tempfile::NamedTempFile::new().unwrap();
-        write!(f, "[remotes\norigin = {{ url = }}").unwrap();
+        write!(
+            f,
+            r#"
+[settings]
+strict_suffix = true
+"#
+        )
+        .unwrap();
 
-        let result = load_config(f.path());
-        assert!(matches!(result, Err(GemoteError::ConfigParse(_))));
+        let cfg = load_config(f.path(), None).unwrap();
+        assert!(cfg.settings.strict_suffix);
     }
 
     #[test]
-    fn load_config_minimal() {
+    fn load_config_rewrites() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[settings.rewrites]
+"https://github.com/" = "https://mirror.example.com/"
+
+[settings.push_rewrites]
+"https://github.com/" = "git@github.com:"
+"#
+        )
+        .unwrap();
+
+        let cfg = load_config(f.path(), None).unwrap();
+        assert_eq!(
+            cfg.settings.rewrites["https://github.com/"],
+            "https://mirror.example.com/"
+        );
+        assert_eq!(
+            cfg.settings.push_rewrites["https://github.com/"],
+            "git@github.com:"
+        );
+    }
+
+    #[test]
+    fn load_config_leaves_remote_defaults_out_of_remotes() {
         let mut f = tempfile::NamedTempFile::new().unwrap();
         write!(
             f,
             r#"
+[settings.remote_defaults]
+prune = true
+tagopt = "--no-tags"
+
 [remotes.origin]
 url = "https://example.com/repo.git"
+
+[remotes.upstream]
+url = "https://example.com/upstream.git"
+prune = false
+
+[[remote]]
+name = "mirror"
+url = "https://example.com/mirror.git"
 "#
         )
         .unwrap();
 
-        let cfg = load_config(f.path()).unwrap();
-        assert_eq!(cfg.settings.extra_remotes, ExtraRemotes::Ignore);
-        assert_eq!(cfg.remotes["origin"].url, "https://example.com/repo.git");
-        assert!(cfg.remotes["origin"].push_url.is_none());
+        // Sync applies the defaults, so saving the config again doesn't copy
+        // them into each remote
+        let cfg = load_config(f.path(), None).unwrap();
+        assert_eq!(cfg.settings.remote_defaults.prune, Some(true));
+        assert!(cfg.remotes["origin"].prune.is_none());
+        assert!(cfg.remotes["origin"].tagopt.is_none());
+        assert_eq!(cfg.remotes["upstream"].prune, Some(false));
+        assert!(cfg.remotes["mirror"].prune.is_none());
     }
 
     #[test]
-    fn load_config_multiple_remotes() {
+    fn load_config_without_remote_defaults_leaves_options_unset() {
         let mut f = tempfile::NamedTempFile::new().unwrap();
         write!(
             f,
             r#"
 [remotes.origin]
-url = "https://github.com/a.git"
+url = "https://example.com/repo.git"
+"#
+        )
+        .unwrap();
 
-[remotes.upstream]
-url = "https://github.com/b.git"
+        let cfg = load_config(f.path(), None).unwrap();
+        assert!(cfg.remotes["origin"].prune.is_none());
+        assert!(cfg.remotes["origin"].tagopt.is_none());
+    }
 
-[remotes.mirror]
-url = "https://gitlab.com/c.git"
+    #[test]
+    fn load_config_remote_defaults_per_submodule() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[settings.remote_defaults]
+prune = true
+
+[submodules."libs/core".settings.remote_defaults]
+tagopt = "--no-tags"
+
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
 "#
         )
         .unwrap();
 
-        let cfg = load_config(f.path()).unwrap();
-        assert_eq!(cfg.remotes.len(), 3);
-        assert!(cfg.remotes.contains_key("origin"));
-        assert!(cfg.remotes.contains_key("upstream"));
-        assert!(cfg.remotes.contains_key("mirror"));
+        let cfg = load_config(f.path(), None).unwrap();
+        let core = &cfg.submodules["libs/core"];
+        assert!(core.remotes["origin"].tagopt.is_none());
+        assert_eq!(
+            core.settings.remote_defaults.tagopt.as_deref(),
+            Some("--no-tags")
+        );
     }
 
     #[test]
-    fn serialize_config_empty() {
-        let cfg = GemoteConfig::default();
-        let output = serialize_config(&cfg).unwrap();
-        // Should be valid TOML that round-trips
-        let _: GemoteConfig = toml::from_str(&output).unwrap();
+    fn load_config_url_path_case_insensitive() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[settings]
+url_path_case_insensitive = true
+"#
+        )
+        .unwrap();
+
+        let cfg = load_config(f.path(), None).unwrap();
+        assert!(cfg.settings.url_path_case_insensitive);
+    }
+
+    #[test]
+    fn load_config_warn_uninitialized_submodules() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[settings]
+warn_uninitialized_submodules = false
+"#
+        )
+        .unwrap();
+
+        let cfg = load_config(f.path(), None).unwrap();
+        assert_eq!(cfg.settings.warn_uninitialized_submodules, Some(false));
+        assert!(
+            GemoteConfig::default()
+                .settings
+                .warn_uninitialized_submodules
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn serialize_omits_default_strict_suffix() {
+        let output = serialize_config(&GemoteConfig::default()).unwrap();
+        assert!(!output.contains("strict_suffix"));
+    }
+
+    #[test]
+    fn load_config_file_not_found() {
+        let result = load_config(Path::new("/nonexistent/.gemote"), None);
+        assert!(matches!(result, Err(GemoteError::ConfigNotFound(_))));
+    }
+
+    #[test]
+    fn expand_repo_name_in_urls() {
+        let cfg: GemoteConfig = toml::from_str(
+            r#"
+[remotes.origin]
+url = "git@github.com:org/{repo_name}.git"
+push_url = "git@github.com:me/{repo_name}.git"
+extra_urls = ["https://mirror.com/{repo_name}"]
+
+[remotes.plain]
+url = "https://example.com/fixed.git"
+"#,
+        )
+        .unwrap();
+
+        let expanded = expand_repo_name(&cfg, Some("tool")).unwrap();
+        let origin = &expanded.remotes["origin"];
+        assert_eq!(origin.url, "git@github.com:org/tool.git");
+        assert_eq!(
+            origin.push_url.as_deref(),
+            Some("git@github.com:me/tool.git")
+        );
+        assert_eq!(origin.extra_urls, ["https://mirror.com/tool"]);
+        assert_eq!(
+            expanded.remotes["plain"].url,
+            "https://example.com/fixed.git"
+        );
+
+        assert!(matches!(
+            expand_repo_name(&cfg, None),
+            Err(GemoteError::RepoNameUnknown(_))
+        ));
+    }
+
+    #[test]
+    fn parse_url_list_names_from_template() {
+        let cfg = parse_url_list(
+            "# mirrors\n\
+             https://github.com/alice/tool.git\n\
+             \n\
+             git@gitlab.com:bob/tool.git\n\
+             https://github.com/alice/lib.git\n",
+            "{org}",
+        )
+        .unwrap();
+
+        let names: Vec<&str> = cfg.remotes.keys().map(String::as_str).collect();
+        assert_eq!(names, ["alice", "alice-2", "bob"]);
+        assert_eq!(
+            cfg.remotes["alice"].url,
+            "https://github.com/alice/tool.git"
+        );
+        assert_eq!(
+            cfg.remotes["alice-2"].url,
+            "https://github.com/alice/lib.git"
+        );
+        assert_eq!(cfg.remotes["bob"].url, "git@gitlab.com:bob/tool.git");
+    }
+
+    #[test]
+    fn parse_url_list_bad_url() {
+        let result = parse_url_list("not-a-url\n", "{org}");
+        assert!(matches!(result, Err(GemoteError::NameTemplate { .. })));
+    }
+
+    #[test]
+    fn parse_url_map_pairs() {
+        let map = parse_url_map(
+            "# old host -> new host\n\
+             https://old.example.com/a.git, https://new.example.com/a.git\n\
+             \n\
+             git@old.example.com:b.git,git@new.example.com:b.git\n",
+        )
+        .unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(
+            map["https://old.example.com/a.git"],
+            "https://new.example.com/a.git"
+        );
+        assert_eq!(
+            map["git@old.example.com:b.git"],
+            "git@new.example.com:b.git"
+        );
+    }
+
+    #[test]
+    fn parse_url_map_bad_lines() {
+        for contents in ["just-a-url\n", "a,\n", "\na,b,c\n", "a,b\na,c\n"] {
+            let result = parse_url_map(contents);
+            assert!(
+                matches!(result, Err(GemoteError::UrlMap { .. })),
+                "{contents:?}"
+            );
+        }
+        let err = parse_url_map("a,b\na,c\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn serialize_config_stable_layout() {
+        let cfg: GemoteConfig = toml::from_str(
+            r#"
+[submodules."libs/b".remotes.origin]
+url = "https://example.com/b.git"
+
+[remotes.upstream]
+prune = true
+push_url = "git@example.com:up.git"
+url = "https://example.com/up.git"
+
+[submodules."libs/a".settings]
+extra_remotes = "remove"
+
+[submodules."libs/a".remotes.origin]
+url = "https://example.com/a.git"
+
+[settings]
+extra_remotes = "warn"
+
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+        )
+        .unwrap();
+        let output = serialize_config_with_options(
+            &cfg,
+            SerializeOptions {
+                header: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Fields come out in the order `RemoteConfig` declares them, `url` first
+        assert_eq!(
+            output,
+            r#"[settings]
+extra_remotes = "warn"
+
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[remotes.upstream]
+url = "https://example.com/up.git"
+push_url = "git@example.com:up.git"
+prune = true
+
+[submodules."libs/a".settings]
+extra_remotes = "remove"
+
+[submodules."libs/a".remotes.origin]
+url = "https://example.com/a.git"
+
+[submodules."libs/b".remotes.origin]
+url = "https://example.com/b.git"
+"#
+        );
+        // Serializing again gives the same text
+        let reparsed: GemoteConfig = toml::from_str(&output).unwrap();
+        assert_eq!(
+            serialize_config_with_options(
+                &reparsed,
+                SerializeOptions {
+                    header: false,
+                    ..Default::default()
+                },
+            )
+            .unwrap(),
+            output
+        );
+    }
+
+    #[test]
+    fn load_config_keeps_schema_key() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#""$schema" = "https://example.com/gemote.schema.json"
+
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#
+        )
+        .unwrap();
+
+        let cfg = load_config(f.path(), None).unwrap();
+        assert_eq!(
+            cfg.schema.as_deref(),
+            Some("https://example.com/gemote.schema.json")
+        );
+        assert_eq!(cfg.remotes.len(), 1);
+
+        let output = serialize_config_with_options(
+            &cfg,
+            SerializeOptions {
+                header: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(output.starts_with(
+            "\"$schema\" = \"https://example.com/gemote.schema.json\"\n\n[remotes.origin]"
+        ));
+        let json = serialize_config_with_options(
+            &cfg,
+            SerializeOptions {
+                format: ConfigFormat::Json,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let reparsed: GemoteConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(reparsed.schema, cfg.schema);
+    }
+
+    #[test]
+    fn load_config_invalid_toml() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(f, "[remotes\norigin = {{ url = }}").unwrap();
+
+        let result = load_config(f.path(), None);
+        assert!(matches!(result, Err(GemoteError::ConfigParse(_))));
+    }
+
+    #[test]
+    fn from_repo_captures_remotes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        repo.remote_set_pushurl("origin", Some("git@example.com:repo.git"))
+            .unwrap();
+        repo.remote("upstream", "https://upstream.com/repo.git")
+            .unwrap();
+        repo.config()
+            .unwrap()
+            .set_bool("remote.upstream.prune", true)
+            .unwrap();
+
+        let cfg = GemoteConfig::from_repo(&repo).unwrap();
+        assert_eq!(cfg.remotes.len(), 2);
+        assert_eq!(cfg.remotes["origin"].url, "https://example.com/repo.git");
+        assert_eq!(
+            cfg.remotes["origin"].push_url.as_deref(),
+            Some("git@example.com:repo.git")
+        );
+        assert_eq!(cfg.remotes["upstream"].url, "https://upstream.com/repo.git");
+        assert_eq!(cfg.remotes["upstream"].prune, Some(true));
+        assert!(cfg.submodules.is_empty());
+    }
+
+    #[test]
+    fn from_repo_recursive_nests_sub_repos() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        let lib = git2::Repository::init(dir.path().join("libs/core")).unwrap();
+        lib.remote("origin", "https://example.com/core.git")
+            .unwrap();
+        let inner = git2::Repository::init(dir.path().join("libs/core/vendor")).unwrap();
+        inner
+            .remote("upstream", "https://example.com/vendor.git")
+            .unwrap();
+
+        let mut warnings = Warnings::default();
+        let cfg = GemoteConfig::from_repo_recursive(
+            &repo,
+            dir.path(),
+            git::Discovery::default(),
+            &mut warnings,
+        )
+        .unwrap();
+        assert_eq!(cfg.remotes["origin"].url, "https://example.com/repo.git");
+        let core = &cfg.submodules["libs/core"];
+        assert_eq!(core.remotes["origin"].url, "https://example.com/core.git");
+        assert_eq!(
+            core.submodules["vendor"].remotes["upstream"].url,
+            "https://example.com/vendor.git"
+        );
+        assert_eq!(cfg.submodules.len(), 1);
+    }
+
+    #[test]
+    fn dotted_remote_name_roundtrip() {
+        let mut cfg = GemoteConfig::default();
+        cfg.remotes.insert(
+            "origin.backup".into(),
+            RemoteConfig {
+                url: "https://backup.com/repo.git".into(),
+                ..Default::default()
+            },
+        );
+        let output = serialize_config(&cfg).unwrap();
+        assert!(output.contains(r#"[remotes."origin.backup"]"#));
+
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(f, "{output}").unwrap();
+        let loaded = load_config(f.path(), None).unwrap();
+        assert_eq!(loaded.remotes.len(), 1);
+        assert_eq!(
+            loaded.remotes["origin.backup"].url,
+            "https://backup.com/repo.git"
+        );
+    }
+
+    #[test]
+    fn load_config_rejects_invalid_remote_name() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[submodules."libs/core".remotes."bad name"]
+url = "https://example.com/core.git"
+"#
+        )
+        .unwrap();
+
+        let result = load_config(f.path(), None);
+        assert!(matches!(result, Err(GemoteError::InvalidRemoteName(name)) if name == "bad name"));
+    }
+
+    #[test]
+    fn load_config_leaves_submodule_defaults_out_of_sections() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[settings.submodule_defaults]
+extra_remotes = "remove"
+
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+
+[submodules."libs/core".submodules."inner".remotes.origin]
+url = "https://example.com/inner.git"
+
+[submodules."libs/util".settings]
+extra_remotes = "warn"
+"#
+        )
+        .unwrap();
+
+        // Sync works out the inherited mode, so saving the config again
+        // doesn't copy it into each section
+        let cfg = load_config(f.path(), None).unwrap();
+        assert_eq!(
+            cfg.settings.submodule_defaults.extra_remotes,
+            Some(ExtraRemotes::Remove)
+        );
+        let core = &cfg.submodules["libs/core"];
+        assert!(core.settings.extra_remotes.is_none());
+        assert!(core.settings.submodule_defaults.is_empty());
+        assert!(core.submodules["inner"].settings.extra_remotes.is_none());
+        assert_eq!(
+            cfg.submodules["libs/util"].settings.extra_remotes,
+            Some(ExtraRemotes::Warn)
+        );
+    }
+
+    #[test]
+    fn load_config_rejects_reserved_remote_names() {
+        for (name, reason) in [
+            ("HEAD", "is reserved by git for a special ref"),
+            ("FETCH_HEAD", "is reserved by git for a special ref"),
+            ("refs/heads/main", "looks like a ref path"),
+        ] {
+            let mut f = tempfile::NamedTempFile::new().unwrap();
+            write!(
+                f,
+                "[remotes.\"{name}\"]\nurl = \"https://example.com/repo.git\"\n"
+            )
+            .unwrap();
+            let result = load_config(f.path(), None);
+            assert!(
+                matches!(&result, Err(GemoteError::ReservedRemoteName { name: n, reason: r }) if n == name && *r == reason),
+                "{name}: {result:?}"
+            );
+        }
+
+        // Similar names that aren't reserved are fine
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[remotes.head-mirror]
+url = "https://example.com/repo.git"
+
+[remotes."team/refs"]
+url = "https://example.com/team.git"
+"#
+        )
+        .unwrap();
+        let loaded = load_config(f.path(), None).unwrap();
+        assert_eq!(loaded.remotes.len(), 2);
+    }
+
+    #[test]
+    fn remove_submodule_sections_keeps_the_rest() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".gemote");
+        std::fs::write(
+            &path,
+            r#"# Team remotes
+
+[settings.remote_defaults]
+prune = true
+
+[[remote]]
+name = "origin"
+url = "https://example.com/repo.git"
+
+# Shared library
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+
+[submodules."libs/gone".remotes.origin]
+url = "https://example.com/gone.git"
+"#,
+        )
+        .unwrap();
+
+        remove_submodule_sections(&path, None, &["libs/gone".into()]).unwrap();
+
+        // Only the orphan goes; comments, order and layout stay
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            contents,
+            r#"# Team remotes
+
+[settings.remote_defaults]
+prune = true
+
+[[remote]]
+name = "origin"
+url = "https://example.com/repo.git"
+
+# Shared library
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+"#
+        );
+    }
+
+    #[test]
+    fn update_remote_entries_edits_in_place() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".gemote");
+        std::fs::write(
+            &path,
+            r#"# Team remotes
+
+[settings]
+extra_remotes = "warn"
+
+# The main repo
+[remotes.origin]
+url = "https://example.com/old.git" # moved last year
+
+[[remote]]
+name = "upstream"
+url = "https://example.com/upstream.git"
+priority = 1
+
+[remotes.fork]
+url = "https://example.com/fork.git"
+"#,
+        )
+        .unwrap();
+
+        let remote = |url: &str, push_url: Option<&str>| RemoteConfig {
+            url: url.into(),
+            push_url: push_url.map(Into::into),
+            ..Default::default()
+        };
+        let remotes = BTreeMap::from([
+            (
+                "origin".to_string(),
+                remote(
+                    "https://example.com/new.git",
+                    Some("git@example.com:new.git"),
+                ),
+            ),
+            (
+                "upstream".to_string(),
+                remote("https://example.com/up2.git", None),
+            ),
+            (
+                "mirror".to_string(),
+                remote("https://example.com/m.git", None),
+            ),
+        ]);
+        update_remote_entries(&path, None, &remotes).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            contents,
+            r#"# Team remotes
+
+[settings]
+extra_remotes = "warn"
+
+# The main repo
+[remotes.origin]
+url = "https://example.com/new.git"
+push_url = "git@example.com:new.git"
+
+[[remote]]
+name = "upstream"
+url = "https://example.com/up2.git"
+
+[remotes.fork]
+url = "https://example.com/fork.git"
+
+[remotes.mirror]
+url = "https://example.com/m.git"
+"#
+        );
+    }
+
+    #[test]
+    fn remove_submodule_sections_matches_backslash_keys() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".gemote");
+        std::fs::write(
+            &path,
+            r#"[remotes.origin]
+url = "https://example.com/repo.git"
+
+[submodules."libs\\gone".remotes.origin]
+url = "https://example.com/gone.git"
+"#,
+        )
+        .unwrap();
+
+        // Orphans are named as `lint_config` reads them
+        let mut cfg = load_config(&path, None).unwrap();
+        lint_config(&mut cfg);
+        let keys: Vec<String> = cfg.submodules.keys().cloned().collect();
+        assert_eq!(keys, ["libs/gone"]);
+        remove_submodule_sections(&path, None, &keys).unwrap();
+
+        let cfg = load_config(&path, None).unwrap();
+        assert!(cfg.submodules.is_empty());
+        assert_eq!(cfg.remotes.len(), 1);
+    }
+
+    #[test]
+    fn lint_normalizes_backslash_submodule_keys() {
+        let mut cfg: GemoteConfig = toml::from_str(
+            r#"
+[submodules."libs\\core".remotes.origin]
+url = "https://example.com/core.git"
+
+[submodules."libs\\core".submodules."vendor\\lib".remotes.origin]
+url = "https://example.com/lib.git"
+
+[submodules."libs/util".remotes.origin]
+url = "https://example.com/util.git"
+"#,
+        )
+        .unwrap();
+
+        let warnings = lint_config(&mut cfg);
+        assert_eq!(warnings.count(WarningKind::SubmodulePath), 2);
+        assert_eq!(
+            cfg.submodules.keys().collect::<Vec<_>>(),
+            ["libs/core", "libs/util"]
+        );
+        assert!(
+            cfg.submodules["libs/core"]
+                .submodules
+                .contains_key("vendor/lib")
+        );
+    }
+
+    #[test]
+    fn lint_keeps_forward_slash_section_on_clash() {
+        let mut cfg: GemoteConfig = toml::from_str(
+            r#"
+[submodules."libs\\core".remotes.origin]
+url = "https://example.com/old.git"
+
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+"#,
+        )
+        .unwrap();
+
+        let warnings = lint_config(&mut cfg);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            cfg.submodules["libs/core"].remotes["origin"].url,
+            "https://example.com/core.git"
+        );
+    }
+
+    #[test]
+    fn lint_reports_remote_names_differing_by_case() {
+        let mut cfg: GemoteConfig = toml::from_str(
+            r#"
+[remotes.Origin]
+url = "https://example.com/repo.git"
+
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[remotes.upstream]
+url = "https://upstream.com/repo.git"
+
+[submodules."libs/core".remotes.FORK]
+url = "https://example.com/a.git"
+
+[submodules."libs/core".remotes.fork]
+url = "https://example.com/b.git"
+"#,
+        )
+        .unwrap();
+
+        let warnings = lint_config(&mut cfg);
+        assert_eq!(warnings.count(WarningKind::RemoteNameCase), 2);
+        let messages: Vec<String> = warnings.into_iter().map(|w| w.to_string()).collect();
+        assert!(messages[0].contains("'Origin', 'origin'"));
+        assert!(messages[1].contains("'FORK', 'fork'"));
+    }
+
+    #[test]
+    fn lint_clean_config() {
+        let mut cfg: GemoteConfig = toml::from_str(
+            r#"
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+"#,
+        )
+        .unwrap();
+        assert!(lint_config(&mut cfg).is_empty());
+    }
+
+    #[test]
+    fn insteadof_roundtrip() {
+        let mut cfg = GemoteConfig::default();
+        cfg.settings
+            .insteadof
+            .insert("git@github.com:".into(), "https://github.com/".into());
+        let output = serialize_config(&cfg).unwrap();
+        assert!(output.contains("[settings.insteadof]"));
+
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(f, "{output}").unwrap();
+        let loaded = load_config(f.path(), None).unwrap();
+        assert_eq!(loaded.settings.insteadof, cfg.settings.insteadof);
+    }
+
+    #[test]
+    fn from_repo_captures_insteadof() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("url.git@github.com:.insteadOf", "https://github.com/")
+            .unwrap();
+
+        let cfg = GemoteConfig::from_repo(&repo).unwrap();
+        assert_eq!(
+            cfg.settings.insteadof["git@github.com:"],
+            "https://github.com/"
+        );
+    }
+
+    #[test]
+    fn config_format_from_extension() {
+        assert_eq!(
+            ConfigFormat::for_path(Path::new("remotes.json"), None),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::for_path(Path::new(".gemote"), None),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::for_path(Path::new("remotes.json"), Some(ConfigFormat::Toml)),
+            ConfigFormat::Toml
+        );
+    }
+
+    #[test]
+    fn load_config_forced_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("remotes.txt");
+        std::fs::write(
+            &path,
+            r#"{"remotes": {"origin": {"url": "https://example.com/repo.git"}}}"#,
+        )
+        .unwrap();
+
+        // Guessing from the extension reads it as TOML and fails
+        assert!(matches!(
+            load_config(&path, None),
+            Err(GemoteError::ConfigParse(_))
+        ));
+        let cfg = load_config(&path, Some(ConfigFormat::Json)).unwrap();
+        assert_eq!(cfg.remotes["origin"].url, "https://example.com/repo.git");
+    }
+
+    #[test]
+    fn serialize_config_json_roundtrip() {
+        let mut cfg = GemoteConfig::default();
+        cfg.remotes.insert(
+            "origin".into(),
+            RemoteConfig {
+                url: "https://example.com/repo.git".into(),
+                ..Default::default()
+            },
+        );
+        let output = serialize_config_with_options(
+            &cfg,
+            SerializeOptions {
+                format: ConfigFormat::Json,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(!output.contains('#'));
+
+        let parsed: GemoteConfig = serde_json::from_str(&output).unwrap();
+        assert_eq!(parsed.remotes["origin"].url, "https://example.com/repo.git");
+    }
+
+    #[test]
+    fn load_config_minimal() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#
+        )
+        .unwrap();
+
+        let cfg = load_config(f.path(), None).unwrap();
+        assert_eq!(cfg.settings.extra_remotes(), ExtraRemotes::Ignore);
+        assert_eq!(cfg.remotes["origin"].url, "https://example.com/repo.git");
+        assert!(cfg.remotes["origin"].push_url.is_none());
+    }
+
+    #[test]
+    fn load_config_multiple_remotes() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[remotes.origin]
+url = "https://github.com/a.git"
+
+[remotes.upstream]
+url = "https://github.com/b.git"
+
+[remotes.mirror]
+url = "https://gitlab.com/c.git"
+"#
+        )
+        .unwrap();
+
+        let cfg = load_config(f.path(), None).unwrap();
+        assert_eq!(cfg.remotes.len(), 3);
+        assert!(cfg.remotes.contains_key("origin"));
+        assert!(cfg.remotes.contains_key("upstream"));
+        assert!(cfg.remotes.contains_key("mirror"));
+    }
+
+    #[test]
+    fn load_config_remote_array() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[[remote]]
+name = "origin"
+url = "https://github.com/a.git"
+
+[[remote]]
+name = "upstream"
+url = "https://github.com/b.git"
+push_url = "git@github.com:b.git"
+"#
+        )
+        .unwrap();
+
+        let cfg = load_config(f.path(), None).unwrap();
+        assert!(cfg.remote_list.is_empty());
+        assert_eq!(cfg.remotes.len(), 2);
+        assert_eq!(cfg.remotes["origin"].url, "https://github.com/a.git");
+        assert_eq!(
+            cfg.remotes["upstream"].push_url.as_deref(),
+            Some("git@github.com:b.git")
+        );
+    }
+
+    #[test]
+    fn load_config_remote_array_mixed_with_map() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[remotes.origin]
+url = "https://github.com/a.git"
+
+[[remote]]
+name = "upstream"
+url = "https://github.com/b.git"
+
+[[submodules."libs/core".remote]]
+name = "origin"
+url = "https://github.com/core.git"
+"#
+        )
+        .unwrap();
+
+        let cfg = load_config(f.path(), None).unwrap();
+        assert_eq!(cfg.remotes.len(), 2);
+        assert!(cfg.remotes.contains_key("origin"));
+        assert!(cfg.remotes.contains_key("upstream"));
+        assert_eq!(
+            cfg.submodules["libs/core"].remotes["origin"].url,
+            "https://github.com/core.git"
+        );
+    }
+
+    #[test]
+    fn load_config_remote_array_duplicate_name() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[[remote]]
+name = "origin"
+url = "https://github.com/a.git"
+
+[[remote]]
+name = "origin"
+url = "https://github.com/b.git"
+"#
+        )
+        .unwrap();
+
+        let result = load_config(f.path(), None);
+        assert!(matches!(result, Err(GemoteError::DuplicateRemote(name)) if name == "origin"));
+    }
+
+    #[test]
+    fn load_config_remote_array_duplicates_map_entry() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            f,
+            r#"
+[remotes.origin]
+url = "https://github.com/a.git"
+
+[[remote]]
+name = "origin"
+url = "https://github.com/b.git"
+"#
+        )
+        .unwrap();
+
+        let result = load_config(f.path(), None);
+        assert!(matches!(result, Err(GemoteError::DuplicateRemote(name)) if name == "origin"));
+    }
+
+    #[test]
+    fn serialize_config_empty() {
+        let cfg = GemoteConfig::default();
+        let output = serialize_config(&cfg).unwrap();
+        // Should be valid TOML that round-trips
+        let _: GemoteConfig = toml::from_str(&output).unwrap();
+    }
+
+    #[test]
+    fn serialize_config_without_header() {
+        let mut cfg = GemoteConfig::default();
+        cfg.remotes.insert(
+            "origin".into(),
+            RemoteConfig {
+                url: "https://example.com/repo.git".into(),
+                ..Default::default()
+            },
+        );
+
+        let output = serialize_config_with_options(
+            &cfg,
+            SerializeOptions {
+                header: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(!output.contains("# Gemote configuration file"));
+        assert!(!output.starts_with('#'));
+        assert!(output.contains("https://example.com/repo.git"));
+
+        // The default keeps the header
+        assert!(
+            serialize_config(&cfg)
+                .unwrap()
+                .starts_with("# Gemote configuration file")
+        );
     }
 
     #[test]
@@ -187,6 +1933,7 @@ url = "https://gitlab.com/c.git"
             RemoteConfig {
                 url: "https://example.com/repo.git".into(),
                 push_url: None,
+                ..Default::default()
             },
         );
         let output = serialize_config(&cfg).unwrap();
@@ -202,6 +1949,7 @@ url = "https://gitlab.com/c.git"
             RemoteConfig {
                 url: "https://example.com/repo.git".into(),
                 push_url: None,
+                ..Default::default()
             },
         );
         let output = serialize_config(&cfg).unwrap();
@@ -211,12 +1959,13 @@ url = "https://gitlab.com/c.git"
     #[test]
     fn roundtrip() {
         let mut cfg = GemoteConfig::default();
-        cfg.settings.extra_remotes = ExtraRemotes::Remove;
+        cfg.settings.extra_remotes = Some(ExtraRemotes::Remove);
         cfg.remotes.insert(
             "origin".into(),
             RemoteConfig {
                 url: "git@github.com:org/repo.git".into(),
                 push_url: Some("https://github.com/org/repo.git".into()),
+                ..Default::default()
             },
         );
         cfg.remotes.insert(
@@ -224,13 +1973,14 @@ url = "https://gitlab.com/c.git"
             RemoteConfig {
                 url: "git@github.com:upstream/repo.git".into(),
                 push_url: None,
+                ..Default::default()
             },
         );
 
         let serialized = serialize_config(&cfg).unwrap();
         let deserialized: GemoteConfig = toml::from_str(&serialized).unwrap();
 
-        assert_eq!(deserialized.settings.extra_remotes, ExtraRemotes::Remove);
+        assert_eq!(deserialized.settings.extra_remotes(), ExtraRemotes::Remove);
         assert_eq!(deserialized.remotes.len(), 2);
         assert_eq!(
             deserialized.remotes["origin"].url,
@@ -255,6 +2005,7 @@ url = "https://gitlab.com/c.git"
             RemoteConfig {
                 url: "git@github.com:org/core.git".into(),
                 push_url: None,
+                ..Default::default()
             },
         );
         sub_cfg.remotes.insert(
@@ -262,16 +2013,18 @@ url = "https://gitlab.com/c.git"
             RemoteConfig {
                 url: "git@github.com:upstream/core.git".into(),
                 push_url: None,
+                ..Default::default()
             },
         );
 
         let mut cfg = GemoteConfig::default();
-        cfg.settings.extra_remotes = ExtraRemotes::Ignore;
+        cfg.settings.extra_remotes = Some(ExtraRemotes::Ignore);
         cfg.remotes.insert(
             "origin".into(),
             RemoteConfig {
                 url: "git@github.com:org/repo.git".into(),
                 push_url: None,
+                ..Default::default()
             },
         );
         cfg.submodules.insert("libs/core".into(), sub_cfg);
@@ -297,6 +2050,7 @@ url = "https://gitlab.com/c.git"
             RemoteConfig {
                 url: "https://example.com/inner.git".into(),
                 push_url: None,
+                ..Default::default()
             },
         );
 
@@ -306,6 +2060,7 @@ url = "https://gitlab.com/c.git"
             RemoteConfig {
                 url: "https://example.com/outer.git".into(),
                 push_url: None,
+                ..Default::default()
             },
         );
         outer.submodules.insert("nested/inner".into(), inner);
@@ -316,6 +2071,7 @@ url = "https://gitlab.com/c.git"
             RemoteConfig {
                 url: "https://example.com/root.git".into(),
                 push_url: None,
+                ..Default::default()
             },
         );
         cfg.submodules.insert("libs/outer".into(), outer);
@@ -347,7 +2103,7 @@ url = "https://example.com/repo.git"
         )
         .unwrap();
 
-        let cfg = load_config(f.path()).unwrap();
+        let cfg = load_config(f.path(), None).unwrap();
         assert!(cfg.submodules.is_empty());
     }
 
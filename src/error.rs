@@ -11,9 +11,71 @@ pub enum GemoteError {
     #[error("failed to parse config: {0}")]
     ConfigParse(#[source] toml::de::Error),
 
+    #[error("failed to parse config as JSON: {0}")]
+    ConfigParseJson(#[source] serde_json::Error),
+
+    #[error("failed to parse config for editing: {0}")]
+    ConfigEdit(#[source] toml_edit::TomlError),
+
+    #[error("remote '{0}' is defined more than once in config")]
+    DuplicateRemote(String),
+
+    #[error("'{0}' is not a valid git remote name")]
+    InvalidRemoteName(String),
+
+    #[error("remote name '{name}' {reason}")]
+    ReservedRemoteName { name: String, reason: &'static str },
+
+    #[error("cannot name remote for '{url}' with template '{template}': {reason}")]
+    NameTemplate {
+        template: String,
+        url: String,
+        reason: String,
+    },
+
+    #[error("cannot expand {{repo_name}} in '{0}': the repository has no name")]
+    RepoNameUnknown(String),
+
+    #[error("sub-repos are nested more than {max_depth} levels deep at {path}")]
+    RecursionTooDeep { path: PathBuf, max_depth: usize },
+
+    #[error("repo at {0} was already scanned; the sub-repo layout loops")]
+    RecursionCycle(PathBuf),
+
+    #[error("in submodule '{path}'")]
+    InSubmodule {
+        path: String,
+        #[source]
+        source: Box<GemoteError>,
+    },
+
+    #[error("sync aborted before changing remote '{0}'")]
+    ApplyAborted(String),
+
+    #[error("sync aborted before changing insteadOf rule 'url.{0}.insteadOf'")]
+    InsteadOfAborted(String),
+
+    #[error("invalid URL map line {line}: {reason}")]
+    UrlMap { line: usize, reason: &'static str },
+
+    #[error("remote '{0}' not found")]
+    RemoteNotFound(String),
+
+    #[error("no sync snapshot found at {0}")]
+    SnapshotNotFound(PathBuf),
+
+    #[error("failed to parse sync snapshot: {0}")]
+    SnapshotParse(#[source] toml::de::Error),
+
     #[error("failed to serialize config")]
     ConfigSerialize(#[source] toml::ser::Error),
 
+    #[error("failed to serialize config as JSON")]
+    ConfigSerializeJson(#[source] serde_json::Error),
+
+    #[error("`{command}` failed: {stderr}")]
+    GitCli { command: String, stderr: String },
+
     #[error("git operation failed")]
     Git(#[from] git2::Error),
 
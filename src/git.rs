@@ -1,13 +1,75 @@
 use std::collections::{BTreeMap, BTreeSet};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use path_slash::PathExt as _;
+use serde::Serialize;
 
+use crate::config::RemoteConfig;
 use crate::error::GemoteError;
+use crate::warnings::{WarningKind, Warnings};
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct RemoteInfo {
+    /// The first `remote.<name>.url`, which git fetches from
     pub url: String,
+    /// Any further `remote.<name>.url` values
+    pub extra_urls: Vec<String>,
+    /// The first `remote.<name>.pushurl`
     pub push_url: Option<String>,
+    /// Any further `remote.<name>.pushurl` values
+    pub extra_push_urls: Vec<String>,
+    pub prune: Option<bool>,
+    pub tagopt: Option<String>,
+    /// Default branch from `refs/remotes/<name>/HEAD`. Only filled in by
+    /// `list_remotes_with(repo, true)`.
+    pub head: Option<String>,
+}
+
+impl RemoteInfo {
+    /// This remote as a config entry, e.g. for `save`.
+    pub fn to_config(&self) -> RemoteConfig {
+        RemoteConfig {
+            url: self.url.clone(),
+            push_url: self.push_url.clone(),
+            extra_urls: self.extra_urls.clone(),
+            extra_push_urls: self.extra_push_urls.clone(),
+            prune: self.prune,
+            tagopt: self.tagopt.clone(),
+            priority: None,
+        }
+    }
+
+    /// The fetch URL followed by any extra URLs.
+    pub fn urls(&self) -> Vec<String> {
+        std::iter::once(self.url.clone())
+            .chain(self.extra_urls.iter().cloned())
+            .collect()
+    }
+
+    /// The push URL followed by any extra push URLs; empty without a push
+    /// URL.
+    pub fn push_urls(&self) -> Vec<String> {
+        self.push_url
+            .iter()
+            .chain(&self.extra_push_urls)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Everything git knows about a single remote, including config keys that
+/// `RemoteInfo` leaves out.
+#[derive(Debug, Serialize)]
+pub struct RemoteDetails {
+    pub name: String,
+    pub urls: Vec<String>,
+    pub push_urls: Vec<String>,
+    pub fetch_refspecs: Vec<String>,
+    pub push_refspecs: Vec<String>,
+    pub tagopt: Option<String>,
+    pub prune: Option<bool>,
+    pub mirror: Option<bool>,
+    pub head: Option<String>,
 }
 
 pub fn open_repo(path: Option<&Path>) -> Result<git2::Repository, GemoteError> {
@@ -18,17 +80,176 @@ pub fn open_repo(path: Option<&Path>) -> Result<git2::Repository, GemoteError> {
 }
 
 pub fn list_remotes(repo: &git2::Repository) -> Result<BTreeMap<String, RemoteInfo>, GemoteError> {
+    list_remotes_with(repo, false)
+}
+
+/// Like `list_remotes`, but also resolves each remote's HEAD branch when
+/// `include_head` is set. That costs a ref lookup per remote, so the diff
+/// path leaves it off.
+pub fn list_remotes_with(
+    repo: &git2::Repository,
+    include_head: bool,
+) -> Result<BTreeMap<String, RemoteInfo>, GemoteError> {
     let mut map = BTreeMap::new();
+    let config = repo.config()?;
     let remotes = repo.remotes()?;
     for name in remotes.iter().flatten() {
         let remote = repo.find_remote(name)?;
-        let url = remote.url().unwrap_or_default().to_string();
-        let push_url = remote.pushurl().map(String::from);
-        map.insert(name.to_string(), RemoteInfo { url, push_url });
+        let mut urls = config_multivar(&config, &format!("remote.{name}.url"))?.into_iter();
+        let url = urls
+            .next()
+            .unwrap_or_else(|| remote.url().unwrap_or_default().to_string());
+        let extra_urls = urls.collect();
+        let mut push_urls =
+            config_multivar(&config, &format!("remote.{name}.pushurl"))?.into_iter();
+        let push_url = push_urls
+            .next()
+            .or_else(|| remote.pushurl().map(String::from));
+        let extra_push_urls = push_urls.collect();
+        let prune = optional(config.get_bool(&format!("remote.{name}.prune")))?;
+        let tagopt = optional(config.get_string(&format!("remote.{name}.tagopt")))?;
+        let head = if include_head {
+            remote_head(repo, name)?
+        } else {
+            None
+        };
+        map.insert(
+            name.to_string(),
+            RemoteInfo {
+                url,
+                extra_urls,
+                push_url,
+                extra_push_urls,
+                prune,
+                tagopt,
+                head,
+            },
+        );
     }
     Ok(map)
 }
 
+/// The branch `refs/remotes/<name>/HEAD` points at, if it is set.
+pub fn remote_head(repo: &git2::Repository, name: &str) -> Result<Option<String>, GemoteError> {
+    let Some(reference) = optional(repo.find_reference(&format!("refs/remotes/{name}/HEAD")))?
+    else {
+        return Ok(None);
+    };
+    let prefix = format!("refs/remotes/{name}/");
+    Ok(reference
+        .symbolic_target()
+        .map(|target| target.strip_prefix(&prefix).unwrap_or(target).to_string()))
+}
+
+/// Read everything git knows about remote `name`.
+///
+/// All keys are read through `repo.config()`, the repository's full config
+/// stack, so values from `[include]`/`[includeIf]` files are seen the same
+/// way git sees them. Don't open `.git/config` directly here.
+pub fn inspect_remote(repo: &git2::Repository, name: &str) -> Result<RemoteDetails, GemoteError> {
+    let remote = repo.find_remote(name).map_err(|e| match e.code() {
+        git2::ErrorCode::NotFound => GemoteError::RemoteNotFound(name.to_string()),
+        _ => GemoteError::Git(e),
+    })?;
+    let config = repo.config()?;
+    let key = |k: &str| format!("remote.{name}.{k}");
+
+    Ok(RemoteDetails {
+        name: name.to_string(),
+        urls: config_multivar(&config, &key("url"))?,
+        push_urls: config_multivar(&config, &key("pushurl"))?,
+        fetch_refspecs: remote
+            .fetch_refspecs()?
+            .iter()
+            .flatten()
+            .map(String::from)
+            .collect(),
+        push_refspecs: remote
+            .push_refspecs()?
+            .iter()
+            .flatten()
+            .map(String::from)
+            .collect(),
+        tagopt: optional(config.get_string(&key("tagopt")))?,
+        prune: optional(config.get_bool(&key("prune")))?,
+        mirror: optional(config.get_bool(&key("mirror")))?,
+        head: remote_head(repo, name)?,
+    })
+}
+
+fn config_multivar(config: &git2::Config, key: &str) -> Result<Vec<String>, GemoteError> {
+    let mut values = Vec::new();
+    config.multivar(key, None)?.for_each(|entry| {
+        if let Some(value) = entry.value() {
+            values.push(value.to_string());
+        }
+    })?;
+    Ok(values)
+}
+
+/// The git operation `repo` is in the middle of, such as `"merge"` or
+/// `"rebase"`, or `None` when it is idle.
+pub fn operation_in_progress(repo: &git2::Repository) -> Option<&'static str> {
+    use git2::RepositoryState as State;
+    match repo.state() {
+        State::Clean => None,
+        State::Merge => Some("merge"),
+        State::Revert | State::RevertSequence => Some("revert"),
+        State::CherryPick | State::CherryPickSequence => Some("cherry-pick"),
+        State::Bisect => Some("bisect"),
+        State::Rebase | State::RebaseInteractive | State::RebaseMerge => Some("rebase"),
+        State::ApplyMailbox | State::ApplyMailboxOrRebase => Some("am"),
+    }
+}
+
+/// The `url.<base>.insteadOf` rules in the repo's own config (not global or
+/// system ones), as base -> the prefixes it replaces.
+pub fn list_insteadof(
+    repo: &git2::Repository,
+) -> Result<BTreeMap<String, Vec<String>>, GemoteError> {
+    let config = repo.config()?.open_level(git2::ConfigLevel::Local)?;
+    let mut rules: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    config
+        .entries(Some(r"^url\..*\.insteadof$"))?
+        .for_each(|entry| {
+            let base = entry
+                .name()
+                .and_then(|name| name.strip_prefix("url."))
+                .and_then(|name| name.strip_suffix(".insteadof"));
+            if let (Some(base), Some(prefix)) = (base, entry.value()) {
+                rules
+                    .entry(base.to_string())
+                    .or_default()
+                    .push(prefix.to_string());
+            }
+        })?;
+    Ok(rules)
+}
+
+/// Make `prefix` the only value of `url.<base>.insteadOf` in `config`.
+pub fn set_insteadof(
+    config: &mut git2::Config,
+    base: &str,
+    prefix: &str,
+) -> Result<(), GemoteError> {
+    let key = format!("url.{base}.insteadOf");
+    match config.remove_multivar(&key, ".*") {
+        Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+        result => result?,
+    }
+    config.set_str(&key, prefix)?;
+    Ok(())
+}
+
+/// Treat a missing config key as `None` rather than an error.
+fn optional<T>(result: Result<T, git2::Error>) -> Result<Option<T>, GemoteError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
 pub fn add_remote(
     repo: &git2::Repository,
     name: &str,
@@ -42,6 +263,41 @@ pub fn add_remote(
     Ok(())
 }
 
+/// Replace every `remote.<name>.url` value in `config` with `urls`, in order.
+pub fn set_remote_urls(
+    config: &mut git2::Config,
+    name: &str,
+    urls: &[String],
+) -> Result<(), GemoteError> {
+    replace_multivar(config, &format!("remote.{name}.url"), urls)
+}
+
+/// Replace every `remote.<name>.pushurl` value in `config` with `urls`, in
+/// order. An empty list leaves the remote with no push URL.
+pub fn set_remote_push_urls(
+    config: &mut git2::Config,
+    name: &str,
+    urls: &[String],
+) -> Result<(), GemoteError> {
+    replace_multivar(config, &format!("remote.{name}.pushurl"), urls)
+}
+
+fn replace_multivar(
+    config: &mut git2::Config,
+    key: &str,
+    urls: &[String],
+) -> Result<(), GemoteError> {
+    match config.remove_multivar(key, ".*") {
+        Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+        result => result?,
+    }
+    for url in urls {
+        // "^$" matches no existing value, so each call appends
+        config.set_multivar(key, "^$", url)?;
+    }
+    Ok(())
+}
+
 pub fn update_remote_url(
     repo: &git2::Repository,
     name: &str,
@@ -60,17 +316,232 @@ pub fn update_remote_push_url(
     Ok(())
 }
 
+/// Set `remote.<name>.<key>` in `config`.
+pub fn set_remote_option(
+    config: &mut git2::Config,
+    name: &str,
+    key: &str,
+    value: &str,
+) -> Result<(), GemoteError> {
+    config.set_str(&format!("remote.{name}.{key}"), value)?;
+    Ok(())
+}
+
+/// How extended remote settings (URL lists and options) are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Through libgit2
+    #[default]
+    Git2,
+    /// By running `git config`, for setups libgit2 handles poorly
+    GitCli,
+}
+
+/// Run `git config <args>` against `repo`'s config. Exit codes listed in
+/// `ok_codes` count as success besides 0.
+fn git_config(repo: &git2::Repository, args: &[&str], ok_codes: &[i32]) -> Result<(), GemoteError> {
+    let output = std::process::Command::new("git")
+        .arg("--git-dir")
+        .arg(repo.path())
+        .arg("config")
+        .args(args)
+        .output()?;
+    match output.status.code() {
+        Some(0) => Ok(()),
+        Some(code) if ok_codes.contains(&code) => Ok(()),
+        _ => Err(GemoteError::GitCli {
+            command: format!("git config {}", args.join(" ")),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }),
+    }
+}
+
+/// Like `set_remote_urls`, but through the `git` CLI.
+pub fn set_remote_urls_cli(
+    repo: &git2::Repository,
+    name: &str,
+    urls: &[String],
+) -> Result<(), GemoteError> {
+    replace_multivar_cli(repo, &format!("remote.{name}.url"), urls)
+}
+
+/// Like `set_remote_push_urls`, but through the `git` CLI.
+pub fn set_remote_push_urls_cli(
+    repo: &git2::Repository,
+    name: &str,
+    urls: &[String],
+) -> Result<(), GemoteError> {
+    replace_multivar_cli(repo, &format!("remote.{name}.pushurl"), urls)
+}
+
+fn replace_multivar_cli(
+    repo: &git2::Repository,
+    key: &str,
+    urls: &[String],
+) -> Result<(), GemoteError> {
+    // 5 means there was nothing to unset
+    git_config(repo, &["--unset-all", key], &[5])?;
+    for url in urls {
+        git_config(repo, &["--add", key, url], &[])?;
+    }
+    Ok(())
+}
+
+/// Like `set_remote_option`, but through the `git` CLI.
+pub fn set_remote_option_cli(
+    repo: &git2::Repository,
+    name: &str,
+    key: &str,
+    value: &str,
+) -> Result<(), GemoteError> {
+    git_config(repo, &[&format!("remote.{name}.{key}"), value], &[])
+}
+
+/// The repo's name: its working directory's name, or for a bare repo the
+/// git directory's name without `.git`.
+pub fn repo_name(repo: &git2::Repository) -> Option<String> {
+    let dir = repo.workdir().unwrap_or_else(|| repo.path());
+    let name = dir.file_name()?.to_str()?;
+    Some(name.strip_suffix(".git").unwrap_or(name).to_string())
+}
+
+pub fn rename_remote(
+    repo: &git2::Repository,
+    old_name: &str,
+    new_name: &str,
+) -> Result<(), GemoteError> {
+    repo.remote_rename(old_name, new_name)?;
+    Ok(())
+}
+
 pub fn remove_remote(repo: &git2::Repository, name: &str) -> Result<(), GemoteError> {
     repo.remote_delete(name)?;
     Ok(())
 }
 
+/// Remotes that have no fetch refspec at all, so `git fetch <name>` gets
+/// nothing.
+pub fn remotes_without_fetch_refspec(repo: &git2::Repository) -> Result<Vec<String>, GemoteError> {
+    let mut missing = Vec::new();
+    for name in repo.remotes()?.iter().flatten() {
+        if repo.find_remote(name)?.fetch_refspecs()?.is_empty() {
+            missing.push(name.to_string());
+        }
+    }
+    Ok(missing)
+}
+
+/// Give remote `name` the refspec `git remote add` would:
+/// `+refs/heads/*:refs/remotes/<name>/*`.
+pub fn add_default_fetch_refspec(repo: &git2::Repository, name: &str) -> Result<(), GemoteError> {
+    repo.remote_add_fetch(name, &format!("+refs/heads/*:refs/remotes/{name}/*"))?;
+    Ok(())
+}
+
+/// A `refs/remotes/<name>/HEAD` whose branch is gone, e.g. after the
+/// remote's default branch was renamed and the old one pruned.
+pub struct DanglingHead {
+    pub remote: String,
+    /// The missing branch HEAD points at
+    pub branch: String,
+    /// `main` or `master`, if exactly one of them is tracked and could take
+    /// its place
+    pub replacement: Option<String>,
+}
+
+/// Every remote whose `HEAD` points at a remote-tracking branch that
+/// doesn't exist.
+pub fn dangling_remote_heads(repo: &git2::Repository) -> Result<Vec<DanglingHead>, GemoteError> {
+    let tracked = |name: &str, branch: &str| {
+        optional(repo.find_reference(&format!("refs/remotes/{name}/{branch}"))).map(|r| r.is_some())
+    };
+    let mut dangling = Vec::new();
+    for name in repo.remotes()?.iter().flatten() {
+        let Some(branch) = remote_head(repo, name)? else {
+            continue;
+        };
+        if tracked(name, &branch)? {
+            continue;
+        }
+        let mut candidates = Vec::new();
+        for candidate in ["main", "master"] {
+            if tracked(name, candidate)? {
+                candidates.push(candidate.to_string());
+            }
+        }
+        dangling.push(DanglingHead {
+            remote: name.to_string(),
+            branch,
+            replacement: (candidates.len() == 1).then(|| candidates.remove(0)),
+        });
+    }
+    Ok(dangling)
+}
+
+/// Point `refs/remotes/<name>/HEAD` at `branch`, like
+/// `git remote set-head <name> <branch>`.
+pub fn set_remote_head(
+    repo: &git2::Repository,
+    name: &str,
+    branch: &str,
+) -> Result<(), GemoteError> {
+    repo.reference_symbolic(
+        &format!("refs/remotes/{name}/HEAD"),
+        &format!("refs/remotes/{name}/{branch}"),
+        true,
+        "gemote: set remote HEAD",
+    )?;
+    Ok(())
+}
+
+/// What `refresh_remote_head` found out.
+pub enum HeadRefresh {
+    /// HEAD now points at this branch
+    Set(String),
+    /// The remote's default branch, which has no remote-tracking branch yet
+    Untracked(String),
+    /// The remote didn't say which branch is its default
+    Unknown,
+}
+
+/// Ask remote `name` which branch is its default and point
+/// `refs/remotes/<name>/HEAD` at it, like `git remote set-head <name>
+/// --auto`. HEAD is only moved to a branch that has been fetched, so it
+/// never ends up dangling. Needs to reach the remote.
+pub fn refresh_remote_head(
+    repo: &git2::Repository,
+    name: &str,
+) -> Result<HeadRefresh, GemoteError> {
+    let mut remote = repo.find_remote(name)?;
+    remote.connect(git2::Direction::Fetch)?;
+    let default = optional(remote.default_branch())?;
+    remote.disconnect()?;
+    let Some(branch) = default
+        .as_ref()
+        .and_then(|buf| buf.as_str())
+        .and_then(|r| r.strip_prefix("refs/heads/"))
+    else {
+        return Ok(HeadRefresh::Unknown);
+    };
+    if optional(repo.find_reference(&format!("refs/remotes/{name}/{branch}")))?.is_none() {
+        return Ok(HeadRefresh::Untracked(branch.to_string()));
+    }
+    set_remote_head(repo, name, branch)?;
+    Ok(HeadRefresh::Set(branch.to_string()))
+}
+
 pub struct SubRepoInfo {
     pub path: String,
     pub repo: git2::Repository,
 }
 
-pub fn list_submodules(repo: &git2::Repository) -> Result<Vec<SubRepoInfo>, GemoteError> {
+/// Open every submodule of `repo`. Submodules that aren't checked out are
+/// skipped, with a warning unless `warn_uninitialized` is off.
+pub fn list_submodules(
+    repo: &git2::Repository,
+    warn_uninitialized: bool,
+    warnings: &mut Warnings,
+) -> Result<Vec<SubRepoInfo>, GemoteError> {
     let mut result = Vec::new();
     let submodules = repo.submodules()?;
     for sub in submodules {
@@ -83,31 +554,57 @@ pub fn list_submodules(repo: &git2::Repository) -> Result<Vec<SubRepoInfo>, Gemo
                 });
             }
             Err(e) => {
-                eprintln!(
-                    "warning: skipping uninitialized submodule '{}': {}",
-                    name, e
-                );
+                if warn_uninitialized {
+                    warnings.push(
+                        WarningKind::UninitializedSubmodule,
+                        &name,
+                        format!("skipping uninitialized submodule '{name}': {e}"),
+                    );
+                }
             }
         }
     }
     Ok(result)
 }
 
+/// Walk the working tree under `repo_root` for nested repos. With
+/// `ignore_rules`, directories that repo's ignore rules exclude are not
+/// walked, unless they are repos themselves: a nested repo is usually
+/// ignored by its parent on purpose.
 pub fn discover_nested_repos(
     repo_root: &Path,
     known_paths: &BTreeSet<String>,
+    ignore_rules: Option<&git2::Repository>,
+    warnings: &mut Warnings,
 ) -> Result<Vec<SubRepoInfo>, GemoteError> {
     let mut result = Vec::new();
-    discover_nested_repos_recursive(repo_root, repo_root, known_paths, &mut result)?;
+    let walk = Walk {
+        base: repo_root,
+        known_paths,
+        ignore_rules,
+    };
+    discover_nested_repos_recursive(&walk, repo_root, &mut result, warnings)?;
     Ok(result)
 }
 
+/// What stays the same across one `discover_nested_repos` walk.
+struct Walk<'a> {
+    base: &'a Path,
+    known_paths: &'a BTreeSet<String>,
+    ignore_rules: Option<&'a git2::Repository>,
+}
+
 fn discover_nested_repos_recursive(
-    base: &Path,
+    walk: &Walk,
     dir: &Path,
-    known_paths: &BTreeSet<String>,
     result: &mut Vec<SubRepoInfo>,
+    warnings: &mut Warnings,
 ) -> Result<(), GemoteError> {
+    let Walk {
+        base,
+        known_paths,
+        ignore_rules,
+    } = *walk;
     let entries = match std::fs::read_dir(dir) {
         Ok(entries) => entries,
         Err(_) => return Ok(()),
@@ -141,32 +638,118 @@ fn discover_nested_repos_recursive(
                     result.push(SubRepoInfo { path: rel, repo });
                 }
                 Err(e) => {
-                    eprintln!(
-                        "warning: could not open nested repo '{}': {}",
-                        path.display(),
-                        e
+                    warnings.push(
+                        WarningKind::UnreadableRepo,
+                        rel,
+                        format!("could not open nested repo '{}': {}", path.display(), e),
                     );
                 }
             }
             // Don't recurse into nested repos — they are their own boundary
             continue;
         }
+        if let Some(repo) = ignore_rules
+            && repo.is_path_ignored(format!("{rel}/"))?
+        {
+            continue;
+        }
         // Recurse into subdirectory
-        discover_nested_repos_recursive(base, &path, known_paths, result)?;
+        discover_nested_repos_recursive(walk, &path, result, warnings)?;
     }
     Ok(())
 }
 
+/// How `collect_all_repos` looks for sub-repos.
+#[derive(Debug, Clone, Copy)]
+pub struct Discovery {
+    /// Also walk the working tree for nested repos, not just submodules
+    pub nested: bool,
+    /// Warn about submodules that aren't checked out
+    pub warn_uninitialized: bool,
+    /// Don't walk directories the repo's ignore rules exclude
+    pub respect_gitignore: bool,
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Discovery {
+            nested: true,
+            warn_uninitialized: true,
+            respect_gitignore: false,
+        }
+    }
+}
+
+/// Deepest sub-repo nesting a recursive run will scan.
+pub const MAX_DEPTH: usize = 16;
+
+/// Bounds recursive discovery across all the `collect_all_repos` calls of
+/// one run: each repo may be scanned once, at most `max_depth` levels below
+/// the top-level repo, so a layout that loops can't cause unbounded work.
+#[derive(Debug)]
+pub struct RecursionGuard {
+    max_depth: usize,
+    /// Canonical roots of the repos scanned so far
+    visited: BTreeSet<PathBuf>,
+}
+
+impl RecursionGuard {
+    pub fn new(max_depth: usize) -> Self {
+        RecursionGuard {
+            max_depth,
+            visited: BTreeSet::new(),
+        }
+    }
+
+    /// Record a scan of the repo at `root`. Sub-repos live inside their
+    /// parent's working tree, so its depth is the number of scanned repos
+    /// that contain it.
+    fn enter(&mut self, root: &Path) -> Result<(), GemoteError> {
+        let root = root.canonicalize()?;
+        if self.visited.contains(&root) {
+            return Err(GemoteError::RecursionCycle(root));
+        }
+        let depth = self.visited.iter().filter(|v| root.starts_with(v)).count();
+        if depth > self.max_depth {
+            return Err(GemoteError::RecursionTooDeep {
+                path: root,
+                max_depth: self.max_depth,
+            });
+        }
+        self.visited.insert(root);
+        Ok(())
+    }
+}
+
+impl Default for RecursionGuard {
+    fn default() -> Self {
+        RecursionGuard::new(MAX_DEPTH)
+    }
+}
+
+/// List submodules and, if `discovery.nested` is set, nested repos found by
+/// walking the working tree. Skipping the walk avoids it on large trees
+/// where every sub-repo is a proper submodule.
 pub fn collect_all_repos(
     repo: &git2::Repository,
     repo_root: &Path,
+    discovery: Discovery,
+    guard: &mut RecursionGuard,
+    warnings: &mut Warnings,
 ) -> Result<Vec<SubRepoInfo>, GemoteError> {
-    let submodules = list_submodules(repo)?;
-    let known: BTreeSet<String> = submodules.iter().map(|s| s.path.clone()).collect();
-    let nested = discover_nested_repos(repo_root, &known)?;
-
+    guard.enter(repo_root)?;
+    let submodules = list_submodules(repo, discovery.warn_uninitialized, warnings)?;
     let mut all = submodules;
-    all.extend(nested);
+    if discovery.nested {
+        let known: BTreeSet<String> = all.iter().map(|s| s.path.clone()).collect();
+        let ignore_rules = discovery.respect_gitignore.then_some(repo);
+        all.extend(discover_nested_repos(
+            repo_root,
+            &known,
+            ignore_rules,
+            warnings,
+        )?);
+    }
     // Deduplicate by path and sort
     let mut seen = BTreeSet::new();
     all.retain(|info| seen.insert(info.path.clone()));
@@ -174,6 +757,25 @@ pub fn collect_all_repos(
     Ok(all)
 }
 
+/// `collect_all_repos`, with the top-level repo itself listed first under an
+/// empty path, so callers can handle every repo in one loop.
+pub fn collect_all_repos_with_root(
+    repo: &git2::Repository,
+    repo_root: &Path,
+    discovery: Discovery,
+    guard: &mut RecursionGuard,
+    warnings: &mut Warnings,
+) -> Result<Vec<SubRepoInfo>, GemoteError> {
+    let mut all = vec![SubRepoInfo {
+        path: String::new(),
+        repo: git2::Repository::open(repo.path())?,
+    }];
+    all.extend(collect_all_repos(
+        repo, repo_root, discovery, guard, warnings,
+    )?);
+    Ok(all)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -247,6 +849,231 @@ mod tests {
         );
     }
 
+    #[test]
+    fn repo_name_from_workdir() {
+        let dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path().join("tool")).unwrap();
+        assert_eq!(repo_name(&repo).as_deref(), Some("tool"));
+
+        let bare = git2::Repository::init_bare(dir.path().join("lib.git")).unwrap();
+        assert_eq!(repo_name(&bare).as_deref(), Some("lib"));
+    }
+
+    #[test]
+    fn list_remotes_extra_urls() {
+        let (_dir, repo) = test_repo();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        set_remote_urls(
+            &mut repo.config().unwrap(),
+            "origin",
+            &[
+                "https://example.com/repo.git".into(),
+                "https://mirror.com/repo.git".into(),
+            ],
+        )
+        .unwrap();
+
+        let remotes = list_remotes(&repo).unwrap();
+        assert_eq!(remotes["origin"].url, "https://example.com/repo.git");
+        assert_eq!(
+            remotes["origin"].extra_urls,
+            vec!["https://mirror.com/repo.git"]
+        );
+
+        // Replacing the list drops the old values
+        set_remote_urls(
+            &mut repo.config().unwrap(),
+            "origin",
+            &["https://other.com/repo.git".into()],
+        )
+        .unwrap();
+        let remotes = list_remotes(&repo).unwrap();
+        assert_eq!(remotes["origin"].url, "https://other.com/repo.git");
+        assert!(remotes["origin"].extra_urls.is_empty());
+    }
+
+    #[test]
+    fn detects_operation_in_progress() {
+        let (dir, repo) = test_repo();
+        assert_eq!(operation_in_progress(&repo), None);
+        std::fs::write(dir.path().join(".git/BISECT_LOG"), "").unwrap();
+        assert_eq!(operation_in_progress(&repo), Some("bisect"));
+    }
+
+    #[test]
+    fn list_remotes_extra_push_urls() {
+        let (_dir, repo) = test_repo();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        set_remote_push_urls(
+            &mut repo.config().unwrap(),
+            "origin",
+            &["git@a.com:repo.git".into(), "git@b.com:repo.git".into()],
+        )
+        .unwrap();
+
+        let remotes = list_remotes(&repo).unwrap();
+        assert_eq!(
+            remotes["origin"].push_url.as_deref(),
+            Some("git@a.com:repo.git")
+        );
+        assert_eq!(remotes["origin"].extra_push_urls, ["git@b.com:repo.git"]);
+        assert_eq!(
+            remotes["origin"].to_config().extra_push_urls,
+            ["git@b.com:repo.git"]
+        );
+    }
+
+    #[test]
+    fn list_remotes_skips_head_by_default() {
+        let (_dir, repo) = test_repo();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/main",
+            true,
+            "test",
+        )
+        .unwrap();
+
+        let remotes = list_remotes(&repo).unwrap();
+        assert!(remotes["origin"].head.is_none());
+    }
+
+    #[test]
+    fn list_remotes_with_head() {
+        let (_dir, repo) = test_repo();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        repo.remote("upstream", "https://example.com/up.git")
+            .unwrap();
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/main",
+            true,
+            "test",
+        )
+        .unwrap();
+
+        let remotes = list_remotes_with(&repo, true).unwrap();
+        assert_eq!(remotes["origin"].head.as_deref(), Some("main"));
+        assert!(remotes["upstream"].head.is_none());
+    }
+
+    #[test]
+    fn missing_fetch_refspec_detected_and_fixed() {
+        let (_dir, repo) = test_repo();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        repo.remote("broken", "https://example.com/broken.git")
+            .unwrap();
+        repo.config()
+            .unwrap()
+            .remove_multivar("remote.broken.fetch", ".*")
+            .unwrap();
+
+        assert_eq!(remotes_without_fetch_refspec(&repo).unwrap(), ["broken"]);
+
+        add_default_fetch_refspec(&repo, "broken").unwrap();
+        assert!(remotes_without_fetch_refspec(&repo).unwrap().is_empty());
+        let remote = repo.find_remote("broken").unwrap();
+        let refspecs = remote.fetch_refspecs().unwrap();
+        assert_eq!(
+            refspecs.iter().flatten().collect::<Vec<_>>(),
+            ["+refs/heads/*:refs/remotes/broken/*"]
+        );
+    }
+
+    #[test]
+    fn inspect_remote_defaults() {
+        let (_dir, repo) = test_repo();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+
+        let details = inspect_remote(&repo, "origin").unwrap();
+        assert_eq!(details.urls, vec!["https://example.com/repo.git"]);
+        assert!(details.push_urls.is_empty());
+        assert_eq!(
+            details.fetch_refspecs,
+            vec!["+refs/heads/*:refs/remotes/origin/*"]
+        );
+        assert!(details.push_refspecs.is_empty());
+        assert!(details.tagopt.is_none());
+        assert!(details.prune.is_none());
+        assert!(details.mirror.is_none());
+        assert!(details.head.is_none());
+    }
+
+    #[test]
+    fn inspect_remote_extended_settings() {
+        let (_dir, repo) = test_repo();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        let mut config = repo.config().unwrap();
+        config
+            .set_multivar("remote.origin.url", "^$", "https://mirror.com/repo.git")
+            .unwrap();
+        config.set_str("remote.origin.tagopt", "--no-tags").unwrap();
+        config.set_bool("remote.origin.prune", true).unwrap();
+        config.set_bool("remote.origin.mirror", false).unwrap();
+
+        let details = inspect_remote(&repo, "origin").unwrap();
+        assert_eq!(
+            details.urls,
+            vec![
+                "https://example.com/repo.git",
+                "https://mirror.com/repo.git"
+            ]
+        );
+        assert_eq!(details.tagopt.as_deref(), Some("--no-tags"));
+        assert_eq!(details.prune, Some(true));
+        assert_eq!(details.mirror, Some(false));
+    }
+
+    #[test]
+    fn remote_from_included_config() {
+        let (dir, repo) = test_repo();
+        let git_dir = repo.path().to_path_buf();
+        drop(repo);
+
+        let mut config = std::fs::read_to_string(git_dir.join("config")).unwrap();
+        config.push_str("[include]\n\tpath = remotes.inc\n");
+        std::fs::write(git_dir.join("config"), config).unwrap();
+        std::fs::write(
+            git_dir.join("remotes.inc"),
+            "[remote \"vendor\"]\n\
+             \turl = https://example.com/vendor.git\n\
+             \tpushurl = git@example.com:vendor.git\n\
+             \tfetch = +refs/heads/*:refs/remotes/vendor/*\n\
+             \ttagopt = --no-tags\n\
+             \tprune = true\n",
+        )
+        .unwrap();
+
+        let repo = git2::Repository::open(dir.path()).unwrap();
+        let remotes = list_remotes(&repo).unwrap();
+        let vendor = &remotes["vendor"];
+        assert_eq!(vendor.url, "https://example.com/vendor.git");
+        assert_eq!(
+            vendor.push_url.as_deref(),
+            Some("git@example.com:vendor.git")
+        );
+
+        let details = inspect_remote(&repo, "vendor").unwrap();
+        assert_eq!(details.push_urls, vec!["git@example.com:vendor.git"]);
+        assert_eq!(details.tagopt.as_deref(), Some("--no-tags"));
+        assert_eq!(details.prune, Some(true));
+    }
+
+    #[test]
+    fn inspect_remote_not_found() {
+        let (_dir, repo) = test_repo();
+        let result = inspect_remote(&repo, "missing");
+        assert!(matches!(result, Err(GemoteError::RemoteNotFound(name)) if name == "missing"));
+    }
+
     #[test]
     fn add_remote_basic() {
         let (_dir, repo) = test_repo();
@@ -339,7 +1166,7 @@ mod tests {
     #[test]
     fn list_submodules_empty() {
         let (_dir, repo) = test_repo();
-        let subs = list_submodules(&repo).unwrap();
+        let subs = list_submodules(&repo, true, &mut Warnings::default()).unwrap();
         assert!(subs.is_empty());
     }
 
@@ -348,7 +1175,8 @@ mod tests {
         let dir = TempDir::new().unwrap();
         git2::Repository::init(dir.path()).unwrap();
         let known = BTreeSet::new();
-        let nested = discover_nested_repos(dir.path(), &known).unwrap();
+        let nested =
+            discover_nested_repos(dir.path(), &known, None, &mut Warnings::default()).unwrap();
         assert!(nested.is_empty());
     }
 
@@ -362,7 +1190,8 @@ mod tests {
         git2::Repository::init(&nested_path).unwrap();
 
         let known = BTreeSet::new();
-        let nested = discover_nested_repos(dir.path(), &known).unwrap();
+        let nested =
+            discover_nested_repos(dir.path(), &known, None, &mut Warnings::default()).unwrap();
         assert_eq!(nested.len(), 1);
         assert_eq!(nested[0].path, "libs/core");
     }
@@ -377,7 +1206,8 @@ mod tests {
 
         let mut known = BTreeSet::new();
         known.insert("libs/core".to_string());
-        let nested = discover_nested_repos(dir.path(), &known).unwrap();
+        let nested =
+            discover_nested_repos(dir.path(), &known, None, &mut Warnings::default()).unwrap();
         assert!(nested.is_empty());
     }
 
@@ -391,14 +1221,22 @@ mod tests {
         git2::Repository::init(&hidden_path).unwrap();
 
         let known = BTreeSet::new();
-        let nested = discover_nested_repos(dir.path(), &known).unwrap();
+        let nested =
+            discover_nested_repos(dir.path(), &known, None, &mut Warnings::default()).unwrap();
         assert!(nested.is_empty());
     }
 
     #[test]
     fn collect_all_repos_empty() {
         let (dir, repo) = test_repo();
-        let all = collect_all_repos(&repo, dir.path()).unwrap();
+        let all = collect_all_repos(
+            &repo,
+            dir.path(),
+            Discovery::default(),
+            &mut RecursionGuard::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
         assert!(all.is_empty());
     }
 
@@ -409,11 +1247,132 @@ mod tests {
         std::fs::create_dir_all(&nested_path).unwrap();
         git2::Repository::init(&nested_path).unwrap();
 
-        let all = collect_all_repos(&repo, dir.path()).unwrap();
+        let all = collect_all_repos(
+            &repo,
+            dir.path(),
+            Discovery::default(),
+            &mut RecursionGuard::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
         assert_eq!(all.len(), 1);
         assert_eq!(all[0].path, "vendor/lib");
     }
 
+    #[test]
+    fn collect_all_repos_without_discovery() {
+        let (dir, repo) = test_repo();
+        let nested_path = dir.path().join("vendor").join("lib");
+        std::fs::create_dir_all(&nested_path).unwrap();
+        git2::Repository::init(&nested_path).unwrap();
+
+        let all = collect_all_repos(
+            &repo,
+            dir.path(),
+            Discovery {
+                nested: false,
+                ..Default::default()
+            },
+            &mut RecursionGuard::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert!(all.is_empty());
+    }
+
+    #[test]
+    fn recursion_guard_limits_depth() {
+        let (dir, repo) = test_repo();
+        let mut guard = RecursionGuard::new(2);
+        let mut scan = |path: &Path| {
+            collect_all_repos(
+                &repo,
+                path,
+                Discovery::default(),
+                &mut guard,
+                &mut Warnings::default(),
+            )
+        };
+
+        // Each level nests inside the one before it
+        let mut path = dir.path().to_path_buf();
+        for level in ["a", "b"] {
+            scan(&path).unwrap();
+            path = path.join(level);
+            git2::Repository::init(&path).unwrap();
+        }
+        scan(&path).unwrap();
+
+        let deeper = path.join("c");
+        git2::Repository::init(&deeper).unwrap();
+        let err = scan(&deeper).err().unwrap();
+        assert!(matches!(
+            err,
+            GemoteError::RecursionTooDeep { max_depth: 2, .. }
+        ));
+        assert!(err.to_string().contains("more than 2 levels deep"));
+    }
+
+    #[test]
+    fn recursion_guard_detects_cycle() {
+        let (dir, repo) = test_repo();
+        let mut guard = RecursionGuard::default();
+        let discovery = Discovery::default();
+        collect_all_repos(
+            &repo,
+            dir.path(),
+            discovery,
+            &mut guard,
+            &mut Warnings::default(),
+        )
+        .unwrap();
+
+        // Reaching the same repo again, e.g. through a looping layout
+        let err = collect_all_repos(
+            &repo,
+            dir.path(),
+            discovery,
+            &mut guard,
+            &mut Warnings::default(),
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, GemoteError::RecursionCycle(_)));
+    }
+
+    #[test]
+    fn collect_all_repos_respects_gitignore() {
+        let (dir, repo) = test_repo();
+        std::fs::write(dir.path().join(".gitignore"), "build/\nvendor/lib/\n").unwrap();
+        // A repo inside an ignored directory
+        std::fs::create_dir_all(dir.path().join("build/tool")).unwrap();
+        git2::Repository::init(dir.path().join("build/tool")).unwrap();
+        // A nested repo that is itself ignored, as nested repos often are
+        std::fs::create_dir_all(dir.path().join("vendor/lib")).unwrap();
+        git2::Repository::init(dir.path().join("vendor/lib")).unwrap();
+
+        let paths = |respect_gitignore| {
+            let discovery = Discovery {
+                respect_gitignore,
+                ..Default::default()
+            };
+            collect_all_repos(
+                &repo,
+                dir.path(),
+                discovery,
+                &mut RecursionGuard::default(),
+                &mut Warnings::default(),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|s| s.path)
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(paths(false), ["build/tool", "vendor/lib"]);
+        assert_eq!(paths(true), ["vendor/lib"]);
+    }
+
     #[test]
     fn discover_nested_repos_multiple() {
         let dir = TempDir::new().unwrap();
@@ -429,7 +1388,8 @@ mod tests {
         git2::Repository::init(&deep).unwrap();
 
         let known = BTreeSet::new();
-        let nested = discover_nested_repos(dir.path(), &known).unwrap();
+        let nested =
+            discover_nested_repos(dir.path(), &known, None, &mut Warnings::default()).unwrap();
         assert_eq!(nested.len(), 2);
 
         let paths: Vec<&str> = nested.iter().map(|s| s.path.as_str()).collect();
@@ -453,11 +1413,31 @@ mod tests {
         git2::Repository::init(&inner).unwrap();
 
         let known = BTreeSet::new();
-        let nested = discover_nested_repos(dir.path(), &known).unwrap();
+        let nested =
+            discover_nested_repos(dir.path(), &known, None, &mut Warnings::default()).unwrap();
         assert_eq!(nested.len(), 1);
         assert_eq!(nested[0].path, "libs/outer");
     }
 
+    #[test]
+    fn collect_all_repos_with_root_lists_root_first() {
+        let (dir, repo) = test_repo();
+        std::fs::create_dir_all(dir.path().join("vendor/lib")).unwrap();
+        git2::Repository::init(dir.path().join("vendor/lib")).unwrap();
+
+        let all = collect_all_repos_with_root(
+            &repo,
+            dir.path(),
+            Discovery::default(),
+            &mut RecursionGuard::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        let paths: Vec<_> = all.iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(paths, ["", "vendor/lib"]);
+        assert_eq!(all[0].repo.path(), repo.path());
+    }
+
     #[test]
     fn collect_all_repos_deduplicates() {
         let (dir, repo) = test_repo();
@@ -469,7 +1449,14 @@ mod tests {
 
         // collect_all_repos merges submodules (empty here) + discovered,
         // then deduplicates — verify no duplicates in output
-        let all = collect_all_repos(&repo, dir.path()).unwrap();
+        let all = collect_all_repos(
+            &repo,
+            dir.path(),
+            Discovery::default(),
+            &mut RecursionGuard::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
         assert_eq!(all.len(), 1);
         assert_eq!(all[0].path, "libs/core");
 
@@ -483,7 +1470,13 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let known = BTreeSet::new();
         // Pass a nonexistent directory — read_dir fails, returns Ok(empty)
-        let result = discover_nested_repos(&dir.path().join("nonexistent"), &known).unwrap();
+        let result = discover_nested_repos(
+            &dir.path().join("nonexistent"),
+            &known,
+            None,
+            &mut Warnings::default(),
+        )
+        .unwrap();
         assert!(result.is_empty());
     }
 
@@ -498,7 +1491,8 @@ mod tests {
         std::fs::write(corrupt.join(".git"), "invalid content").unwrap();
 
         let known = BTreeSet::new();
-        let nested = discover_nested_repos(dir.path(), &known).unwrap();
+        let nested =
+            discover_nested_repos(dir.path(), &known, None, &mut Warnings::default()).unwrap();
         // The corrupt repo should be skipped (warning printed), result is empty
         assert!(nested.is_empty());
     }
@@ -530,7 +1524,7 @@ mod tests {
         sub.clone(None).unwrap();
         sub.add_finalize().unwrap();
 
-        let subs = list_submodules(&repo).unwrap();
+        let subs = list_submodules(&repo, true, &mut Warnings::default()).unwrap();
         assert_eq!(subs.len(), 1);
         assert_eq!(subs[0].path, "sub");
     }
@@ -556,8 +1550,13 @@ mod tests {
             .unwrap();
 
         // list_submodules should handle the uninitialized submodule gracefully
-        let subs = list_submodules(&repo).unwrap();
+        let subs = list_submodules(&repo, true, &mut Warnings::default()).unwrap();
         // The submodule can't be opened, so it should be skipped
         assert!(subs.is_empty());
+        assert!(
+            list_submodules(&repo, false, &mut Warnings::default())
+                .unwrap()
+                .is_empty()
+        );
     }
 }
@@ -0,0 +1,111 @@
+use std::fmt::Write;
+
+/// One `<testcase>` of a JUnit report.
+#[derive(Debug, Default)]
+pub struct Case {
+    pub name: String,
+    /// `<property>` entries, e.g. action counts
+    pub properties: Vec<(&'static str, String)>,
+    /// Failure message; `None` means the case passed
+    pub failure: Option<String>,
+}
+
+/// Render `cases` as a JUnit XML document with a single test suite.
+pub fn render(suite: &str, cases: &[Case]) -> String {
+    let failures = cases.iter().filter(|c| c.failure.is_some()).count();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    // Writing to a String can't fail
+    let _ = writeln!(
+        xml,
+        "<testsuites tests=\"{}\" failures=\"{failures}\">",
+        cases.len()
+    );
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failures}\" errors=\"0\">",
+        escape(suite),
+        cases.len()
+    );
+    for case in cases {
+        let _ = writeln!(
+            xml,
+            "    <testcase name=\"{}\" classname=\"{}\">",
+            escape(&case.name),
+            escape(suite)
+        );
+        if !case.properties.is_empty() {
+            xml.push_str("      <properties>\n");
+            for (name, value) in &case.properties {
+                let _ = writeln!(
+                    xml,
+                    "        <property name=\"{name}\" value=\"{}\"/>",
+                    escape(value)
+                );
+            }
+            xml.push_str("      </properties>\n");
+        }
+        if let Some(message) = &case.failure {
+            let message = escape(message);
+            let _ = writeln!(
+                xml,
+                "      <failure message=\"{message}\">{message}</failure>"
+            );
+        }
+        xml.push_str("    </testcase>\n");
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Escape text for use in XML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' => out.push_str("&#10;"),
+            // Other control characters aren't allowed in XML 1.0
+            c if c.is_control() && c != '\t' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_passed_and_failed_cases() {
+        let cases = [
+            Case {
+                name: ".".into(),
+                properties: vec![("changes", "2".into())],
+                failure: None,
+            },
+            Case {
+                name: "libs/core".into(),
+                failure: Some("remote 'a' is \"bad\" <here>".into()),
+                ..Default::default()
+            },
+        ];
+        let xml = render("gemote sync", &cases);
+
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains("<testsuite name=\"gemote sync\" tests=\"2\" failures=\"1\""));
+        assert!(xml.contains("<property name=\"changes\" value=\"2\"/>"));
+        assert!(xml.contains("<testcase name=\"libs/core\""));
+        assert!(xml.contains("message=\"remote &apos;a&apos; is &quot;bad&quot; &lt;here&gt;\""));
+        assert_eq!(xml.matches("<failure").count(), 1);
+    }
+
+    #[test]
+    fn escapes_newlines_and_drops_control_chars() {
+        assert_eq!(escape("a\nb\u{1b}[0m&"), "a&#10;b[0m&amp;");
+    }
+}
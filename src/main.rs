@@ -2,26 +2,76 @@ mod cli;
 mod config;
 mod error;
 mod git;
+mod junit;
+mod naming;
+mod order;
+mod output;
+mod snapshot;
 mod sync;
+mod url;
+mod warnings;
 
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, IsTerminal, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 use anyhow::{Context, Result};
 use clap::{CommandFactory, Parser};
 use clap_complete::generate;
 use colored::Colorize;
+use serde::Serialize;
 
-use cli::{Cli, Commands};
-use config::{GemoteConfig, RemoteConfig};
+use cli::{Cli, Commands, Format, ReportFormat};
+use config::{
+    ConfigFormat, ExtraRemotes, GemoteConfig, RemoteConfig, SerializeOptions, SubmoduleDefaults,
+};
+use error::GemoteError;
+use order::RepoOrder;
+use output::Output;
+use sync::{ActionDecision, ActionKind, UrlScope};
+use warnings::{WarningKind, Warnings};
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    if let Commands::Completions { shell } = cli.command {
+    if let Commands::Completions {
+        shell,
+        install,
+        force,
+    } = cli.command
+    {
+        if install {
+            return install_completions(shell, force);
+        }
         generate(shell, &mut Cli::command(), "gemote", &mut std::io::stdout());
         return Ok(());
     }
 
+    // Like `git -C`: everything after this, including relative --repo and
+    // --config paths, resolves against the new directory
+    if let Some(dir) = &cli.chdir {
+        std::env::set_current_dir(dir)
+            .with_context(|| format!("Failed to change directory to {}", dir.display()))?;
+    }
+
+    // The repo doesn't exist until bootstrap has cloned it
+    if let Commands::Bootstrap { url, dir, dry_run } = cli.command {
+        return cmd_bootstrap(&url, dir, cli.config, cli.config_format, dry_run);
+    }
+
+    // Reads only the config, so it works where there is no repo yet
+    if let Commands::Status {
+        only_config: true,
+        prefix,
+        ..
+    } = &cli.command
+    {
+        let config_file = cli.config.unwrap_or_else(|| PathBuf::from(".gemote"));
+        return cmd_status_config_only(&config_file, cli.config_format, prefix.as_deref());
+    }
+
     let repo = git::open_repo(cli.repo.as_deref()).context("Could not open git repository")?;
     let repo_root = repo
         .workdir()
@@ -29,130 +79,1681 @@ fn main() -> Result<()> {
         .to_path_buf();
 
     match cli.command {
-        Commands::Sync { dry_run, recursive } => {
-            cmd_sync(&repo, &repo_root, cli.config, dry_run, recursive)
+        Commands::Sync {
+            dry_run,
+            verbose,
+            recursive,
+            strict_paths,
+            strict,
+            prune_config_sections,
+            action_kind,
+            only_push_url,
+            only_fetch_url,
+            prefix,
+            show_unmanaged,
+            preserve_unknown_remotes,
+            select,
+            refuse_during_operation,
+            max_removals_per_repo,
+            no_discover,
+            no_submodule_init_warn,
+            repo_order,
+            remotes_from,
+            remote_name_template,
+            remote_url_map,
+            remote_name_rewrite,
+            repo_label,
+            repo_name,
+            refresh_head,
+            use_git_cli,
+            rename_detect,
+            jobs,
+            format,
+            warnings_as_json,
+            ndjson,
+            report_format,
+            report,
+        } => {
+            let config_file =
+                resolve_config(cli.config, &repo_root, cli.config_search_stop.as_deref())?;
+            if select && !io::stdin().is_terminal() {
+                anyhow::bail!("--select needs an interactive terminal to ask on");
+            }
+            let config_root = config_root(cli.config_root, &config_file);
+            let url_map = match remote_url_map {
+                Some(path) => {
+                    let path = config_root.join(path);
+                    config::load_url_map(&path)
+                        .with_context(|| format!("Failed to load URL map {}", path.display()))?
+                }
+                None => BTreeMap::new(),
+            };
+            let opts = SyncOptions {
+                dry_run,
+                verbose,
+                recursive,
+                strict_paths,
+                strict,
+                prune_config_sections,
+                action_kinds: action_kind,
+                url_scope: if only_push_url {
+                    Some(UrlScope::Push)
+                } else if only_fetch_url {
+                    Some(UrlScope::Fetch)
+                } else {
+                    None
+                },
+                prefix,
+                show_unmanaged,
+                preserve_unknown_remotes,
+                select,
+                refuse_during_operation,
+                max_removals_per_repo,
+                discover: !no_discover,
+                submodule_init_warn: !no_submodule_init_warn,
+                order: repo_order,
+                remotes_from: remotes_from.map(|path| config_root.join(path)),
+                name_template: remote_name_template.unwrap_or_else(|| "{org}".into()),
+                url_map,
+                name_rewrite: remote_name_rewrite.map(|rule| *rule),
+                repo_label,
+                repo_name,
+                refresh_head,
+                backend: if use_git_cli {
+                    git::Backend::GitCli
+                } else {
+                    git::Backend::Git2
+                },
+                rename_detect,
+                jobs: jobs.into(),
+                format,
+                warnings_as_json,
+                ndjson,
+                report: report_format.zip(report),
+                config_format: cli.config_format,
+            };
+            cmd_sync(&repo, &repo_root, &config_file, &opts)
+        }
+        Commands::Save {
+            force,
+            recursive,
+            remote_name_template,
+            no_discover,
+            no_submodule_init_warn,
+            repo_order,
+            remote,
+            dry_run,
+            format,
+        } => {
+            let opts = SaveOptions {
+                force,
+                recursive,
+                name_template: remote_name_template,
+                discover: !no_discover,
+                submodule_init_warn: !no_submodule_init_warn,
+                order: repo_order,
+                remotes: remote,
+                dry_run,
+                format,
+                config_format: cli.config_format,
+            };
+            cmd_save(&repo, &repo_root, cli.config, &opts)
         }
-        Commands::Save { force, recursive } => {
-            cmd_save(&repo, &repo_root, cli.config, force, recursive)
+        Commands::Undo {
+            dry_run,
+            from_backup,
+        } => cmd_undo(&repo, from_backup, dry_run),
+        Commands::Status {
+            exit_zero, prefix, ..
+        } => {
+            let config_file =
+                resolve_config(cli.config, &repo_root, cli.config_search_stop.as_deref())?;
+            cmd_status(
+                &repo,
+                &config_file,
+                cli.config_format,
+                exit_zero,
+                prefix.as_deref(),
+            )
         }
-        Commands::Completions { .. } => unreachable!(),
+        Commands::Verify => {
+            let config_file =
+                resolve_config(cli.config, &repo_root, cli.config_search_stop.as_deref())?;
+            cmd_verify(&repo, &config_file, cli.config_format)
+        }
+        Commands::Doctor { fix, yes } => cmd_doctor(&repo, fix, yes),
+        Commands::Inspect { name, format } => {
+            let config_file =
+                resolve_config(cli.config, &repo_root, cli.config_search_stop.as_deref())?;
+            cmd_inspect(&repo, &config_file, cli.config_format, &name, format)
+        }
+        Commands::Completions { .. } | Commands::Bootstrap { .. } => unreachable!(),
     }
 }
 
-fn cmd_sync(
-    repo: &git2::Repository,
-    repo_root: &Path,
+/// Where `shell` looks for a per-user completion script for gemote, given
+/// the user's home directory and any XDG base directories that are set.
+fn completions_path(
+    shell: clap_complete::Shell,
+    home: &Path,
+    xdg_data_home: Option<&Path>,
+    xdg_config_home: Option<&Path>,
+) -> Option<PathBuf> {
+    use clap_complete::Shell;
+
+    let data = xdg_data_home.map_or_else(|| home.join(".local/share"), Path::to_path_buf);
+    let config = xdg_config_home.map_or_else(|| home.join(".config"), Path::to_path_buf);
+    match shell {
+        Shell::Bash => Some(data.join("bash-completion/completions/gemote")),
+        // Not on zsh's fpath by default; the install message says so
+        Shell::Zsh => Some(home.join(".zfunc/_gemote")),
+        Shell::Fish => Some(config.join("fish/completions/gemote.fish")),
+        _ => None,
+    }
+}
+
+fn install_completions(shell: clap_complete::Shell, force: bool) -> Result<()> {
+    let home = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .context("HOME is not set")?;
+    let env_dir = |var| {
+        std::env::var_os(var)
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+    };
+    let path = completions_path(
+        shell,
+        &home,
+        env_dir("XDG_DATA_HOME").as_deref(),
+        env_dir("XDG_CONFIG_HOME").as_deref(),
+    )
+    .with_context(|| {
+        format!(
+            "no per-user completions directory known for {shell}; \
+             redirect `gemote completions {shell}` to a file instead"
+        )
+    })?;
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists. Use --force to replace it.",
+            path.display()
+        );
+    }
+
+    let mut script = Vec::new();
+    generate(shell, &mut Cli::command(), "gemote", &mut script);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(&path, script).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    println!(
+        "{} {}",
+        "Installed completions to".green(),
+        path.display().to_string().bold()
+    );
+    if shell == clap_complete::Shell::Zsh {
+        println!(
+            "{}",
+            "Add `fpath=(~/.zfunc $fpath)` before `compinit` in ~/.zshrc if it isn't there yet."
+                .dimmed()
+        );
+    }
+    println!(
+        "{}",
+        format!("Run `gemote completions {shell} --install --force` again after upgrading gemote.")
+            .dimmed()
+    );
+    Ok(())
+}
+
+/// Pick the config file to read: `--config` if given, otherwise the nearest
+/// `.gemote` between the repo root and the search stop (the repo root itself
+/// unless `--config-search-stop` widens it).
+fn resolve_config(
     config_path: Option<PathBuf>,
+    repo_root: &Path,
+    search_stop: Option<&Path>,
+) -> Result<PathBuf> {
+    if let Some(path) = config_path {
+        return Ok(path);
+    }
+    let Some(stop) = search_stop else {
+        return Ok(repo_root.join(".gemote"));
+    };
+
+    let root = repo_root
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", repo_root.display()))?;
+    let stop = stop
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", stop.display()))?;
+    if !root.starts_with(&stop) {
+        anyhow::bail!(
+            "--config-search-stop {} is not an ancestor of the repository root {}",
+            stop.display(),
+            root.display()
+        );
+    }
+    Ok(config::find_config(&root, &stop).unwrap_or_else(|| repo_root.join(".gemote")))
+}
+
+/// The directory relative config paths resolve against: `--config-root` if
+/// given, otherwise the directory holding the config file.
+fn config_root(config_root: Option<PathBuf>, config_file: &Path) -> PathBuf {
+    config_root.unwrap_or_else(|| {
+        config_file
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    })
+}
+
+struct SyncOptions {
     dry_run: bool,
+    /// Print the git commands behind each change in a dry run
+    verbose: bool,
     recursive: bool,
+    strict_paths: bool,
+    /// Fail instead of warning when the config check finds a problem
+    strict: bool,
+    /// Drop orphaned submodule sections from the config file afterwards
+    prune_config_sections: bool,
+    /// Only apply actions of these kinds; empty means all
+    action_kinds: Vec<ActionKind>,
+    /// Only change this kind of URL
+    url_scope: Option<UrlScope>,
+    /// Only touch remotes whose names start with this
+    prefix: Option<String>,
+    /// List remotes sync leaves alone in the regular output
+    show_unmanaged: bool,
+    /// Never remove remotes missing from the config; list them instead
+    preserve_unknown_remotes: bool,
+    /// Ask about each action before applying it
+    select: bool,
+    /// Fail rather than warn when a repo is mid-merge, mid-rebase and so on
+    refuse_during_operation: bool,
+    /// Most remotes a single repo may lose in one run
+    max_removals_per_repo: Option<usize>,
+    /// Scan the working tree for nested repos, not just submodules
+    discover: bool,
+    /// Warn about submodules that aren't checked out
+    submodule_init_warn: bool,
+    order: RepoOrder,
+    /// Use this URL list as the config for the run
+    remotes_from: Option<PathBuf>,
+    /// Names the remotes from `remotes_from`
+    name_template: String,
+    /// Old URL to new URL, applied to every repo on top of the config
+    url_map: BTreeMap<String, String>,
+    /// Rename the matching local remotes before diffing
+    name_rewrite: Option<naming::NameRewrite>,
+    /// Output prefix for the top-level repo, which otherwise has none
+    repo_label: Option<String>,
+    /// `{repo_name}` for the top-level repo, instead of its directory name
+    repo_name: Option<String>,
+    /// Re-read the remote HEAD of every remote whose fetch URL changed
+    refresh_head: bool,
+    /// How URL lists and remote options are written
+    backend: git::Backend,
+    /// Rename matching remotes instead of removing and re-adding them
+    rename_detect: bool,
+    /// Number of repos to sync at once
+    jobs: usize,
+    format: Format,
+    /// Print the warnings as a JSON array on stderr at the end
+    warnings_as_json: bool,
+    /// Print a JSON line per repo as it finishes instead of the text output
+    ndjson: bool,
+    /// Also write a per-repo report in this format to this file
+    report: Option<(ReportFormat, PathBuf)>,
+    /// Parse the config in this format instead of guessing from its extension
+    config_format: Option<ConfigFormat>,
+}
+
+impl Default for SyncOptions {
+    /// A plain, non-recursive sync of one repo, as with no flags given.
+    fn default() -> Self {
+        SyncOptions {
+            dry_run: false,
+            verbose: false,
+            recursive: false,
+            strict_paths: false,
+            strict: false,
+            prune_config_sections: false,
+            action_kinds: Vec::new(),
+            url_scope: None,
+            prefix: None,
+            show_unmanaged: false,
+            preserve_unknown_remotes: false,
+            select: false,
+            refuse_during_operation: false,
+            max_removals_per_repo: None,
+            discover: true,
+            submodule_init_warn: true,
+            order: RepoOrder::Path,
+            remotes_from: None,
+            name_template: "{org}".into(),
+            url_map: BTreeMap::new(),
+            name_rewrite: None,
+            repo_label: None,
+            repo_name: None,
+            refresh_head: false,
+            backend: git::Backend::Git2,
+            rename_detect: false,
+            jobs: 1,
+            format: Format::Text,
+            warnings_as_json: false,
+            ndjson: false,
+            report: None,
+            config_format: None,
+        }
+    }
+}
+
+impl SyncOptions {
+    /// Discovery settings for this run; the config can also turn off the
+    /// uninitialized-submodule warning and skip ignored directories.
+    fn discovery(&self, settings: &config::Settings) -> git::Discovery {
+        git::Discovery {
+            nested: self.discover,
+            warn_uninitialized: self.submodule_init_warn
+                && settings.warn_uninitialized_submodules.unwrap_or(true),
+            respect_gitignore: settings.discovery.respect_gitignore,
+        }
+    }
+}
+
+/// What sync planned, and did, for one repo.
+#[derive(Serialize)]
+struct RepoPlan {
+    /// Path relative to the top-level repo, empty for the root
+    path: String,
+    actions: Vec<sync::SyncAction>,
+    /// Actions left out by --action-kind
+    skipped: usize,
+    applied: bool,
+    /// Why syncing this repo failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl RepoPlan {
+    /// A repo whose sync failed before anything was recorded for it.
+    fn failed(path: &str, error: &anyhow::Error) -> Self {
+        RepoPlan {
+            path: path.to_string(),
+            actions: Vec::new(),
+            skipped: 0,
+            applied: false,
+            error: Some(format!("{error:#}")),
+        }
+    }
+}
+
+/// One `--ndjson` line: the outcome for one repo.
+#[derive(Serialize)]
+struct RepoEvent<'a> {
+    path: &'a str,
+    /// `in_sync`, `planned` (found changes but didn't apply them),
+    /// `applied` or `failed`
+    status: &'static str,
+    adds: usize,
+    updates: usize,
+    removes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+impl<'a> RepoEvent<'a> {
+    fn new(repo_plan: &'a RepoPlan) -> Self {
+        let count = |kind| {
+            repo_plan
+                .actions
+                .iter()
+                .filter(|a| a.kind() == kind)
+                .count()
+        };
+        let status = if repo_plan.error.is_some() {
+            "failed"
+        } else if repo_plan.actions.is_empty() {
+            "in_sync"
+        } else if repo_plan.applied {
+            "applied"
+        } else {
+            "planned"
+        };
+        RepoEvent {
+            path: &repo_plan.path,
+            status,
+            adds: count(ActionKind::Add),
+            updates: count(ActionKind::Update),
+            removes: count(ActionKind::Remove),
+            error: repo_plan.error.as_deref(),
+        }
+    }
+}
+
+/// Add `repo_plan` to `plan`, and with `--ndjson` print its event right
+/// away.
+fn record_plan(plan: &mut Vec<RepoPlan>, repo_plan: RepoPlan, opts: &SyncOptions) -> Result<()> {
+    if opts.ndjson {
+        let mut stdout = io::stdout().lock();
+        writeln!(
+            stdout,
+            "{}",
+            serde_json::to_string(&RepoEvent::new(&repo_plan))?
+        )?;
+        stdout.flush()?;
+    }
+    plan.push(repo_plan);
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct SyncReport<'a> {
+    dry_run: bool,
+    repos: &'a [RepoPlan],
+}
+
+/// Final status line written to stderr in JSON mode.
+#[derive(Serialize)]
+struct SyncSummary<'a> {
+    status: &'static str,
+    exit_code: i32,
+    meaning: &'static str,
+    repos: usize,
+    changes: usize,
+    applied: usize,
+    skipped: usize,
+    warnings: &'a Warnings,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl<'a> SyncSummary<'a> {
+    fn new(plan: &[RepoPlan], warnings: &'a Warnings, error: Option<&anyhow::Error>) -> Self {
+        let (status, exit_code, meaning) = match error {
+            None => ("ok", 0, "all repos processed"),
+            Some(_) => (
+                "error",
+                1,
+                "sync stopped at an error; later repos were not processed",
+            ),
+        };
+        SyncSummary {
+            status,
+            exit_code,
+            meaning,
+            repos: plan.len(),
+            changes: plan.iter().map(|p| p.actions.len()).sum(),
+            applied: plan
+                .iter()
+                .filter(|p| p.applied)
+                .map(|p| p.actions.len())
+                .sum(),
+            skipped: plan.iter().map(|p| p.skipped).sum(),
+            warnings,
+            error: error.map(|e| format!("{e:#}")),
+        }
+    }
+}
+
+fn cmd_sync(
+    repo: &git2::Repository,
+    repo_root: &Path,
+    config_file: &Path,
+    opts: &SyncOptions,
 ) -> Result<()> {
-    let config_file = config_path.unwrap_or_else(|| repo_root.join(".gemote"));
-    let cfg = config::load_config(&config_file)
-        .with_context(|| format!("Failed to load config from {}", config_file.display()))?;
+    let mut out = match opts.format {
+        Format::Text if !opts.ndjson => Output::default(),
+        Format::Text | Format::Json => Output::quiet(),
+    };
+    let mut plan = Vec::new();
+    let result = sync_tree(repo, repo_root, config_file, opts, &mut out, &mut plan);
+
+    if let Some((ReportFormat::Junit, path)) = &opts.report {
+        let xml = junit_report(&plan, result.as_ref().err());
+        std::fs::write(path, xml)
+            .with_context(|| format!("Failed to write report to {}", path.display()))?;
+    }
 
-    sync_one_repo(repo, &cfg, None, dry_run)?;
+    if opts.format == Format::Json || opts.ndjson {
+        if !opts.ndjson {
+            let report = SyncReport {
+                dry_run: opts.dry_run,
+                repos: &plan,
+            };
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        print_warnings_json(opts, out.warnings())?;
+        let summary = SyncSummary::new(&plan, out.warnings(), result.as_ref().err());
+        eprintln!("{}", serde_json::to_string(&summary)?);
+        if result.is_err() {
+            // The summary already carries the error; don't print it again
+            std::process::exit(summary.exit_code);
+        }
+    } else if !out.warnings().is_empty() {
+        let count = out.warnings().len();
+        let noun = if count == 1 { "warning" } else { "warnings" };
+        eprintln!("{}", format!("{count} {noun} emitted").yellow());
+        print_warnings_json(opts, out.warnings())?;
+    }
+    result
+}
 
-    if recursive {
+/// With `--warnings-as-json`, print `warnings` as one JSON array on stderr,
+/// unless there are none.
+fn print_warnings_json(opts: &SyncOptions, warnings: &Warnings) -> Result<()> {
+    if opts.warnings_as_json && !warnings.is_empty() {
+        eprintln!("{}", serde_json::to_string(warnings)?);
+    }
+    Ok(())
+}
+
+/// One JUnit test case per repo: passed when it was in sync or applied
+/// cleanly, failed with the error otherwise. An error that no repo owns,
+/// such as a bad config, becomes a case of its own.
+fn junit_report(plan: &[RepoPlan], error: Option<&anyhow::Error>) -> String {
+    let mut cases: Vec<junit::Case> = plan
+        .iter()
+        .map(|repo_plan| junit::Case {
+            name: if repo_plan.path.is_empty() {
+                ".".to_string()
+            } else {
+                repo_plan.path.clone()
+            },
+            properties: vec![
+                ("changes", repo_plan.actions.len().to_string()),
+                ("applied", repo_plan.applied.to_string()),
+                ("skipped", repo_plan.skipped.to_string()),
+            ],
+            failure: repo_plan.error.clone(),
+        })
+        .collect();
+    if let Some(e) = error
+        && plan.iter().all(|p| p.error.is_none())
+    {
+        cases.push(junit::Case {
+            name: "gemote sync".to_string(),
+            failure: Some(format!("{e:#}")),
+            ..Default::default()
+        });
+    }
+    junit::render("gemote sync", &cases)
+}
+
+fn sync_tree(
+    repo: &git2::Repository,
+    repo_root: &Path,
+    config_file: &Path,
+    opts: &SyncOptions,
+    out: &mut Output,
+    plan: &mut Vec<RepoPlan>,
+) -> Result<()> {
+    let mut cfg = match &opts.remotes_from {
+        Some(list) => config::load_url_list(list, &opts.name_template)
+            .with_context(|| format!("Failed to load remotes from {}", list.display()))?,
+        None => config::load_config(config_file, opts.config_format)
+            .with_context(|| format!("Failed to load config from {}", config_file.display()))?,
+    };
+    let lint = config::lint_config(&mut cfg);
+    if opts.strict && !lint.is_empty() {
+        let count = lint.len();
+        let problems = lint
+            .into_iter()
+            .map(|w| w.message)
+            .collect::<Vec<_>>()
+            .join("; ");
+        let noun = if count == 1 { "problem" } else { "problems" };
+        anyhow::bail!("config has {count} {noun} (--strict): {problems}");
+    }
+    out.report(lint);
+    let discovery = opts.discovery(&cfg.settings);
+
+    let mut guard = git::RecursionGuard::default();
+    let mut orphaned = Vec::new();
+    let sub_repos = if opts.recursive {
+        let mut found = Warnings::default();
         let sub_repos =
-            git::collect_all_repos(repo, repo_root).context("Failed to discover sub-repos")?;
+            git::collect_all_repos_with_root(repo, repo_root, discovery, &mut guard, &mut found)
+                .context("Failed to discover sub-repos")?;
+        out.report(found);
 
-        // Warn about config sections with no matching repo
+        // Config sections with no matching repo are checked before anything
+        // is applied, so --strict-paths can fail without side effects
         let discovered_paths: std::collections::BTreeSet<String> =
             sub_repos.iter().map(|s| s.path.clone()).collect();
-        for path in cfg.submodules.keys() {
-            if !discovered_paths.contains(path) {
-                eprintln!(
-                    "{} config has submodule section '{}' but no matching repo found",
-                    "warning:".yellow().bold(),
-                    path
-                );
-            }
+        orphaned = cfg
+            .submodules
+            .keys()
+            .filter(|path| !discovered_paths.contains(*path))
+            .cloned()
+            .collect();
+        if opts.strict_paths && !orphaned.is_empty() {
+            let list = orphaned
+                .iter()
+                .map(|p| format!("'{p}'"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            anyhow::bail!("config has submodule sections with no matching repo: {list}");
+        }
+        for path in &orphaned {
+            out.warn(
+                WarningKind::OrphanedSection,
+                path.as_str(),
+                format_args!("config has submodule section '{path}' but no matching repo found"),
+            );
         }
+        sub_repos
+    } else {
+        vec![git::SubRepoInfo {
+            path: String::new(),
+            repo: git2::Repository::open(repo.path()).context("Failed to open repository")?,
+        }]
+    };
 
-        for sub in &sub_repos {
-            if let Some(sub_cfg) = cfg.submodules.get(&sub.path) {
-                println!("\n{} {}", "Submodule:".cyan().bold(), sub.path.bold());
-                sync_one_repo(&sub.repo, sub_cfg, Some(&sub.path), dry_run)?;
-                // Recurse into sub-submodules
-                if !sub_cfg.submodules.is_empty()
-                    && let Some(sub_root) = sub.repo.workdir()
-                {
-                    sync_submodules_recursive(&sub.repo, sub_root, sub_cfg, &sub.path, dry_run)?;
+    let mut targets = Vec::new();
+    collect_sync_targets(
+        sub_repos,
+        ParentConfig {
+            cfg: &cfg,
+            defaults: &cfg.settings.submodule_defaults,
+        },
+        "",
+        discovery,
+        &mut guard,
+        &mut targets,
+        out,
+    )?;
+    order::sort_by_order(&mut targets, opts.order, |t| &t.path);
+    out.flush()?;
+
+    if opts.jobs > 1 {
+        sync_parallel(targets, opts, out, plan)?;
+    } else {
+        // Output is flushed after each repo so it stays grouped per repo, in
+        // processing order
+        for target in &targets {
+            let result = sync_target(target, opts, out);
+            out.flush()?;
+            match result {
+                Ok(repo_plan) => record_plan(plan, repo_plan, opts)?,
+                Err(e) => {
+                    record_plan(plan, RepoPlan::failed(&target.path, &e), opts)?;
+                    return Err(e);
                 }
-            } else {
-                eprintln!(
-                    "{} discovered repo '{}' has no config section (skipping)",
-                    "warning:".yellow().bold(),
-                    sub.path
-                );
             }
         }
     }
 
+    if opts.prune_config_sections && !orphaned.is_empty() {
+        prune_config_sections(config_file, &orphaned, opts, out)?;
+        out.flush()?;
+    }
     Ok(())
 }
 
-fn sync_submodules_recursive(
-    parent_repo: &git2::Repository,
-    parent_root: &Path,
-    parent_cfg: &GemoteConfig,
-    parent_path: &str,
-    dry_run: bool,
+/// Remove the orphaned `[submodules."..."]` sections from the config file,
+/// or in a dry run just say which ones would go.
+fn prune_config_sections(
+    config_file: &Path,
+    orphaned: &[String],
+    opts: &SyncOptions,
+    out: &mut Output,
+) -> Result<()> {
+    if !opts.dry_run {
+        config::remove_submodule_sections(config_file, opts.config_format, orphaned)
+            .with_context(|| format!("Failed to update {}", config_file.display()))?;
+    }
+    let verb = if opts.dry_run {
+        "Would remove"
+    } else {
+        "Removed"
+    };
+    for path in orphaned {
+        out.line(format_args!("{} config section '{}'", verb.red(), path));
+    }
+    Ok(())
+}
+
+/// Sync one target, with its header and errors naming the sub-repo.
+fn sync_target(target: &SyncTarget, opts: &SyncOptions, out: &mut Output) -> Result<RepoPlan> {
+    let label = (!target.path.is_empty()).then_some(target.path.as_str());
+    if let Some(label) = label {
+        out.line(format_args!(
+            "\n{} {}",
+            "Submodule:".cyan().bold(),
+            label.bold()
+        ));
+    }
+    let result = sync_one_repo(&target.repo, &target.cfg, label, opts, out);
+    match label {
+        Some(label) => result.with_context(|| format!("in submodule '{label}'")),
+        None => result,
+    }
+}
+
+/// Sync `targets` on up to `opts.jobs` threads. Each repo's output is kept
+/// apart and printed, along with its plan, in path order once all repos are
+/// done, so the result doesn't depend on which repo finished first. Every
+/// repo is processed even if one fails; the first failure in path order is
+/// returned.
+fn sync_parallel(
+    targets: Vec<SyncTarget>,
+    opts: &SyncOptions,
+    out: &mut Output,
+    plan: &mut Vec<RepoPlan>,
 ) -> Result<()> {
-    let sub_repos =
-        git::collect_all_repos(parent_repo, parent_root).context("Failed to discover sub-repos")?;
-    for sub in &sub_repos {
-        let full_path = format!("{}/{}", parent_path, sub.path);
-        if let Some(sub_cfg) = parent_cfg.submodules.get(&sub.path) {
-            println!("\n{} {}", "Submodule:".cyan().bold(), full_path.bold());
-            sync_one_repo(&sub.repo, sub_cfg, Some(&full_path), dry_run)?;
-            if !sub_cfg.submodules.is_empty()
-                && let Some(sub_root) = sub.repo.workdir()
-            {
-                sync_submodules_recursive(&sub.repo, sub_root, sub_cfg, &full_path, dry_run)?;
+    let workers = opts.jobs.min(targets.len());
+    let queue = Mutex::new(targets.into_iter());
+    let results = Mutex::new(BTreeMap::new());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let Some(target) = queue.lock().unwrap().next() else {
+                        break;
+                    };
+                    let mut target_out = out.child();
+                    let result = sync_target(&target, opts, &mut target_out);
+                    results
+                        .lock()
+                        .unwrap()
+                        .insert(target.path.clone(), (target_out, result));
+                }
+            });
+        }
+    });
+
+    let mut first_error = None;
+    for (path, (target_out, result)) in results.into_inner().unwrap() {
+        out.append(target_out);
+        out.flush()?;
+        match result {
+            Ok(repo_plan) => record_plan(plan, repo_plan, opts)?,
+            Err(e) => {
+                record_plan(plan, RepoPlan::failed(&path, &e), opts)?;
+                first_error.get_or_insert(e);
             }
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+/// A repo to sync, with its path relative to the top-level repo.
+struct SyncTarget<'a> {
+    /// Empty for the top-level repo itself
+    path: String,
+    repo: git2::Repository,
+    /// The repo's config section, with inherited defaults filled in
+    cfg: Cow<'a, GemoteConfig>,
+}
+
+/// A config section and the `submodule_defaults` its submodule sections
+/// fall back to: its own, or else those of the section it is in.
+#[derive(Clone, Copy)]
+struct ParentConfig<'a> {
+    cfg: &'a GemoteConfig,
+    defaults: &'a SubmoduleDefaults,
+}
+
+/// Walk `sub_repos` and their configured sub-repos, appending every repo
+/// that has a config section to `targets` with parents before children. An
+/// entry with an empty path is the top-level repo and uses the parent's
+/// config.
+fn collect_sync_targets<'a>(
+    sub_repos: Vec<git::SubRepoInfo>,
+    parent: ParentConfig<'a>,
+    parent_path: &str,
+    discovery: git::Discovery,
+    guard: &mut git::RecursionGuard,
+    targets: &mut Vec<SyncTarget<'a>>,
+    out: &mut Output,
+) -> Result<()> {
+    for sub in sub_repos {
+        if sub.path.is_empty() {
+            targets.push(SyncTarget {
+                path: String::new(),
+                repo: sub.repo,
+                cfg: Cow::Borrowed(parent.cfg),
+            });
+            continue;
+        }
+        let full_path = if parent_path.is_empty() {
+            sub.path.clone()
         } else {
-            eprintln!(
-                "{} discovered repo '{}' has no config section (skipping)",
-                "warning:".yellow().bold(),
-                full_path
+            format!("{}/{}", parent_path, sub.path)
+        };
+        let Some(sub_cfg) = parent.cfg.submodules.get(&sub.path) else {
+            out.warn(
+                WarningKind::UnconfiguredRepo,
+                full_path.as_str(),
+                format_args!("discovered repo '{full_path}' has no config section (skipping)"),
             );
-        }
+            continue;
+        };
+        // Recurse into sub-submodules
+        let nested = match sub.repo.workdir() {
+            Some(sub_root) if !sub_cfg.submodules.is_empty() => {
+                let mut found = Warnings::default();
+                let nested =
+                    git::collect_all_repos(&sub.repo, sub_root, discovery, guard, &mut found)
+                        .context("Failed to discover sub-repos")
+                        .with_context(|| format!("in submodule '{full_path}'"))?;
+                out.report(found);
+                nested
+            }
+            _ => Vec::new(),
+        };
+        // Filled in on a copy, so the loaded config stays as written
+        let cfg = match (
+            &sub_cfg.settings.extra_remotes,
+            &parent.defaults.extra_remotes,
+        ) {
+            (None, Some(mode)) => {
+                let mut cfg = sub_cfg.clone();
+                cfg.settings.extra_remotes = Some(mode.clone());
+                Cow::Owned(cfg)
+            }
+            _ => Cow::Borrowed(sub_cfg),
+        };
+        targets.push(SyncTarget {
+            path: full_path.clone(),
+            repo: sub.repo,
+            cfg,
+        });
+        let defaults = if sub_cfg.settings.submodule_defaults.is_empty() {
+            parent.defaults
+        } else {
+            &sub_cfg.settings.submodule_defaults
+        };
+        let section = ParentConfig {
+            cfg: sub_cfg,
+            defaults,
+        };
+        collect_sync_targets(nested, section, &full_path, discovery, guard, targets, out)?;
     }
     Ok(())
 }
 
+/// The flags that left changes out, for the notes about skipped changes:
+/// `--action-kind`, the URL scope flag, or both.
+fn skipped_by(by_kind: usize, by_scope: usize, scope: Option<UrlScope>) -> String {
+    let mut flags = Vec::new();
+    if by_kind > 0 {
+        flags.push("--action-kind");
+    }
+    if let Some(scope) = scope
+        && by_scope > 0
+    {
+        flags.push(match scope {
+            UrlScope::Fetch => "--only-fetch-url",
+            UrlScope::Push => "--only-push-url",
+        });
+    }
+    flags.join(" and ")
+}
+
+/// Drop every remote whose name doesn't start with `prefix` from both sides,
+/// so nothing outside it is reported, changed or removed.
+fn retain_prefix(
+    prefix: Option<&str>,
+    cfg: &mut GemoteConfig,
+    local: &mut BTreeMap<String, git::RemoteInfo>,
+) {
+    if let Some(prefix) = prefix {
+        cfg.remotes.retain(|name, _| name.starts_with(prefix));
+        local.retain(|name, _| name.starts_with(prefix));
+    }
+}
+
 fn sync_one_repo(
     repo: &git2::Repository,
     cfg: &GemoteConfig,
     label: Option<&str>,
-    dry_run: bool,
-) -> Result<()> {
-    let local = git::list_remotes(repo).context("Failed to list local remotes")?;
-    let actions = sync::compute_diff(cfg, &local);
+    opts: &SyncOptions,
+    out: &mut Output,
+) -> Result<RepoPlan> {
+    let repo_name = match (label, &opts.repo_name) {
+        (None, Some(name)) => Some(name.clone()),
+        _ => git::repo_name(repo),
+    };
+    let mut cfg = config::expand_repo_name(cfg, repo_name.as_deref())?;
+    // All remotes as they are, for the undo snapshot: `local` may be
+    // narrowed by --prefix and renamed below, but undo restores every remote
+    let before = git::list_remotes(repo).context("Failed to list local remotes")?;
+    let mut local = before.clone();
+    retain_prefix(opts.prefix.as_deref(), &mut cfg, &mut local);
+    let renames = match &opts.name_rewrite {
+        Some(rewrite) => {
+            let (renames, skipped) = sync::rewrite_remote_names(&mut cfg, &mut local, rewrite);
+            for message in skipped {
+                out.warn(WarningKind::NameRewrite, label.unwrap_or_default(), message);
+            }
+            renames
+        }
+        None => Vec::new(),
+    };
+    sync::apply_url_map(&mut cfg, &local, &opts.url_map);
+    if opts.preserve_unknown_remotes {
+        // Listed below instead of warned about or removed
+        cfg.settings.extra_remotes = Some(ExtraRemotes::Ignore);
+    }
+    let cfg = &cfg;
+    if cfg.settings.extra_remotes() == ExtraRemotes::Warn {
+        for name in sync::unmanaged_remotes(cfg, &local) {
+            out.warn(
+                WarningKind::ExtraRemote,
+                label.unwrap_or_default(),
+                format_args!("remote '{name}' exists locally but not in config"),
+            );
+        }
+    }
+    // With `extra_remotes = "remove"` these show up as removals instead
+    if (opts.show_unmanaged || opts.preserve_unknown_remotes)
+        && cfg.settings.extra_remotes() != ExtraRemotes::Remove
+    {
+        for name in sync::unmanaged_remotes(cfg, &local) {
+            out.line(format_args!("  {} {}", name.bold(), "(unmanaged)".dimmed()));
+        }
+    }
+    let rename_detect = opts.rename_detect || cfg.settings.rename_detection;
+    let mut all_actions = renames;
+    all_actions.extend(sync::compute_diff_with(cfg, &local, rename_detect));
+    // insteadOf rules aren't remotes, so --prefix leaves them out
+    if opts.prefix.is_none() {
+        let insteadof = git::list_insteadof(repo).context("Failed to read insteadOf rules")?;
+        all_actions.extend(sync::diff_insteadof(cfg, &insteadof));
+    }
+    let total = all_actions.len();
+    let mut actions = sync::filter_by_kind(all_actions, &opts.action_kinds);
+    let kind_skipped = total - actions.len();
+    if let Some(scope) = opts.url_scope {
+        actions = sync::filter_by_url_scope(actions, scope);
+    }
+    let skipped = total - actions.len();
+    let skipped_by = skipped_by(kind_skipped, skipped - kind_skipped, opts.url_scope);
+
+    let mut plan = RepoPlan {
+        path: label.unwrap_or_default().to_string(),
+        actions: Vec::new(),
+        skipped,
+        applied: false,
+        error: None,
+    };
 
+    let prefix = label
+        .or(opts.repo_label.as_deref())
+        .map(|l| format!("[{}] ", l))
+        .unwrap_or_default();
     if actions.is_empty() {
-        let prefix = label.map(|l| format!("[{}] ", l)).unwrap_or_default();
-        println!(
+        let mode = if opts.dry_run {
+            format!("{} ", "(dry run)".dimmed())
+        } else {
+            String::new()
+        };
+        if skipped > 0 {
+            out.line(format_args!(
+                "{}{}{}",
+                prefix,
+                mode,
+                format!("No changes match {skipped_by} ({skipped} skipped).").green()
+            ));
+        } else {
+            out.line(format_args!(
+                "{}{}{}",
+                prefix,
+                mode,
+                "Already in sync. No changes needed.".green()
+            ));
+        }
+        return Ok(plan);
+    }
+
+    // Listed by priority, but applied in the order they were computed
+    let mut shown = actions.clone();
+    sync::sort_for_display(&mut shown, cfg);
+    if opts.dry_run {
+        for line in sync::render_grouped(&shown) {
+            out.line(format_args!("  {line}"));
+        }
+        if opts.verbose {
+            // In the order they would run, which can differ from the display
+            for action in &actions {
+                out.line(format!("  $ {}", action.as_git_command()).dimmed());
+            }
+        }
+    } else {
+        for action in &shown {
+            out.line(format_args!("  {action}"));
+        }
+    }
+    if skipped > 0 {
+        out.line(format!("({skipped} other changes skipped by {skipped_by})").dimmed());
+    }
+
+    // Checked per repo, so a recursive run can remove many remotes overall
+    // but no single repo gets emptied by mistake
+    if let Some(max) = opts.max_removals_per_repo {
+        let removals = actions
+            .iter()
+            .filter(|a| a.kind() == ActionKind::Remove)
+            .count();
+        if removals > max {
+            let repo = label.map_or("the top-level repo".to_string(), |l| format!("'{l}'"));
+            anyhow::bail!(
+                "{repo} would remove {removals} remotes, more than --max-removals-per-repo {max}"
+            );
+        }
+    }
+
+    if opts.dry_run {
+        out.line("(dry run — no changes applied)".dimmed());
+    } else {
+        if let Some(operation) = git::operation_in_progress(repo) {
+            let repo_label = label.map_or("the repo".to_string(), |l| format!("'{l}'"));
+            if opts.refuse_during_operation || cfg.settings.refuse_during_operation {
+                anyhow::bail!(
+                    "{repo_label} is in the middle of a {operation}; finish or abort it before syncing"
+                );
+            }
+            out.warn(
+                WarningKind::OperationInProgress,
+                label.unwrap_or_default(),
+                format_args!("changing remotes of {repo_label} during a {operation}"),
+            );
+        }
+        let actions = if opts.select {
+            // The plan has to be on screen before the first question
+            out.flush()?;
+            let applied = apply_selected(
+                repo,
+                &before,
+                &actions,
+                opts.backend,
+                &mut io::stdin().lock(),
+                &mut io::stderr().lock(),
+            )?;
+            if applied.is_empty() {
+                out.line(format_args!(
+                    "{}{}",
+                    prefix,
+                    "No changes selected.".yellow()
+                ));
+                plan.actions = Vec::new();
+                return Ok(plan);
+            }
+            shown = applied.clone();
+            sync::sort_for_display(&mut shown, cfg);
+            applied
+        } else {
+            write_snapshot(repo, &before, &actions)?;
+            sync::apply_actions(repo, &actions, opts.backend)
+                .context("Failed to apply sync actions")?;
+            actions
+        };
+        if opts.refresh_head {
+            refresh_heads(repo, &actions, label, out);
+        }
+        out.line(format_args!(
             "{}{}",
             prefix,
-            "Already in sync. No changes needed.".green()
-        );
+            "Sync complete.".green().bold()
+        ));
+        plan.applied = true;
+    }
+
+    plan.actions = shown;
+    Ok(plan)
+}
+
+/// Point the HEAD of each remote whose fetch URL `actions` changed at the
+/// new URL's default branch. Problems are warnings: the URLs are already
+/// updated, and a stale HEAD is only cosmetic.
+fn refresh_heads(
+    repo: &git2::Repository,
+    actions: &[sync::SyncAction],
+    label: Option<&str>,
+    out: &mut Output,
+) {
+    for action in actions {
+        let (sync::SyncAction::UpdateUrl { name, .. } | sync::SyncAction::UpdateUrls { name, .. }) =
+            action
+        else {
+            continue;
+        };
+        match git::refresh_remote_head(repo, name) {
+            Ok(git::HeadRefresh::Set(branch)) => out.line(format_args!(
+                "  {} {name}/HEAD -> {name}/{branch}",
+                "refreshed".cyan()
+            )),
+            Ok(git::HeadRefresh::Untracked(branch)) => out.warn(
+                WarningKind::RemoteHeadRefresh,
+                label.unwrap_or_default(),
+                format_args!(
+                    "default branch of '{name}' is '{branch}', which isn't fetched yet; \
+                     run `git fetch {name}` and `git remote set-head {name} --auto`"
+                ),
+            ),
+            Ok(git::HeadRefresh::Unknown) => out.warn(
+                WarningKind::RemoteHeadRefresh,
+                label.unwrap_or_default(),
+                format_args!("remote '{name}' didn't report a default branch; HEAD left as is"),
+            ),
+            Err(e) => out.warn(
+                WarningKind::RemoteHeadRefresh,
+                label.unwrap_or_default(),
+                format_args!(
+                    "could not refresh HEAD of '{name}': {:#}",
+                    anyhow::Error::new(e)
+                ),
+            ),
+        }
+    }
+}
+
+/// Record `before` and the state `actions` lead to, for `gemote undo`.
+fn write_snapshot(
+    repo: &git2::Repository,
+    before: &BTreeMap<String, git::RemoteInfo>,
+    actions: &[sync::SyncAction],
+) -> Result<()> {
+    let after = sync::simulate(before, actions);
+    snapshot::write_snapshot(repo, &snapshot::Snapshot::new(before, &after))
+        .context("Failed to write sync snapshot")
+}
+
+/// Apply `actions`, asking on `prompt` about each one first and reading the
+/// answers from `input`, and return the applied ones in order. As in `git
+/// add -p`, `y` applies, `n` skips and `q` skips this action and all after
+/// it, as does the end of the input. The snapshot is written before the
+/// first change and narrowed to the applied actions at the end, so nothing
+/// is recorded when every action is skipped.
+fn apply_selected(
+    repo: &git2::Repository,
+    before: &BTreeMap<String, git::RemoteInfo>,
+    actions: &[sync::SyncAction],
+    backend: git::Backend,
+    input: &mut impl BufRead,
+    prompt: &mut impl Write,
+) -> Result<Vec<sync::SyncAction>> {
+    let mut applied = Vec::new();
+    let mut quit = false;
+    let mut failure = None;
+    let result = sync::apply_actions_with_callback(repo, actions, backend, |action| {
+        if quit {
+            return ActionDecision::Skip;
+        }
+        match ask(format_args!("  {action}  Apply"), input, prompt) {
+            Ok(Answer::Yes) => {}
+            Ok(Answer::No) => return ActionDecision::Skip,
+            Ok(Answer::Quit) => {
+                quit = true;
+                return ActionDecision::Skip;
+            }
+            Err(e) => {
+                failure = Some(anyhow::Error::from(e).context("Failed to read selection"));
+                return ActionDecision::Abort;
+            }
+        }
+        if applied.is_empty()
+            && let Err(e) = write_snapshot(repo, before, actions)
+        {
+            failure = Some(e);
+            return ActionDecision::Abort;
+        }
+        applied.push(action.clone());
+        ActionDecision::Continue
+    });
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    result.context("Failed to apply sync actions")?;
+    if !applied.is_empty() && applied.len() < actions.len() {
+        write_snapshot(repo, before, &applied)?;
+    }
+    Ok(applied)
+}
+
+/// An answer to a question from `ask`.
+enum Answer {
+    Yes,
+    No,
+    /// No, and don't ask about the rest either
+    Quit,
+}
+
+/// Print `question` on `prompt` with `[y,n,q,?]` and read answers from
+/// `input` until one is understood. The end of the input counts as `q`.
+fn ask(
+    question: impl std::fmt::Display,
+    input: &mut impl BufRead,
+    prompt: &mut impl Write,
+) -> io::Result<Answer> {
+    loop {
+        write!(prompt, "{question} {} ", "[y,n,q,?]".blue().bold())?;
+        prompt.flush()?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            writeln!(prompt)?;
+            return Ok(Answer::Quit);
+        }
+        match answer.trim() {
+            "y" | "yes" => return Ok(Answer::Yes),
+            "n" | "no" => return Ok(Answer::No),
+            "q" | "quit" => return Ok(Answer::Quit),
+            _ => writeln!(
+                prompt,
+                "y - yes\nn - no, skip this one\nq - no, and skip all after it"
+            )?,
+        }
+    }
+}
+
+struct SaveOptions {
+    force: bool,
+    recursive: bool,
+    /// Derive remote names from their URLs with this template
+    name_template: Option<String>,
+    /// Scan the working tree for nested repos, not just submodules
+    discover: bool,
+    /// Warn about submodules that aren't checked out
+    submodule_init_warn: bool,
+    order: RepoOrder,
+    /// Only update these remotes in the existing config
+    remotes: Vec<String>,
+    /// Print the config instead of writing it
+    dry_run: bool,
+    /// How to print the config in a dry run
+    format: Format,
+    /// Read and write the config in this format instead of guessing from its
+    /// extension
+    config_format: Option<ConfigFormat>,
+}
+
+fn cmd_undo(repo: &git2::Repository, from_backup: Option<PathBuf>, dry_run: bool) -> Result<()> {
+    let path = from_backup.unwrap_or_else(|| snapshot::snapshot_path(repo));
+    let snap = snapshot::read_snapshot(&path)
+        .with_context(|| format!("Failed to read snapshot from {}", path.display()))?;
+
+    // Restore the exact prior remote set, literal URLs included
+    let mut cfg = GemoteConfig::default();
+    cfg.settings.extra_remotes = Some(ExtraRemotes::Remove);
+    cfg.settings.strict_suffix = true;
+    cfg.remotes = snap.before;
+
+    let opts = SyncOptions {
+        dry_run,
+        ..Default::default()
+    };
+    let mut out = Output::default();
+    let result = sync_one_repo(repo, &cfg, None, &opts, &mut out);
+    out.flush()?;
+    result.map(|_| ())
+}
+
+/// Where `bootstrap` clones `url` when no directory is given: the repo name,
+/// as `git clone` would pick it.
+fn clone_dir_name(url: &str) -> Option<String> {
+    let name = match url::parse_url(url) {
+        Some(parts) => parts.repo()?.to_string(),
+        None => Path::new(url.trim_end_matches('/'))
+            .file_name()?
+            .to_str()?
+            .to_string(),
+    };
+    let name = name.strip_suffix(".git").unwrap_or(&name);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Clone `url` and sync the clone against the config: `config` if given,
+/// otherwise the clone's own `.gemote`.
+fn cmd_bootstrap(
+    url: &str,
+    dir: Option<PathBuf>,
+    config: Option<PathBuf>,
+    config_format: Option<ConfigFormat>,
+    dry_run: bool,
+) -> Result<()> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => clone_dir_name(url)
+            .map(PathBuf::from)
+            .with_context(|| format!("Can't tell a directory name from '{url}'; pass one"))?,
+    };
+    if dir.exists() && dir.read_dir().map_or(true, |mut d| d.next().is_some()) {
+        anyhow::bail!("{} already exists and is not empty", dir.display());
+    }
+
+    if dry_run {
+        println!("{} {} into {}", "Would clone".green(), url, dir.display());
+        let Some(config_file) = config else {
+            println!("{}", "(then sync with the clone's .gemote)".dimmed());
+            return Ok(());
+        };
+        // A fresh clone has just `origin`, pointing at the clone URL
+        let cfg = config::load_config(&config_file, config_format)
+            .with_context(|| format!("Failed to load config from {}", config_file.display()))?;
+        let repo_name = dir.file_name().and_then(|name| name.to_str());
+        let cfg = config::expand_repo_name(&cfg, repo_name)?;
+        let local = BTreeMap::from([(
+            "origin".to_string(),
+            git::RemoteInfo {
+                url: url.to_string(),
+                extra_urls: Vec::new(),
+                push_url: None,
+                extra_push_urls: Vec::new(),
+                prune: None,
+                tagopt: None,
+                head: None,
+            },
+        )]);
+        let mut actions = sync::compute_diff(&cfg, &local);
+        sync::sort_for_display(&mut actions, &cfg);
+        for action in &actions {
+            println!("  {action}");
+        }
+        println!("{}", "(dry run — nothing cloned)".dimmed());
         return Ok(());
     }
 
+    let repo = git2::Repository::clone(url, &dir)
+        .with_context(|| format!("Failed to clone {url} into {}", dir.display()))?;
+    println!("{} {} into {}", "Cloned".green(), url, dir.display());
+    let repo_root = repo
+        .workdir()
+        .context("Repository has no working directory (bare repo)")?
+        .to_path_buf();
+    let config_file = resolve_config(config, &repo_root, None)?;
+    let opts = SyncOptions {
+        config_format,
+        ..Default::default()
+    };
+    cmd_sync(&repo, &repo_root, &config_file, &opts)
+}
+
+fn cmd_status(
+    repo: &git2::Repository,
+    config_file: &Path,
+    config_format: Option<ConfigFormat>,
+    exit_zero: bool,
+    prefix: Option<&str>,
+) -> Result<()> {
+    let cfg = config::load_config(config_file, config_format)
+        .with_context(|| format!("Failed to load config from {}", config_file.display()))?;
+    let mut cfg = config::expand_repo_name(&cfg, git::repo_name(repo).as_deref())?;
+    let mut local = git::list_remotes(repo).context("Failed to list local remotes")?;
+    retain_prefix(prefix, &mut cfg, &mut local);
+    let actions = sync::compute_diff(&cfg, &local);
+    let pending: std::collections::BTreeSet<&str> =
+        actions.iter().map(|action| action.name()).collect();
+
+    let mut names: Vec<&String> = cfg.remotes.keys().chain(local.keys()).collect();
+    names.sort_by_key(|name| sync::display_key(&cfg, name));
+    names.dedup();
+    for name in names {
+        let state = match (
+            cfg.remotes.contains_key(name),
+            local.contains_key(name),
+            pending.contains(name.as_str()),
+        ) {
+            (true, false, _) => "missing".red(),
+            (false, _, true) => "extra".red(),
+            (false, _, false) => "not in config".dimmed(),
+            (true, true, true) => "drift".yellow(),
+            (true, true, false) => "in sync".green(),
+        };
+        println!("  {name}: {state}");
+    }
+
+    if actions.is_empty() {
+        println!("{}", "Remotes match the config.".green());
+        return Ok(());
+    }
+    let count = pending.len();
+    let noun = if count == 1 { "remote" } else { "remotes" };
+    let message = format!("{count} {noun} out of sync; run `gemote sync` to apply");
+    if exit_zero {
+        println!("{}", message.yellow());
+        return Ok(());
+    }
+    anyhow::bail!(message)
+}
+
+/// `status --only-config`: diff the config against an empty remote list, so
+/// every remote shows up as an add. `{repo_name}` is taken from the
+/// directory holding the config.
+fn cmd_status_config_only(
+    config_file: &Path,
+    config_format: Option<ConfigFormat>,
+    prefix: Option<&str>,
+) -> Result<()> {
+    let cfg = config::load_config(config_file, config_format)
+        .with_context(|| format!("Failed to load config from {}", config_file.display()))?;
+    let repo_name = std::path::absolute(config_file)
+        .ok()
+        .and_then(|path| Some(path.parent()?.file_name()?.to_str()?.to_string()));
+    let mut cfg = config::expand_repo_name(&cfg, repo_name.as_deref())?;
+    let mut local = BTreeMap::new();
+    retain_prefix(prefix, &mut cfg, &mut local);
+    let mut actions = sync::compute_diff(&cfg, &local);
+    if actions.is_empty() {
+        println!("{}", "The config lists no remotes.".dimmed());
+        return Ok(());
+    }
+
+    sync::sort_for_display(&mut actions, &cfg);
     for action in &actions {
         println!("  {action}");
     }
+    let count = cfg.remotes.len();
+    let noun = if count == 1 { "remote" } else { "remotes" };
+    println!("{count} {noun} would be added to a repo with none");
+    Ok(())
+}
+
+fn cmd_verify(
+    repo: &git2::Repository,
+    config_file: &Path,
+    config_format: Option<ConfigFormat>,
+) -> Result<()> {
+    let cfg = config::load_config(config_file, config_format)
+        .with_context(|| format!("Failed to load config from {}", config_file.display()))?;
+    let mut cfg = config::expand_repo_name(&cfg, git::repo_name(repo).as_deref())?;
+    // Every remote the config doesn't list is a violation, whatever the mode
+    cfg.settings.extra_remotes = Some(ExtraRemotes::Remove);
+    let local = git::list_remotes(repo).context("Failed to list local remotes")?;
+    let mut violations = sync::compute_diff_with(&cfg, &local, false);
+    if violations.is_empty() {
+        println!("{}", "Remotes match the config exactly.".green());
+        return Ok(());
+    }
 
-    if dry_run {
-        println!("{}", "(dry run — no changes applied)".dimmed());
+    sync::sort_for_display(&mut violations, &cfg);
+    for action in &violations {
+        let label = match action.kind() {
+            ActionKind::Add => "missing".red(),
+            ActionKind::Remove => "extra".red(),
+            ActionKind::Update => "drift".yellow(),
+        };
+        println!("  {label}: {action}");
+    }
+    let count = violations.len();
+    let noun = if count == 1 {
+        "difference"
     } else {
-        sync::apply_actions(repo, &actions).context("Failed to apply sync actions")?;
-        let prefix = label.map(|l| format!("[{}] ", l)).unwrap_or_default();
-        println!("{}{}", prefix, "Sync complete.".green().bold());
+        "differences"
+    };
+    anyhow::bail!("remotes do not match the config ({count} {noun})");
+}
+
+/// A problem `doctor --fix` can repair.
+enum DoctorFix {
+    /// Give the remote the default fetch refspec
+    FetchRefspec { remote: String },
+    /// Point the remote's HEAD at `branch` instead of a missing one
+    RemoteHead { remote: String, branch: String },
+}
+
+impl DoctorFix {
+    fn question(&self) -> String {
+        match self {
+            DoctorFix::FetchRefspec { remote } => {
+                format!("Add the default fetch refspec to '{remote}'?")
+            }
+            DoctorFix::RemoteHead { remote, branch } => {
+                format!("Point {remote}/HEAD at {remote}/{branch}?")
+            }
+        }
+    }
+
+    fn apply(&self, repo: &git2::Repository) -> Result<String> {
+        match self {
+            DoctorFix::FetchRefspec { remote } => {
+                git::add_default_fetch_refspec(repo, remote)
+                    .with_context(|| format!("Failed to add a fetch refspec to '{remote}'"))?;
+                Ok(format!(
+                    "{} default fetch refspec to '{remote}'",
+                    "Added".green()
+                ))
+            }
+            DoctorFix::RemoteHead { remote, branch } => {
+                git::set_remote_head(repo, remote, branch)
+                    .with_context(|| format!("Failed to set {remote}/HEAD"))?;
+                Ok(format!(
+                    "{} {remote}/HEAD at {remote}/{branch}",
+                    "Pointed".green()
+                ))
+            }
+        }
     }
+}
 
+fn cmd_doctor(repo: &git2::Repository, fix: bool, yes: bool) -> Result<()> {
+    let mut out = Output::default();
+    let path = snapshot::snapshot_path(repo);
+    match snapshot::read_snapshot(&path) {
+        Err(GemoteError::SnapshotNotFound(_)) => {
+            out.line("No sync recorded for this repo.".dimmed());
+        }
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to read snapshot from {}", path.display()));
+        }
+        Ok(snap) => {
+            let local = git::list_remotes(repo).context("Failed to list local remotes")?;
+            match snap.state_of(&local) {
+                snapshot::SyncState::Applied => {
+                    out.line("Last sync was fully applied.".green());
+                }
+                snapshot::SyncState::Changed => {
+                    out.line("Remotes have changed since the last sync.".dimmed());
+                }
+                snapshot::SyncState::NotApplied => out.warn(
+                    WarningKind::PartialSync,
+                    "",
+                    "last sync was not applied; run `gemote sync` again",
+                ),
+                snapshot::SyncState::Partial { pending } => {
+                    let list = pending
+                        .iter()
+                        .map(|name| format!("'{name}'"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    out.warn(
+                        WarningKind::PartialSync,
+                        "",
+                        format_args!(
+                            "repo appears partially synced: {list} not yet changed; \
+                             run `gemote sync` to finish or `gemote undo` to roll back"
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    // Each problem, with the fix for it if there is one
+    let mut problems: Vec<(WarningKind, String, Option<DoctorFix>)> = Vec::new();
+    for name in git::remotes_without_fetch_refspec(repo)? {
+        problems.push((
+            WarningKind::MissingFetchRefspec,
+            format!("remote '{name}' has no fetch refspec, so fetching it gets nothing"),
+            Some(DoctorFix::FetchRefspec { remote: name }),
+        ));
+    }
+    for head in git::dangling_remote_heads(repo)? {
+        let git::DanglingHead {
+            remote,
+            branch,
+            replacement,
+        } = head;
+        problems.push((
+            WarningKind::DanglingRemoteHead,
+            format!("{remote}/HEAD points at {remote}/{branch}, which doesn't exist"),
+            replacement.map(|branch| DoctorFix::RemoteHead { remote, branch }),
+        ));
+    }
+    out.flush()?;
+
+    // Ask before each fix only when someone can answer
+    let ask_first = fix && !yes && io::stdin().is_terminal();
+    let mut quit = false;
+    let mut fixed = 0;
+    for (kind, problem, repair) in problems {
+        let Some(repair) = repair else {
+            let hint = match kind {
+                WarningKind::DanglingRemoteHead => {
+                    "; run `git remote set-head <name> --auto` to ask the remote"
+                }
+                _ => "",
+            };
+            out.warn(kind, "", format_args!("{problem}{hint}"));
+            continue;
+        };
+        if !fix {
+            out.warn(
+                kind,
+                "",
+                format_args!("{problem}; run `gemote doctor --fix` to repair it"),
+            );
+            continue;
+        }
+        let accepted = !quit
+            && (!ask_first
+                || match ask(
+                    repair.question(),
+                    &mut io::stdin().lock(),
+                    &mut io::stderr(),
+                )? {
+                    Answer::Yes => true,
+                    Answer::No => false,
+                    Answer::Quit => {
+                        quit = true;
+                        false
+                    }
+                });
+        if accepted {
+            out.line(repair.apply(repo)?);
+            fixed += 1;
+        } else {
+            out.warn(kind, "", format_args!("{problem} (not fixed)"));
+        }
+        out.flush()?;
+    }
+    if fix {
+        let noun = if fixed == 1 { "problem" } else { "problems" };
+        out.line(format!("Fixed {fixed} {noun}.").bold());
+    }
+    out.flush()?;
+
+    let problems = out.warnings().len();
+    if problems > 0 {
+        let noun = if problems == 1 { "problem" } else { "problems" };
+        anyhow::bail!("doctor found {problems} {noun}");
+    }
     Ok(())
 }
 
@@ -160,35 +1761,76 @@ fn cmd_save(
     repo: &git2::Repository,
     repo_root: &Path,
     config_path: Option<PathBuf>,
-    force: bool,
-    recursive: bool,
+    opts: &SaveOptions,
 ) -> Result<()> {
     let config_file = config_path.unwrap_or_else(|| repo_root.join(".gemote"));
 
-    if config_file.exists() && !force {
+    if !opts.remotes.is_empty() {
+        return save_named_remotes(repo, &config_file, opts);
+    }
+
+    if config_file.exists() && !opts.force && !opts.dry_run {
         anyhow::bail!(
             "{} already exists. Use --force to replace it.",
             config_file.display()
         );
     }
 
-    let mut cfg = save_one_repo(repo)?;
+    let mut out = Output::default();
+    let mut cfg = if opts.recursive {
+        let discovery = git::Discovery {
+            nested: opts.discover,
+            warn_uninitialized: opts.submodule_init_warn,
+            ..Default::default()
+        };
+        let mut found = Warnings::default();
+        let cfg = GemoteConfig::from_repo_recursive(repo, repo_root, discovery, &mut found)
+            .context("Failed to save sub-repos")?;
+        out.report(found);
+        cfg
+    } else {
+        GemoteConfig::from_repo(repo).context("Failed to list local remotes")?
+    };
+    out.flush()?;
 
-    if recursive {
-        let sub_repos =
-            git::collect_all_repos(repo, repo_root).context("Failed to discover sub-repos")?;
-        for sub in &sub_repos {
-            println!("{} {}", "Submodule:".cyan().bold(), sub.path.bold());
-            let mut sub_cfg = save_one_repo(&sub.repo)?;
-            // Recurse into sub-submodules
-            if let Some(sub_root) = sub.repo.workdir() {
-                save_submodules_recursive(&sub.repo, sub_root, &mut sub_cfg)?;
-            }
-            cfg.submodules.insert(sub.path.clone(), sub_cfg);
+    let mut sections = Vec::new();
+    section_keys(&cfg, &[], &mut sections);
+    order::sort_by_order(&mut sections, opts.order, |(path, _)| path);
+    for (path, keys) in &sections {
+        // A dry run prints only the config itself
+        if !keys.is_empty() && !opts.dry_run {
+            out.line(format_args!(
+                "{} {}",
+                "Submodule:".cyan().bold(),
+                path.bold()
+            ));
+        }
+        if let Some(template) = &opts.name_template {
+            let section = keys.iter().fold(&mut cfg, |cfg, key| {
+                cfg.submodules
+                    .get_mut(key)
+                    .expect("keys were listed from this config")
+            });
+            apply_name_template(section, path, template, &mut out);
         }
+        out.flush()?;
+    }
+
+    // Spelled out at the top so it's easy to change; submodule sections
+    // leave it unset and follow `submodule_defaults`
+    cfg.settings.extra_remotes = Some(ExtraRemotes::Ignore);
+    if opts.dry_run {
+        return print_config(&cfg, opts.format);
     }
 
-    let content = config::serialize_config(&cfg).context("Failed to serialize config")?;
+    let content = config::serialize_config_with_options(
+        &cfg,
+        SerializeOptions {
+            format: ConfigFormat::for_path(&config_file, opts.config_format),
+            ..Default::default()
+        },
+    )
+    .context("Failed to serialize config")?;
     std::fs::write(&config_file, &content)
         .with_context(|| format!("Failed to write {}", config_file.display()))?;
 
@@ -201,34 +1843,509 @@ fn cmd_save(
     Ok(())
 }
 
-fn save_submodules_recursive(
-    parent_repo: &git2::Repository,
-    parent_root: &Path,
-    parent_cfg: &mut GemoteConfig,
+/// Update the entries for `names` in the config file from the live repo,
+/// leaving every other entry as it is.
+fn save_named_remotes(
+    repo: &git2::Repository,
+    config_file: &Path,
+    opts: &SaveOptions,
 ) -> Result<()> {
-    let sub_repos =
-        git::collect_all_repos(parent_repo, parent_root).context("Failed to discover sub-repos")?;
-    for sub in &sub_repos {
-        let mut sub_cfg = save_one_repo(&sub.repo)?;
-        if let Some(sub_root) = sub.repo.workdir() {
-            save_submodules_recursive(&sub.repo, sub_root, &mut sub_cfg)?;
+    let names = &opts.remotes;
+    let config_format = ConfigFormat::for_path(config_file, opts.config_format);
+    let (mut cfg, exists) = match config::load_config(config_file, opts.config_format) {
+        Ok(cfg) => (cfg, true),
+        Err(GemoteError::ConfigNotFound(_)) => (GemoteConfig::default(), false),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to load config from {}", config_file.display()));
         }
-        parent_cfg.submodules.insert(sub.path.clone(), sub_cfg);
+    };
+
+    let local = git::list_remotes(repo).context("Failed to list local remotes")?;
+    let mut saved = BTreeMap::new();
+    for name in names {
+        let info = local
+            .get(name)
+            .ok_or_else(|| GemoteError::RemoteNotFound(name.clone()))?;
+        // Keep config-only fields the repo doesn't know about
+        let priority = cfg.remotes.get(name).and_then(|rc| rc.priority);
+        saved.insert(
+            name.clone(),
+            RemoteConfig {
+                priority,
+                ..info.to_config()
+            },
+        );
+    }
+    if opts.dry_run {
+        cfg.remotes.extend(saved);
+        return print_config(&cfg, opts.format);
+    }
+
+    if exists {
+        // Edited in place, so the rest of the file stays as written
+        config::update_remote_entries(config_file, opts.config_format, &saved)
+            .with_context(|| format!("Failed to update {}", config_file.display()))?;
+    } else {
+        cfg.remotes.extend(saved);
+        let content = config::serialize_config_with_options(
+            &cfg,
+            SerializeOptions {
+                format: config_format,
+                ..Default::default()
+            },
+        )
+        .context("Failed to serialize config")?;
+        std::fs::write(config_file, &content)
+            .with_context(|| format!("Failed to write {}", config_file.display()))?;
     }
+
+    println!(
+        "{} {} {}",
+        format!("Saved {} to", names.join(", ")).green(),
+        config_file.display().to_string().bold(),
+        "(other entries unchanged)".dimmed()
+    );
+
     Ok(())
 }
 
-fn save_one_repo(repo: &git2::Repository) -> Result<GemoteConfig> {
-    let local = git::list_remotes(repo).context("Failed to list local remotes")?;
-    let mut cfg = GemoteConfig::default();
-    for (name, info) in local {
+/// List every section of `cfg` as its path from the top-level repo and the
+/// chain of `submodules` keys leading to it, the top level itself first.
+fn section_keys(cfg: &GemoteConfig, keys: &[String], sections: &mut Vec<(String, Vec<String>)>) {
+    sections.push((keys.join("/"), keys.to_vec()));
+    for (key, sub_cfg) in &cfg.submodules {
+        let mut sub_keys = keys.to_vec();
+        sub_keys.push(key.clone());
+        section_keys(sub_cfg, &sub_keys, sections);
+    }
+}
+
+/// Rename the remotes in one captured section from `template`, keeping a
+/// remote's own name when the template can't name it.
+fn apply_name_template(cfg: &mut GemoteConfig, path: &str, template: &str, out: &mut Output) {
+    let captured = std::mem::take(&mut cfg.remotes);
+    for (name, remote) in captured {
+        let taken = cfg.remotes.keys().cloned().collect();
+        let base = naming::render_name_template(template, &remote.url).unwrap_or_else(|e| {
+            out.warn(
+                WarningKind::NameTemplate,
+                path,
+                format_args!("{e} (keeping '{name}')"),
+            );
+            name
+        });
+        cfg.remotes
+            .insert(naming::unique_name(&base, &taken), remote);
+    }
+}
+
+/// Print a config that a dry-run save would write.
+fn print_config(cfg: &GemoteConfig, format: Format) -> Result<()> {
+    let content = match format {
+        Format::Text => config::serialize_config(cfg).context("Failed to serialize config")?,
+        Format::Json => serde_json::to_string_pretty(cfg)?,
+    };
+    println!("{}", content.trim_end());
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ConfigMatch {
+    InSync,
+    Drift,
+    NotInConfig,
+    NoConfig,
+}
+
+impl std::fmt::Display for ConfigMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigMatch::InSync => write!(f, "{}", "in sync".green()),
+            ConfigMatch::Drift => write!(f, "{}", "drift".yellow()),
+            ConfigMatch::NotInConfig => write!(f, "{}", "not in config".dimmed()),
+            ConfigMatch::NoConfig => write!(f, "{}", "no config file".dimmed()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct InspectReport {
+    #[serde(flatten)]
+    details: git::RemoteDetails,
+    config: ConfigMatch,
+}
+
+fn cmd_inspect(
+    repo: &git2::Repository,
+    config_file: &Path,
+    config_format: Option<ConfigFormat>,
+    name: &str,
+    format: Format,
+) -> Result<()> {
+    let details = git::inspect_remote(repo, name)?;
+
+    let config_match = match config::load_config(config_file, config_format) {
+        Err(GemoteError::ConfigNotFound(_)) => ConfigMatch::NoConfig,
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to load config from {}", config_file.display()));
+        }
+        Ok(cfg) if !cfg.remotes.contains_key(name) => ConfigMatch::NotInConfig,
+        Ok(cfg) => {
+            let mut cfg = config::expand_repo_name(&cfg, git::repo_name(repo).as_deref())?;
+            // Only this remote matters here, so don't report or remove extras
+            cfg.settings.extra_remotes = Some(ExtraRemotes::Ignore);
+            let local = git::list_remotes(repo).context("Failed to list local remotes")?;
+            let drifted = sync::compute_diff(&cfg, &local)
+                .iter()
+                .any(|action| action.name() == name);
+            if drifted {
+                ConfigMatch::Drift
+            } else {
+                ConfigMatch::InSync
+            }
+        }
+    };
+
+    let report = InspectReport {
+        details,
+        config: config_match,
+    };
+
+    match format {
+        Format::Json => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        Format::Text => print_inspect_report(&report),
+    }
+    Ok(())
+}
+
+fn print_inspect_report(report: &InspectReport) {
+    let d = &report.details;
+    let list = |values: &[String], empty: &str| {
+        if values.is_empty() {
+            empty.dimmed().to_string()
+        } else {
+            values.join(", ")
+        }
+    };
+    let setting = |value: Option<String>| value.unwrap_or_else(|| "(default)".dimmed().to_string());
+
+    println!("{} {}", "Remote:".cyan().bold(), d.name.bold());
+    println!("  url:           {}", list(&d.urls, "(none)"));
+    println!("  push url:      {}", list(&d.push_urls, "(same as url)"));
+    println!("  fetch refspec: {}", list(&d.fetch_refspecs, "(none)"));
+    println!("  push refspec:  {}", list(&d.push_refspecs, "(none)"));
+    println!("  tagopt:        {}", setting(d.tagopt.clone()));
+    println!(
+        "  prune:         {}",
+        setting(d.prune.map(|b| b.to_string()))
+    );
+    println!(
+        "  mirror:        {}",
+        setting(d.mirror.map(|b| b.to_string()))
+    );
+    println!(
+        "  head:          {}",
+        d.head
+            .as_deref()
+            .unwrap_or(&"(unknown)".dimmed().to_string())
+    );
+    println!("  config:        {}", report.config);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sync_opts(dry_run: bool) -> SyncOptions {
+        SyncOptions {
+            dry_run,
+            discover: false,
+            ..Default::default()
+        }
+    }
+
+    fn config_with_origin() -> GemoteConfig {
+        let mut cfg = GemoteConfig::default();
         cfg.remotes.insert(
-            name,
+            "origin".into(),
             RemoteConfig {
-                url: info.url,
-                push_url: info.push_url,
+                url: "https://example.com/repo.git".into(),
+                push_url: None,
+                ..Default::default()
             },
         );
+        cfg
+    }
+
+    #[test]
+    fn skipped_by_names_the_filtering_flags() {
+        assert_eq!(skipped_by(2, 0, None), "--action-kind");
+        assert_eq!(skipped_by(0, 1, Some(UrlScope::Push)), "--only-push-url");
+        assert_eq!(
+            skipped_by(1, 1, Some(UrlScope::Fetch)),
+            "--action-kind and --only-fetch-url"
+        );
+        // A scope that skipped nothing isn't blamed
+        assert_eq!(skipped_by(1, 0, Some(UrlScope::Push)), "--action-kind");
+    }
+
+    fn add_action(name: &str) -> sync::SyncAction {
+        sync::SyncAction::Add {
+            name: name.into(),
+            url: format!("https://example.com/{name}.git"),
+            extra_urls: Vec::new(),
+            push_url: None,
+            extra_push_urls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn apply_selected_applies_only_accepted() {
+        let dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let actions = ["a", "b", "c", "d"].map(add_action);
+
+        // An unknown answer asks again; the input ends before `d`
+        let mut input = io::Cursor::new("y\nmaybe\nn\ny\n");
+        let mut prompt = Vec::new();
+        let before = BTreeMap::new();
+        let applied = apply_selected(
+            &repo,
+            &before,
+            &actions,
+            git::Backend::Git2,
+            &mut input,
+            &mut prompt,
+        )
+        .unwrap();
+        assert_eq!(applied.len(), 2);
+
+        let mut names: Vec<_> = repo
+            .remotes()
+            .unwrap()
+            .iter()
+            .flatten()
+            .map(String::from)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["a", "c"]);
+        let prompt = String::from_utf8(prompt).unwrap();
+        assert_eq!(prompt.matches("[y,n,q,?]").count(), 5);
+        assert!(prompt.contains("q - no, and skip all after it"));
+
+        // The snapshot covers only what was applied
+        let snapshot = snapshot::read_snapshot(&snapshot::snapshot_path(&repo)).unwrap();
+        let after: Vec<_> = snapshot.after.keys().collect();
+        assert_eq!(after, ["a", "c"]);
+    }
+
+    #[test]
+    fn apply_selected_quit_skips_the_rest() {
+        let dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let actions = ["a", "b", "c"].map(add_action);
+        let mut input = io::Cursor::new("yes\nq\ny\n");
+        let mut prompt = Vec::new();
+        let applied = apply_selected(
+            &repo,
+            &BTreeMap::new(),
+            &actions,
+            git::Backend::Git2,
+            &mut input,
+            &mut prompt,
+        )
+        .unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].name(), "a");
+        // Nothing is asked after `q`
+        let prompt = String::from_utf8(prompt).unwrap();
+        assert_eq!(prompt.matches("[y,n,q,?]").count(), 2);
+        assert!(repo.find_remote("b").is_err());
+    }
+
+    #[test]
+    fn apply_selected_records_nothing_when_all_skipped() {
+        let dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let applied = apply_selected(
+            &repo,
+            &BTreeMap::new(),
+            &[add_action("a")],
+            git::Backend::Git2,
+            &mut io::Cursor::new("n\n"),
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert!(applied.is_empty());
+        assert!(!snapshot::snapshot_path(&repo).exists());
+    }
+
+    #[test]
+    fn sync_one_repo_captures_output() {
+        let dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+
+        let mut out = Output::default();
+        sync_one_repo(
+            &repo,
+            &config_with_origin(),
+            Some("libs/core"),
+            &sync_opts(false),
+            &mut out,
+        )
+        .unwrap();
+
+        let stdout = out.stdout();
+        assert!(stdout.contains("origin"));
+        assert!(stdout.contains("https://example.com/repo.git"));
+        assert!(stdout.contains("[libs/core] "));
+        assert!(stdout.contains("Sync complete."));
+        assert!(out.stderr().is_empty());
+        assert!(repo.find_remote("origin").is_ok());
+    }
+
+    #[test]
+    fn sync_one_repo_captures_dry_run() {
+        let dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+
+        let mut out = Output::default();
+        sync_one_repo(
+            &repo,
+            &config_with_origin(),
+            None,
+            &sync_opts(true),
+            &mut out,
+        )
+        .unwrap();
+
+        assert!(out.stdout().contains("no changes applied"));
+        assert!(repo.find_remote("origin").is_err());
+    }
+
+    #[test]
+    fn sync_one_repo_uses_repo_label_for_top_level() {
+        let dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let opts = SyncOptions {
+            repo_label: Some("ci/app".into()),
+            ..sync_opts(false)
+        };
+
+        let mut out = Output::default();
+        sync_one_repo(&repo, &config_with_origin(), None, &opts, &mut out).unwrap();
+        assert!(out.stdout().contains("[ci/app] Sync complete."));
+
+        // Sub-repos keep their own path as the label
+        let mut out = Output::default();
+        sync_one_repo(
+            &repo,
+            &config_with_origin(),
+            Some("libs/core"),
+            &opts,
+            &mut out,
+        )
+        .unwrap();
+        assert!(out.stdout().contains("[libs/core] "));
+        assert!(!out.stdout().contains("ci/app"));
+    }
+
+    #[test]
+    fn completions_paths_per_shell() {
+        use clap_complete::Shell;
+
+        let home = Path::new("/home/dev");
+        assert_eq!(
+            completions_path(Shell::Bash, home, None, None).unwrap(),
+            Path::new("/home/dev/.local/share/bash-completion/completions/gemote")
+        );
+        assert_eq!(
+            completions_path(Shell::Zsh, home, None, None).unwrap(),
+            Path::new("/home/dev/.zfunc/_gemote")
+        );
+        assert_eq!(
+            completions_path(Shell::Fish, home, None, Some(Path::new("/xdg/config"))).unwrap(),
+            Path::new("/xdg/config/fish/completions/gemote.fish")
+        );
+        assert_eq!(
+            completions_path(Shell::Bash, home, Some(Path::new("/xdg/data")), None).unwrap(),
+            Path::new("/xdg/data/bash-completion/completions/gemote")
+        );
+        assert!(completions_path(Shell::PowerShell, home, None, None).is_none());
+    }
+
+    #[test]
+    fn clone_dir_from_url() {
+        for (url, dir) in [
+            ("https://github.com/org/tool.git", "tool"),
+            ("git@github.com:org/tool.git", "tool"),
+            ("https://github.com/org/tool/", "tool"),
+            ("/srv/git/tool.git", "tool"),
+            ("../tool", "tool"),
+        ] {
+            assert_eq!(clone_dir_name(url).as_deref(), Some(dir), "{url}");
+        }
+        assert!(clone_dir_name("/").is_none());
+    }
+
+    #[test]
+    fn config_root_defaults_to_config_dir() {
+        let config_file = Path::new("/work/repo/.gemote");
+        assert_eq!(config_root(None, config_file), Path::new("/work/repo"));
+        assert_eq!(
+            config_root(Some("/team".into()), config_file),
+            Path::new("/team")
+        );
+        // A bare file name has no directory; paths stay relative to the cwd
+        assert_eq!(config_root(None, Path::new(".gemote")), Path::new(""));
+    }
+
+    #[test]
+    fn sync_tree_collects_mixed_warnings() {
+        let dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        repo.remote("stray", "https://example.com/stray.git")
+            .unwrap();
+        git2::Repository::init(dir.path().join("vendor/lib")).unwrap();
+
+        let config_file = dir.path().join(".gemote");
+        std::fs::write(
+            &config_file,
+            r#"
+[settings]
+extra_remotes = "warn"
+
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[submodules."libs/gone".remotes.origin]
+url = "https://example.com/gone.git"
+"#,
+        )
+        .unwrap();
+
+        let opts = SyncOptions {
+            recursive: true,
+            discover: true,
+            format: Format::Json,
+            ..sync_opts(true)
+        };
+        let mut out = Output::quiet();
+        let mut plan = Vec::new();
+        sync_tree(&repo, dir.path(), &config_file, &opts, &mut out, &mut plan).unwrap();
+
+        let warnings = out.warnings();
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(warnings.count(WarningKind::OrphanedSection), 1);
+        assert_eq!(warnings.count(WarningKind::UnconfiguredRepo), 1);
+        assert_eq!(warnings.count(WarningKind::ExtraRemote), 1);
     }
-    Ok(cfg)
 }
@@ -0,0 +1,173 @@
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::error::GemoteError;
+use crate::url::parse_url;
+
+/// Render a remote name from `template` using components of `url`.
+///
+/// Supported placeholders are `{host}`, `{org}` and `{repo}`. Slashes in
+/// nested org paths become dashes so the result is a single name segment.
+pub fn render_name_template(template: &str, url: &str) -> Result<String, GemoteError> {
+    let fail = |reason: String| GemoteError::NameTemplate {
+        template: template.to_string(),
+        url: url.to_string(),
+        reason,
+    };
+
+    let parts = parse_url(url).ok_or_else(|| fail("URL has no host".into()))?;
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| fail("unclosed '{'".into()))?;
+        let key = &rest[start + 1..start + end];
+        let value = match key {
+            "host" => Some(parts.host.clone()),
+            "org" => parts.org(),
+            "repo" => parts.repo().map(String::from),
+            _ => return Err(fail(format!("unknown placeholder '{{{key}}}'"))),
+        };
+        let value = value.ok_or_else(|| fail(format!("URL has no {{{key}}} component")))?;
+        name.push_str(&value.replace('/', "-"));
+        rest = &rest[start + end + 1..];
+    }
+    name.push_str(rest);
+
+    if name.is_empty() {
+        return Err(fail("template produced an empty name".into()));
+    }
+    Ok(name)
+}
+
+/// Return `base`, or `base-2`, `base-3`, ... if it is already taken.
+pub fn unique_name(base: &str, taken: &BTreeSet<String>) -> String {
+    if !taken.contains(base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| format!("{base}-{n}"))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap()
+}
+
+/// A `--remote-name-rewrite` rule: remote names that `pattern` matches in
+/// full are renamed to `replacement`, with `$1`, `${name}` and so on filled
+/// in from the match.
+#[derive(Debug, Clone)]
+pub struct NameRewrite {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl FromStr for NameRewrite {
+    type Err = String;
+
+    /// Parse `FROM=TO`, where `FROM` is a regex.
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let (from, to) = rule
+            .split_once('=')
+            .ok_or("expected FROM=TO, e.g. 'old-(.*)=new-$1'")?;
+        if from.is_empty() || to.is_empty() {
+            return Err("expected FROM=TO, e.g. 'old-(.*)=new-$1'".into());
+        }
+        let pattern = Regex::new(&format!("^(?:{from})$")).map_err(|e| e.to_string())?;
+        Ok(NameRewrite {
+            pattern,
+            replacement: to.to_string(),
+        })
+    }
+}
+
+impl NameRewrite {
+    /// The new name for `name`, or `None` if the rule doesn't match it.
+    pub fn apply(&self, name: &str) -> Option<String> {
+        let captures = self.pattern.captures(name)?;
+        let mut renamed = String::new();
+        captures.expand(&self.replacement, &mut renamed);
+        Some(renamed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_rewrite_matches_whole_name() {
+        let rule: NameRewrite = "old-(.*)=new-$1".parse().unwrap();
+        assert_eq!(rule.apply("old-fork").as_deref(), Some("new-fork"));
+        assert_eq!(rule.apply("my-old-fork"), None);
+        assert_eq!(rule.apply("origin"), None);
+
+        let rule: NameRewrite = "(?<who>.+)-mirror=mirror-${who}".parse().unwrap();
+        assert_eq!(rule.apply("alice-mirror").as_deref(), Some("mirror-alice"));
+    }
+
+    #[test]
+    fn name_rewrite_rejects_bad_rules() {
+        assert!("old-*".parse::<NameRewrite>().is_err());
+        assert!("=new".parse::<NameRewrite>().is_err());
+        assert!("old-(=new".parse::<NameRewrite>().is_err());
+    }
+
+    #[test]
+    fn template_org_https() {
+        let name =
+            render_name_template("{org}", "https://github.com/twangodev/gemote.git").unwrap();
+        assert_eq!(name, "twangodev");
+    }
+
+    #[test]
+    fn template_host_org_scp() {
+        let name = render_name_template("{host}-{org}", "git@gitlab.com:acme/tool.git").unwrap();
+        assert_eq!(name, "gitlab.com-acme");
+    }
+
+    #[test]
+    fn template_repo_ssh() {
+        let name =
+            render_name_template("mirror-{repo}", "ssh://git@host:22/team/project.git").unwrap();
+        assert_eq!(name, "mirror-project");
+    }
+
+    #[test]
+    fn template_nested_org() {
+        let name = render_name_template("{org}", "https://gitlab.com/group/sub/repo.git").unwrap();
+        assert_eq!(name, "group-sub");
+    }
+
+    #[test]
+    fn template_missing_component() {
+        let result = render_name_template("{org}", "https://example.com/repo.git");
+        assert!(matches!(result, Err(GemoteError::NameTemplate { .. })));
+    }
+
+    #[test]
+    fn template_unknown_placeholder() {
+        let result = render_name_template("{owner}", "https://github.com/org/repo.git");
+        assert!(matches!(result, Err(GemoteError::NameTemplate { .. })));
+    }
+
+    #[test]
+    fn template_local_path() {
+        let result = render_name_template("{org}", "/srv/git/repo.git");
+        assert!(matches!(result, Err(GemoteError::NameTemplate { .. })));
+    }
+
+    #[test]
+    fn unique_name_free() {
+        let taken = BTreeSet::new();
+        assert_eq!(unique_name("origin", &taken), "origin");
+    }
+
+    #[test]
+    fn unique_name_collision() {
+        let taken: BTreeSet<String> = ["acme".to_string(), "acme-2".to_string()].into();
+        assert_eq!(unique_name("acme", &taken), "acme-3");
+    }
+}
@@ -0,0 +1,95 @@
+use std::cmp::Ordering;
+
+/// Order in which recursive sync and save visit the root and its sub-repos.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RepoOrder {
+    /// Parents before their sub-repos, siblings sorted by path
+    #[default]
+    Path,
+    /// Deepest paths first, the root last
+    DepthFirst,
+    /// Every repo right after its own sub-repos, the root last
+    LeafFirst,
+}
+
+fn components(path: &str) -> Vec<&str> {
+    path.split('/').filter(|c| !c.is_empty()).collect()
+}
+
+/// Compare two repo paths (relative to the top-level repo, `""` for the
+/// root itself) according to `order`.
+pub fn compare(a: &str, b: &str, order: RepoOrder) -> Ordering {
+    let (ca, cb) = (components(a), components(b));
+    match order {
+        RepoOrder::Path => ca.cmp(&cb),
+        RepoOrder::DepthFirst => cb.len().cmp(&ca.len()).then_with(|| ca.cmp(&cb)),
+        RepoOrder::LeafFirst => match ca
+            .iter()
+            .zip(&cb)
+            .map(|(x, y)| x.cmp(y))
+            .find(|o| o.is_ne())
+        {
+            Some(o) => o,
+            // One path contains the other: the nested one goes first
+            None => cb.len().cmp(&ca.len()),
+        },
+    }
+}
+
+/// Sort `items` by their repo path in the given order.
+pub fn sort_by_order<T>(items: &mut [T], order: RepoOrder, path: impl Fn(&T) -> &str) {
+    items.sort_by(|a, b| compare(path(a), path(b), order));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(order: RepoOrder) -> Vec<&'static str> {
+        let mut paths = vec!["libs/core/inner", "", "vendor", "libs/core", "libs/util"];
+        sort_by_order(&mut paths, order, |p| p);
+        paths
+    }
+
+    #[test]
+    fn path_order() {
+        assert_eq!(
+            sorted(RepoOrder::Path),
+            ["", "libs/core", "libs/core/inner", "libs/util", "vendor"]
+        );
+    }
+
+    #[test]
+    fn depth_first_order() {
+        assert_eq!(
+            sorted(RepoOrder::DepthFirst),
+            ["libs/core/inner", "libs/core", "libs/util", "vendor", ""]
+        );
+    }
+
+    #[test]
+    fn leaf_first_order() {
+        assert_eq!(
+            sorted(RepoOrder::LeafFirst),
+            ["libs/core/inner", "libs/core", "libs/util", "vendor", ""]
+        );
+    }
+
+    #[test]
+    fn depth_first_and_leaf_first_differ() {
+        let mut paths = vec!["", "a", "a/x", "b/y/z"];
+        sort_by_order(&mut paths, RepoOrder::DepthFirst, |p| p);
+        assert_eq!(paths, ["b/y/z", "a/x", "a", ""]);
+
+        sort_by_order(&mut paths, RepoOrder::LeafFirst, |p| p);
+        assert_eq!(paths, ["a/x", "a", "b/y/z", ""]);
+    }
+
+    #[test]
+    fn compares_by_component_not_string() {
+        // "a-b" sorts before "a/x" as a string, but "a/x" lives inside "a"
+        let mut paths = vec!["a-b", "a/x", "a"];
+        sort_by_order(&mut paths, RepoOrder::Path, |p| p);
+        assert_eq!(paths, ["a", "a/x", "a-b"]);
+    }
+}
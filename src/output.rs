@@ -0,0 +1,223 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use colored::Colorize;
+
+use crate::warnings::{Warning, WarningKind, Warnings};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Buffered output for one unit of work (usually one repo).
+///
+/// Lines are kept in the order they were written and only reach the
+/// terminal on `flush`, so the caller decides when and in which order each
+/// repo's output appears.
+#[derive(Debug, Default)]
+pub struct Output {
+    lines: Vec<(Stream, String)>,
+    /// Every warning so far, kept across flushes
+    warnings: Warnings,
+    quiet: bool,
+}
+
+impl Output {
+    /// An output that drops all lines but still records warnings, for
+    /// modes that report through a structured document instead.
+    pub fn quiet() -> Self {
+        Output {
+            quiet: true,
+            ..Default::default()
+        }
+    }
+
+    /// A new, empty output with the same settings, e.g. for one repo of a
+    /// parallel run.
+    pub fn child(&self) -> Self {
+        Output {
+            quiet: self.quiet,
+            ..Default::default()
+        }
+    }
+
+    /// Queue everything from `other` after what is already queued.
+    pub fn append(&mut self, other: Output) {
+        self.lines.extend(other.lines);
+        for Warning {
+            kind,
+            context,
+            message,
+        } in other.warnings
+        {
+            self.warnings.push(kind, context, message);
+        }
+    }
+
+    /// Queue a line for stdout.
+    pub fn line(&mut self, line: impl Display) {
+        if !self.quiet {
+            self.lines.push((Stream::Stdout, line.to_string()));
+        }
+    }
+
+    /// Queue a line for stderr.
+    pub fn error(&mut self, line: impl Display) {
+        if !self.quiet {
+            self.lines.push((Stream::Stderr, line.to_string()));
+        }
+    }
+
+    /// Record a warning and queue a `warning:` line for it on stderr.
+    pub fn warn(&mut self, kind: WarningKind, context: impl Into<String>, message: impl Display) {
+        self.error(format_args!("{} {}", "warning:".yellow().bold(), message));
+        self.warnings.push(kind, context, message.to_string());
+    }
+
+    /// Record and print warnings collected elsewhere, e.g. during discovery.
+    pub fn report(&mut self, warnings: Warnings) {
+        for Warning {
+            kind,
+            context,
+            message,
+        } in warnings
+        {
+            self.warn(kind, context, message);
+        }
+    }
+
+    /// All warnings recorded so far.
+    pub fn warnings(&self) -> &Warnings {
+        &self.warnings
+    }
+
+    /// Write the queued lines to the given streams and clear the buffer.
+    pub fn flush_to(&mut self, stdout: &mut impl Write, stderr: &mut impl Write) -> io::Result<()> {
+        for (stream, line) in self.lines.drain(..) {
+            match stream {
+                Stream::Stdout => writeln!(stdout, "{line}")?,
+                Stream::Stderr => writeln!(stderr, "{line}")?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the queued lines to the process's stdout and stderr.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.flush_to(&mut io::stdout().lock(), &mut io::stderr().lock())
+    }
+
+    /// Everything queued for stdout so far, one line each.
+    #[cfg(test)]
+    pub fn stdout(&self) -> String {
+        self.collect(Stream::Stdout)
+    }
+
+    /// Everything queued for stderr so far, one line each.
+    #[cfg(test)]
+    pub fn stderr(&self) -> String {
+        self.collect(Stream::Stderr)
+    }
+
+    #[cfg(test)]
+    fn collect(&self, stream: Stream) -> String {
+        self.lines
+            .iter()
+            .filter(|(s, _)| *s == stream)
+            .map(|(_, line)| format!("{line}\n"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_streams_apart() {
+        let mut out = Output::default();
+        out.line("one");
+        out.error("two");
+        out.line("three");
+        assert_eq!(out.stdout(), "one\nthree\n");
+        assert_eq!(out.stderr(), "two\n");
+    }
+
+    #[test]
+    fn warn_goes_to_stderr() {
+        let mut out = Output::default();
+        out.warn(WarningKind::ExtraRemote, "", "careful");
+        assert!(out.stdout().is_empty());
+        assert!(out.stderr().contains("warning:"));
+        assert!(out.stderr().contains("careful"));
+    }
+
+    #[test]
+    fn quiet_records_warnings_only() {
+        let mut out = Output::quiet();
+        out.line("hidden");
+        out.warn(WarningKind::ExtraRemote, "", "careful");
+        assert!(out.stdout().is_empty());
+        assert!(out.stderr().is_empty());
+        assert_eq!(out.warnings().len(), 1);
+    }
+
+    #[test]
+    fn warnings_survive_flush() {
+        let mut out = Output::default();
+        out.warn(WarningKind::ExtraRemote, "", "careful");
+        out.flush_to(&mut Vec::new(), &mut Vec::new()).unwrap();
+        assert_eq!(out.warnings().len(), 1);
+    }
+
+    #[test]
+    fn report_prints_and_records() {
+        let mut found = Warnings::default();
+        found.push(WarningKind::UnreadableRepo, "vendor/lib", "could not open");
+        found.push(
+            WarningKind::UninitializedSubmodule,
+            "sub",
+            "not checked out",
+        );
+
+        let mut out = Output::default();
+        out.report(found);
+        assert_eq!(out.stderr().lines().count(), 2);
+        assert!(out.stderr().contains("could not open"));
+        assert_eq!(out.warnings().count(WarningKind::UnreadableRepo), 1);
+    }
+
+    #[test]
+    fn append_keeps_order_and_warnings() {
+        let mut out = Output::quiet();
+        let mut child = out.child();
+        child.warn(WarningKind::ExtraRemote, "libs/core", "careful");
+        out.append(child);
+        assert_eq!(out.warnings().len(), 1);
+        assert!(out.stderr().is_empty());
+
+        let mut out = Output::default();
+        out.line("first");
+        let mut child = out.child();
+        child.line("second");
+        out.append(child);
+        assert_eq!(out.stdout(), "first\nsecond\n");
+    }
+
+    #[test]
+    fn flush_writes_in_order_and_clears() {
+        let mut out = Output::default();
+        out.line("a");
+        out.error("b");
+        out.line("c");
+
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        out.flush_to(&mut stdout, &mut stderr).unwrap();
+        assert_eq!(stdout, b"a\nc\n");
+        assert_eq!(stderr, b"b\n");
+        assert!(out.stdout().is_empty());
+        assert!(out.stderr().is_empty());
+    }
+}
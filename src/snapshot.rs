@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::RemoteConfig;
+use crate::error::GemoteError;
+use crate::git::RemoteInfo;
+
+/// File name of the snapshot, stored inside the repo's git directory.
+pub const SNAPSHOT_FILE: &str = "gemote-last-applied";
+
+/// Remotes of a repo around the most recent sync, written just before the
+/// sync's actions are applied.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Remotes as they were before the sync
+    #[serde(default)]
+    pub before: BTreeMap<String, RemoteConfig>,
+    /// Remotes the sync was expected to leave behind
+    #[serde(default)]
+    pub after: BTreeMap<String, RemoteConfig>,
+}
+
+impl Snapshot {
+    pub fn new(
+        before: &BTreeMap<String, RemoteInfo>,
+        after: &BTreeMap<String, RemoteInfo>,
+    ) -> Self {
+        Snapshot {
+            before: capture(before),
+            after: capture(after),
+        }
+    }
+}
+
+/// How a repo's current remotes relate to its last sync snapshot.
+#[derive(Debug, PartialEq)]
+pub enum SyncState {
+    /// Remotes are what the last sync left behind
+    Applied,
+    /// Remotes are still as they were before the last sync
+    NotApplied,
+    /// Some remotes are in their state from before the last sync and the
+    /// rest in the state after it, as when an apply stops halfway
+    Partial {
+        /// Remotes still in their pre-sync state
+        pending: Vec<String>,
+    },
+    /// Remotes match neither state, e.g. after manual edits
+    Changed,
+}
+
+impl Snapshot {
+    /// Compare `current` against the states recorded around the last sync.
+    pub fn state_of(&self, current: &BTreeMap<String, RemoteInfo>) -> SyncState {
+        let current = capture(current);
+        let hash = fingerprint(&current);
+        if hash == fingerprint(&self.after) {
+            return SyncState::Applied;
+        }
+        if hash == fingerprint(&self.before) {
+            return SyncState::NotApplied;
+        }
+
+        // Partial only if every remote is in one of its two recorded states
+        let names: BTreeSet<&String> = current
+            .keys()
+            .chain(self.before.keys())
+            .chain(self.after.keys())
+            .collect();
+        let mut pending = Vec::new();
+        for name in names {
+            let now = current.get(name);
+            let after = self.after.get(name);
+            if now == after {
+                continue;
+            }
+            if now != self.before.get(name) {
+                return SyncState::Changed;
+            }
+            pending.push(name.clone());
+        }
+        SyncState::Partial { pending }
+    }
+}
+
+/// Stable fingerprint of a set of remotes: FNV-1a over their TOML form, so
+/// it doesn't change between builds the way `std`'s hasher may.
+pub fn fingerprint(remotes: &BTreeMap<String, RemoteConfig>) -> u64 {
+    let text = toml::to_string(remotes).unwrap_or_default();
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn capture(remotes: &BTreeMap<String, RemoteInfo>) -> BTreeMap<String, RemoteConfig> {
+    remotes
+        .iter()
+        .map(|(name, info)| (name.clone(), info.to_config()))
+        .collect()
+}
+
+pub fn snapshot_path(repo: &git2::Repository) -> PathBuf {
+    repo.path().join(SNAPSHOT_FILE)
+}
+
+pub fn write_snapshot(repo: &git2::Repository, snapshot: &Snapshot) -> Result<(), GemoteError> {
+    let content = toml::to_string_pretty(snapshot).map_err(GemoteError::ConfigSerialize)?;
+    std::fs::write(snapshot_path(repo), content)?;
+    Ok(())
+}
+
+pub fn read_snapshot(path: &Path) -> Result<Snapshot, GemoteError> {
+    if !path.exists() {
+        return Err(GemoteError::SnapshotNotFound(path.to_path_buf()));
+    }
+    let contents = std::fs::read_to_string(path)?;
+    toml::from_str(&contents).map_err(GemoteError::SnapshotParse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(url: &str, push_url: Option<&str>) -> RemoteInfo {
+        RemoteInfo {
+            url: url.into(),
+            extra_urls: Vec::new(),
+            push_url: push_url.map(Into::into),
+            extra_push_urls: Vec::new(),
+            prune: None,
+            tagopt: None,
+            head: None,
+        }
+    }
+
+    #[test]
+    fn write_then_read() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+
+        let before = BTreeMap::from([("origin".to_string(), info("https://old.com/r.git", None))]);
+        let after = BTreeMap::from([(
+            "origin".to_string(),
+            info("https://new.com/r.git", Some("git@new.com:r.git")),
+        )]);
+        write_snapshot(&repo, &Snapshot::new(&before, &after)).unwrap();
+
+        let snapshot = read_snapshot(&snapshot_path(&repo)).unwrap();
+        assert_eq!(snapshot.before["origin"].url, "https://old.com/r.git");
+        assert!(snapshot.before["origin"].push_url.is_none());
+        assert_eq!(snapshot.after["origin"].url, "https://new.com/r.git");
+        assert_eq!(
+            snapshot.after["origin"].push_url.as_deref(),
+            Some("git@new.com:r.git")
+        );
+    }
+
+    fn remotes(entries: &[(&str, &str)]) -> BTreeMap<String, RemoteInfo> {
+        entries
+            .iter()
+            .map(|(name, url)| (name.to_string(), info(url, None)))
+            .collect()
+    }
+
+    #[test]
+    fn fingerprint_is_stable() {
+        let a = capture(&remotes(&[("origin", "https://a.com/r.git")]));
+        let b = capture(&remotes(&[("origin", "https://a.com/r.git")]));
+        let c = capture(&remotes(&[("origin", "https://b.com/r.git")]));
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+        assert_ne!(fingerprint(&a), fingerprint(&c));
+    }
+
+    #[test]
+    fn state_of_applied_and_not_applied() {
+        let before = remotes(&[("origin", "https://old.com/r.git")]);
+        let after = remotes(&[("origin", "https://new.com/r.git")]);
+        let snapshot = Snapshot::new(&before, &after);
+
+        assert_eq!(snapshot.state_of(&after), SyncState::Applied);
+        assert_eq!(snapshot.state_of(&before), SyncState::NotApplied);
+    }
+
+    #[test]
+    fn state_of_partial() {
+        let before = remotes(&[
+            ("origin", "https://old.com/r.git"),
+            ("stale", "https://stale.com/r.git"),
+        ]);
+        let after = remotes(&[
+            ("origin", "https://new.com/r.git"),
+            ("upstream", "https://up.com/r.git"),
+        ]);
+        let snapshot = Snapshot::new(&before, &after);
+
+        // origin was updated, but upstream wasn't added nor stale removed
+        let current = remotes(&[
+            ("origin", "https://new.com/r.git"),
+            ("stale", "https://stale.com/r.git"),
+        ]);
+        assert_eq!(
+            snapshot.state_of(&current),
+            SyncState::Partial {
+                pending: vec!["stale".into(), "upstream".into()]
+            }
+        );
+    }
+
+    #[test]
+    fn state_of_changed() {
+        let before = remotes(&[("origin", "https://old.com/r.git")]);
+        let after = remotes(&[("origin", "https://new.com/r.git")]);
+        let snapshot = Snapshot::new(&before, &after);
+
+        let current = remotes(&[("origin", "https://elsewhere.com/r.git")]);
+        assert_eq!(snapshot.state_of(&current), SyncState::Changed);
+    }
+
+    #[test]
+    fn read_missing() {
+        let result = read_snapshot(Path::new("/nonexistent/gemote-last-applied"));
+        assert!(matches!(result, Err(GemoteError::SnapshotNotFound(_))));
+    }
+
+    #[test]
+    fn snapshot_lives_in_git_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        assert_eq!(snapshot_path(&repo), repo.path().join(SNAPSHOT_FILE));
+    }
+}
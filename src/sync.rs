@@ -1,22 +1,33 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 
 use colored::Colorize;
+use serde::Serialize;
 
-use crate::config::{ExtraRemotes, GemoteConfig};
+use crate::config::{ExtraRemotes, GemoteConfig, RemoteConfig};
 use crate::error::GemoteError;
-use crate::git::{self, RemoteInfo};
+use crate::git::{self, Backend, RemoteInfo};
+use crate::naming::NameRewrite;
+use crate::url::{rewrite_fetch_url, rewrite_push_url, url_lists_match, urls_match};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
 pub enum SyncAction {
     Add {
         name: String,
         url: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        extra_urls: Vec<String>,
         push_url: Option<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        extra_push_urls: Vec<String>,
     },
     UpdateUrl {
         name: String,
+        // Same `old`/`new` keys as the other updates in the JSON plan
+        #[serde(rename = "old")]
         old_url: String,
+        #[serde(rename = "new")]
         new_url: String,
     },
     UpdatePushUrl {
@@ -24,47 +35,152 @@ pub enum SyncAction {
         old: Option<String>,
         new: Option<String>,
     },
+    /// Replace the whole URL list of a remote with more than one URL
+    UpdateUrls {
+        name: String,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    /// Replace the whole push URL list of a remote with more than one push
+    /// URL
+    UpdatePushUrls {
+        name: String,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    SetOption {
+        name: String,
+        option: RemoteOption,
+        old: Option<String>,
+        new: String,
+    },
+    /// Replace the prefixes a `url.<base>.insteadOf` rule rewrites
+    SetInsteadOf {
+        base: String,
+        old: Vec<String>,
+        new: String,
+    },
+    /// Rename a local remote in place, keeping its refspecs and
+    /// remote-tracking branches
+    Rename {
+        old_name: String,
+        name: String,
+    },
     Remove {
         name: String,
     },
 }
 
+/// Per-remote git settings that sync manages besides URLs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteOption {
+    Prune,
+    Tagopt,
+}
+
+impl RemoteOption {
+    /// The key under `remote.<name>.` in git config.
+    pub fn key(self) -> &'static str {
+        match self {
+            RemoteOption::Prune => "prune",
+            RemoteOption::Tagopt => "tagopt",
+        }
+    }
+}
+
+/// Coarse grouping of actions, used to apply only some kinds of change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ActionKind {
+    Add,
+    Update,
+    Remove,
+}
+
+impl SyncAction {
+    pub fn kind(&self) -> ActionKind {
+        match self {
+            SyncAction::Add { .. } => ActionKind::Add,
+            SyncAction::UpdateUrl { .. }
+            | SyncAction::UpdatePushUrl { .. }
+            | SyncAction::UpdateUrls { .. }
+            | SyncAction::UpdatePushUrls { .. }
+            | SyncAction::SetOption { .. }
+            | SyncAction::SetInsteadOf { .. }
+            | SyncAction::Rename { .. } => ActionKind::Update,
+            SyncAction::Remove { .. } => ActionKind::Remove,
+        }
+    }
+
+    /// The remote this action applies to, or the base URL of an insteadOf
+    /// rule.
+    pub fn name(&self) -> &str {
+        match self {
+            SyncAction::SetInsteadOf { base, .. } => base,
+            SyncAction::Add { name, .. }
+            | SyncAction::UpdateUrl { name, .. }
+            | SyncAction::UpdatePushUrl { name, .. }
+            | SyncAction::UpdateUrls { name, .. }
+            | SyncAction::UpdatePushUrls { name, .. }
+            | SyncAction::SetOption { name, .. }
+            | SyncAction::Rename { name, .. }
+            | SyncAction::Remove { name } => name,
+        }
+    }
+}
+
 impl fmt::Display for SyncAction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SyncAction::Add {
                 name,
                 url,
+                extra_urls,
                 push_url,
+                extra_push_urls,
             } => {
                 write!(f, "{} remote {} (url: {})", "add".green(), name.bold(), url)?;
+                if !extra_urls.is_empty() {
+                    write!(f, " (extra_urls: {})", extra_urls.join(", "))?;
+                }
                 if let Some(pu) = push_url {
                     write!(f, " (push_url: {pu})")?;
                 }
+                if !extra_push_urls.is_empty() {
+                    write!(f, " (extra_push_urls: {})", extra_push_urls.join(", "))?;
+                }
                 Ok(())
             }
-            SyncAction::UpdateUrl {
-                name,
-                old_url,
-                new_url,
-            } => {
+            SyncAction::UpdateUrl { name, .. }
+            | SyncAction::UpdatePushUrl { name, .. }
+            | SyncAction::UpdateUrls { name, .. }
+            | SyncAction::UpdatePushUrls { name, .. }
+            | SyncAction::SetOption { name, .. } => {
+                let change = self.change().unwrap_or_default();
+                write!(f, "{} remote {} {}", "update".yellow(), name.bold(), change)
+            }
+            SyncAction::SetInsteadOf { base, old, new } => {
+                let old = if old.is_empty() {
+                    "(unset)".to_string()
+                } else {
+                    old.join(", ")
+                };
                 write!(
                     f,
-                    "{} remote {} url: {} -> {}",
+                    "{} url.{}.insteadOf: {} -> {}",
                     "update".yellow(),
-                    name.bold(),
-                    old_url,
-                    new_url
+                    base.bold(),
+                    old,
+                    new
                 )
             }
-            SyncAction::UpdatePushUrl { name, old, new } => {
+            SyncAction::Rename { old_name, name } => {
                 write!(
                     f,
-                    "{} remote {} push_url: {} -> {}",
-                    "update".yellow(),
-                    name.bold(),
-                    old.as_deref().unwrap_or("(none)"),
-                    new.as_deref().unwrap_or("(none)")
+                    "{} remote {} -> {}",
+                    "rename".yellow(),
+                    old_name.bold(),
+                    name.bold()
                 )
             }
             SyncAction::Remove { name } => {
@@ -77,33 +193,60 @@ impl fmt::Display for SyncAction {
 pub fn compute_diff(
     config: &GemoteConfig,
     local: &BTreeMap<String, RemoteInfo>,
+) -> Vec<SyncAction> {
+    compute_diff_with(config, local, config.settings.rename_detection)
+}
+
+/// Like `compute_diff`, with rename detection chosen by the caller. When
+/// `detect_renames` is set and extra remotes are removed, a local remote
+/// missing from the config whose URLs match a remote the config would add
+/// is renamed instead of being removed and re-added.
+pub fn compute_diff_with(
+    config: &GemoteConfig,
+    local: &BTreeMap<String, RemoteInfo>,
+    detect_renames: bool,
 ) -> Vec<SyncAction> {
     let mut actions = Vec::new();
+    let mut renamed = BTreeSet::new();
 
     // Check config remotes against local
     for (name, rc) in &config.remotes {
+        let wanted = Wanted::new(rc, config);
         match local.get(name) {
+            Some(local_remote) => diff_remote(name, wanted, local_remote, config, &mut actions),
             None => {
-                actions.push(SyncAction::Add {
-                    name: name.clone(),
-                    url: rc.url.clone(),
-                    push_url: rc.push_url.clone(),
+                let source = local.iter().find(|(old_name, info)| {
+                    detect_renames
+                        && config.settings.extra_remotes() == ExtraRemotes::Remove
+                        && !config.remotes.contains_key(*old_name)
+                        && !renamed.contains(*old_name)
+                        && url_lists_match(&info.urls(), &wanted.urls(), &config.settings)
                 });
-            }
-            Some(local_remote) => {
-                if local_remote.url != rc.url {
-                    actions.push(SyncAction::UpdateUrl {
+                if let Some((old_name, local_remote)) = source {
+                    renamed.insert(old_name.clone());
+                    actions.push(SyncAction::Rename {
+                        old_name: old_name.clone(),
                         name: name.clone(),
-                        old_url: local_remote.url.clone(),
-                        new_url: rc.url.clone(),
                     });
+                    diff_remote(name, wanted, local_remote, config, &mut actions);
+                    continue;
                 }
-                if local_remote.push_url != rc.push_url {
-                    actions.push(SyncAction::UpdatePushUrl {
-                        name: name.clone(),
-                        old: local_remote.push_url.clone(),
-                        new: rc.push_url.clone(),
-                    });
+                actions.push(SyncAction::Add {
+                    name: name.clone(),
+                    url: wanted.url,
+                    extra_urls: wanted.extra_urls,
+                    push_url: wanted.push_url,
+                    extra_push_urls: wanted.extra_push_urls,
+                });
+                for (option, new) in wanted.options {
+                    if let Some(new) = new {
+                        actions.push(SyncAction::SetOption {
+                            name: name.clone(),
+                            option,
+                            old: None,
+                            new,
+                        });
+                    }
                 }
             }
         }
@@ -111,16 +254,10 @@ pub fn compute_diff(
 
     // Check local remotes not in config
     for name in local.keys() {
-        if !config.remotes.contains_key(name) {
-            match config.settings.extra_remotes {
-                ExtraRemotes::Ignore => {}
-                ExtraRemotes::Warn => {
-                    eprintln!(
-                        "{} remote '{}' exists locally but not in config",
-                        "warning:".yellow().bold(),
-                        name
-                    );
-                }
+        if !config.remotes.contains_key(name) && !renamed.contains(name) {
+            match config.settings.extra_remotes() {
+                // Reported by the caller, see `unmanaged_remotes`
+                ExtraRemotes::Ignore | ExtraRemotes::Warn => {}
                 ExtraRemotes::Remove => {
                     actions.push(SyncAction::Remove { name: name.clone() });
                 }
@@ -131,15 +268,681 @@ pub fn compute_diff(
     actions
 }
 
-pub fn apply_actions(repo: &git2::Repository, actions: &[SyncAction]) -> Result<(), GemoteError> {
+/// A config remote with its URLs rewritten, as it should end up locally.
+struct Wanted {
+    url: String,
+    extra_urls: Vec<String>,
+    push_url: Option<String>,
+    extra_push_urls: Vec<String>,
+    options: [(RemoteOption, Option<String>); 2],
+}
+
+impl Wanted {
+    fn new(rc: &RemoteConfig, config: &GemoteConfig) -> Self {
+        let defaults = &config.settings.remote_defaults;
+        Wanted {
+            url: rewrite_fetch_url(&rc.url, &config.settings),
+            extra_urls: rc
+                .extra_urls
+                .iter()
+                .map(|u| rewrite_fetch_url(u, &config.settings))
+                .collect(),
+            push_url: rc
+                .push_url
+                .as_deref()
+                .map(|pu| rewrite_push_url(pu, &config.settings)),
+            extra_push_urls: rc
+                .extra_push_urls
+                .iter()
+                .map(|pu| rewrite_push_url(pu, &config.settings))
+                .collect(),
+            options: [
+                (
+                    RemoteOption::Prune,
+                    rc.prune.or(defaults.prune).map(|p| p.to_string()),
+                ),
+                (
+                    RemoteOption::Tagopt,
+                    rc.tagopt.clone().or_else(|| defaults.tagopt.clone()),
+                ),
+            ],
+        }
+    }
+
+    fn urls(&self) -> Vec<String> {
+        std::iter::once(self.url.clone())
+            .chain(self.extra_urls.iter().cloned())
+            .collect()
+    }
+
+    fn push_urls(&self) -> Vec<String> {
+        self.push_url
+            .iter()
+            .chain(&self.extra_push_urls)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Push the actions that bring `local_remote` in line with `wanted`.
+fn diff_remote(
+    name: &str,
+    wanted: Wanted,
+    local_remote: &RemoteInfo,
+    config: &GemoteConfig,
+    actions: &mut Vec<SyncAction>,
+) {
+    if wanted.extra_urls.is_empty() && local_remote.extra_urls.is_empty() {
+        if !urls_match(&local_remote.url, &wanted.url, &config.settings) {
+            actions.push(SyncAction::UpdateUrl {
+                name: name.to_string(),
+                old_url: local_remote.url.clone(),
+                new_url: wanted.url.clone(),
+            });
+        }
+    } else {
+        let old = local_remote.urls();
+        let new = wanted.urls();
+        if !url_lists_match(&old, &new, &config.settings) {
+            actions.push(SyncAction::UpdateUrls {
+                name: name.to_string(),
+                old,
+                new,
+            });
+        }
+    }
+    if wanted.extra_push_urls.is_empty() && local_remote.extra_push_urls.is_empty() {
+        let push_matches = match (&local_remote.push_url, &wanted.push_url) {
+            (Some(a), Some(b)) => urls_match(a, b, &config.settings),
+            (None, None) => true,
+            _ => false,
+        };
+        if !push_matches {
+            actions.push(SyncAction::UpdatePushUrl {
+                name: name.to_string(),
+                old: local_remote.push_url.clone(),
+                new: wanted.push_url.clone(),
+            });
+        }
+    } else {
+        let old = local_remote.push_urls();
+        let new = wanted.push_urls();
+        if !url_lists_match(&old, &new, &config.settings) {
+            actions.push(SyncAction::UpdatePushUrls {
+                name: name.to_string(),
+                old,
+                new,
+            });
+        }
+    }
+    for (option, new) in wanted.options {
+        // An option the config leaves unset is not managed
+        let Some(new) = new else { continue };
+        let old = match option {
+            RemoteOption::Prune => local_remote.prune.map(|p| p.to_string()),
+            RemoteOption::Tagopt => local_remote.tagopt.clone(),
+        };
+        if old.as_deref() != Some(new.as_str()) {
+            actions.push(SyncAction::SetOption {
+                name: name.to_string(),
+                option,
+                old,
+                new,
+            });
+        }
+    }
+}
+
+/// Local remotes that have no entry in the config.
+pub fn unmanaged_remotes<'a>(
+    config: &GemoteConfig,
+    local: &'a BTreeMap<String, RemoteInfo>,
+) -> Vec<&'a str> {
+    local
+        .keys()
+        .filter(|name| !config.remotes.contains_key(*name))
+        .map(String::as_str)
+        .collect()
+}
+
+impl SyncAction {
+    /// The `key: old -> new` part of an update to a single remote setting,
+    /// or `None` for actions that add, rename or remove a whole remote.
+    fn change(&self) -> Option<String> {
+        let list = |urls: &[String]| {
+            if urls.is_empty() {
+                "(none)".to_string()
+            } else {
+                urls.join(", ")
+            }
+        };
+        let change = match self {
+            SyncAction::UpdateUrl {
+                old_url, new_url, ..
+            } => format!("url: {old_url} -> {new_url}"),
+            SyncAction::UpdatePushUrl { old, new, .. } => format!(
+                "push_url: {} -> {}",
+                old.as_deref().unwrap_or("(none)"),
+                new.as_deref().unwrap_or("(none)")
+            ),
+            SyncAction::UpdateUrls { old, new, .. } => {
+                format!("urls: {} -> {}", old.join(", "), new.join(", "))
+            }
+            SyncAction::UpdatePushUrls { old, new, .. } => {
+                format!("push_urls: {} -> {}", list(old), list(new))
+            }
+            SyncAction::SetOption {
+                option, old, new, ..
+            } => format!(
+                "{}: {} -> {}",
+                option.key(),
+                old.as_deref().unwrap_or("(unset)"),
+                new
+            ),
+            _ => return None,
+        };
+        Some(change)
+    }
+}
+
+impl SyncAction {
+    /// The `git` command line that makes this change, with `&&` between
+    /// the steps when there are several, as shown by `sync --dry-run -v`.
+    pub fn as_git_command(&self) -> String {
+        let git = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| shell_quote(arg)).collect();
+            format!("git {}", args.join(" "))
+        };
+        // Mirrors how `--use-git-cli` rewrites a multi-valued key
+        let replace_all = |key: &str, values: &[String]| {
+            std::iter::once(git(&["config", "--unset-all", key]))
+                .chain(values.iter().map(|v| git(&["config", "--add", key, v])))
+                .collect::<Vec<_>>()
+        };
+        let commands = match self {
+            SyncAction::Add {
+                name,
+                url,
+                extra_urls,
+                push_url,
+                extra_push_urls,
+            } => std::iter::once(git(&["remote", "add", name, url]))
+                .chain(
+                    extra_urls
+                        .iter()
+                        .map(|u| git(&["remote", "set-url", "--add", name, u])),
+                )
+                .chain(
+                    push_url
+                        .iter()
+                        .chain(extra_push_urls)
+                        .map(|u| git(&["remote", "set-url", "--add", "--push", name, u])),
+                )
+                .collect(),
+            SyncAction::UpdateUrl { name, new_url, .. } => {
+                vec![git(&["remote", "set-url", name, new_url])]
+            }
+            SyncAction::UpdatePushUrl { name, new, .. } => match new {
+                Some(new) => vec![git(&["remote", "set-url", "--push", name, new])],
+                None => vec![git(&[
+                    "config",
+                    "--unset",
+                    &format!("remote.{name}.pushurl"),
+                ])],
+            },
+            SyncAction::UpdateUrls { name, new, .. } => {
+                replace_all(&format!("remote.{name}.url"), new)
+            }
+            SyncAction::UpdatePushUrls { name, new, .. } => {
+                replace_all(&format!("remote.{name}.pushurl"), new)
+            }
+            SyncAction::SetOption {
+                name, option, new, ..
+            } => vec![git(&[
+                "config",
+                &format!("remote.{name}.{}", option.key()),
+                new,
+            ])],
+            SyncAction::SetInsteadOf { base, new, .. } => vec![git(&[
+                "config",
+                "--replace-all",
+                &format!("url.{base}.insteadOf"),
+                new,
+            ])],
+            SyncAction::Rename { old_name, name } => {
+                vec![git(&["remote", "rename", old_name, name])]
+            }
+            SyncAction::Remove { name } => vec![git(&["remote", "remove", name])],
+        };
+        commands.join(" && ")
+    }
+}
+
+/// Quote `arg` for a POSIX shell, leaving it bare when that's safe.
+fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./_-~".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Render `actions` (sorted for display) one per line, except that several
+/// updates to the same remote are merged into one block: an `update remote`
+/// line followed by each changed setting, so the net change reads at once.
+pub fn render_grouped(actions: &[SyncAction]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for group in actions.chunk_by(|a, b| a.name() == b.name()) {
+        let changes: Option<Vec<String>> = group.iter().map(SyncAction::change).collect();
+        match changes {
+            Some(changes) if group.len() > 1 => {
+                lines.push(format!(
+                    "{} remote {}",
+                    "update".yellow(),
+                    group[0].name().bold()
+                ));
+                lines.extend(changes.into_iter().map(|change| format!("  {change}")));
+            }
+            _ => lines.extend(group.iter().map(SyncAction::to_string)),
+        }
+    }
+    lines
+}
+
+/// Sort `actions` for display: remotes with a `priority` first, lowest
+/// first, then the rest by name. Actions on the same remote keep their
+/// order. This doesn't change the order actions are applied in.
+pub fn sort_for_display(actions: &mut [SyncAction], config: &GemoteConfig) {
+    actions.sort_by_key(|action| display_key(config, action.name()));
+}
+
+/// Sort key that lists remote `name` in priority order, as
+/// `sort_for_display` does.
+pub fn display_key(config: &GemoteConfig, name: &str) -> (bool, Option<i32>, String) {
+    let priority = config.remotes.get(name).and_then(|rc| rc.priority);
+    (priority.is_none(), priority, name.to_string())
+}
+
+/// Keep only actions whose kind is in `kinds`. An empty list keeps everything.
+pub fn filter_by_kind(actions: Vec<SyncAction>, kinds: &[ActionKind]) -> Vec<SyncAction> {
+    if kinds.is_empty() {
+        return actions;
+    }
+    actions
+        .into_iter()
+        .filter(|action| kinds.contains(&action.kind()))
+        .collect()
+}
+
+/// Actions that bring the repo's `url.<base>.insteadOf` rules in line with
+/// `settings.insteadof`. Rules the config doesn't mention are left alone.
+pub fn diff_insteadof(
+    config: &GemoteConfig,
+    current: &BTreeMap<String, Vec<String>>,
+) -> Vec<SyncAction> {
+    config
+        .settings
+        .insteadof
+        .iter()
+        .filter_map(|(base, prefix)| {
+            let old = current.get(base).cloned().unwrap_or_default();
+            (old != [prefix.clone()]).then(|| SyncAction::SetInsteadOf {
+                base: base.clone(),
+                old,
+                new: prefix.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Which of a remote's URLs a sync may change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlScope {
+    Fetch,
+    Push,
+}
+
+/// Keep only the URL changes in `scope`. Option changes, renames and
+/// removals are dropped. With `Fetch`, adds keep their fetch URLs but lose
+/// the push URL; with `Push`, adds are dropped, since a remote can't be
+/// created without a fetch URL.
+pub fn filter_by_url_scope(actions: Vec<SyncAction>, scope: UrlScope) -> Vec<SyncAction> {
+    actions
+        .into_iter()
+        .filter_map(|action| match (scope, action) {
+            (
+                UrlScope::Fetch,
+                SyncAction::Add {
+                    name,
+                    url,
+                    extra_urls,
+                    ..
+                },
+            ) => Some(SyncAction::Add {
+                name,
+                url,
+                extra_urls,
+                push_url: None,
+                extra_push_urls: Vec::new(),
+            }),
+            (
+                UrlScope::Fetch,
+                action @ (SyncAction::UpdateUrl { .. } | SyncAction::UpdateUrls { .. }),
+            )
+            | (
+                UrlScope::Push,
+                action @ (SyncAction::UpdatePushUrl { .. } | SyncAction::UpdatePushUrls { .. }),
+            ) => Some(action),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Plan renaming each remote in `local` whose name `rewrite` matches, and
+/// carry the renames out on `local` and on config remotes under the old
+/// name, so the rest of the diff sees the new names. A rename onto a name
+/// that is already taken or isn't a valid remote name is left out and
+/// described in the returned messages.
+pub fn rewrite_remote_names(
+    config: &mut GemoteConfig,
+    local: &mut BTreeMap<String, RemoteInfo>,
+    rewrite: &NameRewrite,
+) -> (Vec<SyncAction>, Vec<String>) {
+    let planned: Vec<(String, String)> = local
+        .keys()
+        .filter_map(|old| {
+            let new = rewrite.apply(old)?;
+            (new != *old).then(|| (old.clone(), new))
+        })
+        .collect();
+    let mut renames = Vec::new();
+    let mut skipped = Vec::new();
+    for (old, new) in planned {
+        let problem = if !git2::Remote::is_valid_name(&new)
+            || crate::config::reserved_name_reason(&new).is_some()
+        {
+            Some("not a valid remote name")
+        } else if local.contains_key(&new) {
+            Some("a remote with that name already exists")
+        } else if config.remotes.contains_key(&new) && config.remotes.contains_key(&old) {
+            Some("the config has a remote under each name")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            skipped.push(format!("not renaming remote '{old}' to '{new}': {problem}"));
+            continue;
+        }
+        if let Some(info) = local.remove(&old) {
+            local.insert(new.clone(), info);
+        }
+        if let Some(rc) = config.remotes.remove(&old) {
+            config.remotes.insert(new.clone(), rc);
+        }
+        renames.push(SyncAction::Rename {
+            old_name: old,
+            name: new,
+        });
+    }
+    (renames, skipped)
+}
+
+/// Point every remote with a URL listed as an old URL in `map` at the new
+/// URL instead, for a one-off migration. Config remotes are rewritten in
+/// place. Local remotes the config doesn't manage are taken into it with
+/// their URLs mapped, unless extra remotes are being removed anyway.
+pub fn apply_url_map(
+    config: &mut GemoteConfig,
+    local: &BTreeMap<String, RemoteInfo>,
+    map: &BTreeMap<String, String>,
+) {
+    if map.is_empty() {
+        return;
+    }
+    let settings = config.settings.clone();
+    let mapped = |url: &mut String| {
+        let new = map
+            .iter()
+            .find(|(old, _)| urls_match(url, old, &settings))
+            .map(|(_, new)| new.clone());
+        let changed = new.is_some();
+        if let Some(new) = new {
+            *url = new;
+        }
+        changed
+    };
+    let map_remote = |rc: &mut RemoteConfig| {
+        let mut changed = mapped(&mut rc.url);
+        for url in rc
+            .extra_urls
+            .iter_mut()
+            .chain(rc.push_url.iter_mut())
+            .chain(rc.extra_push_urls.iter_mut())
+        {
+            changed |= mapped(url);
+        }
+        changed
+    };
+
+    for rc in config.remotes.values_mut() {
+        map_remote(rc);
+    }
+    if config.settings.extra_remotes() == ExtraRemotes::Remove {
+        return;
+    }
+    for (name, info) in local {
+        if config.remotes.contains_key(name) {
+            continue;
+        }
+        let mut rc = info.to_config();
+        if map_remote(&mut rc) {
+            config.remotes.insert(name.clone(), rc);
+        }
+    }
+}
+
+/// The remotes `local` would have once `actions` are applied.
+pub fn simulate(
+    local: &BTreeMap<String, RemoteInfo>,
+    actions: &[SyncAction],
+) -> BTreeMap<String, RemoteInfo> {
+    let mut result = local.clone();
+    for action in actions {
+        match action {
+            SyncAction::Add {
+                name,
+                url,
+                extra_urls,
+                push_url,
+                extra_push_urls,
+            } => {
+                result.insert(
+                    name.clone(),
+                    RemoteInfo {
+                        url: url.clone(),
+                        extra_urls: extra_urls.clone(),
+                        push_url: push_url.clone(),
+                        extra_push_urls: extra_push_urls.clone(),
+                        prune: None,
+                        tagopt: None,
+                        head: None,
+                    },
+                );
+            }
+            SyncAction::UpdateUrl { name, new_url, .. } => {
+                if let Some(info) = result.get_mut(name) {
+                    info.url = new_url.clone();
+                }
+            }
+            SyncAction::UpdatePushUrl { name, new, .. } => {
+                if let Some(info) = result.get_mut(name) {
+                    info.push_url = new.clone();
+                }
+            }
+            SyncAction::UpdatePushUrls { name, new, .. } => {
+                if let Some(info) = result.get_mut(name) {
+                    info.push_url = new.first().cloned();
+                    info.extra_push_urls = new.iter().skip(1).cloned().collect();
+                }
+            }
+            SyncAction::UpdateUrls { name, new, .. } => {
+                if let Some(info) = result.get_mut(name)
+                    && let Some((first, rest)) = new.split_first()
+                {
+                    info.url = first.clone();
+                    info.extra_urls = rest.to_vec();
+                }
+            }
+            SyncAction::SetOption {
+                name, option, new, ..
+            } => {
+                if let Some(info) = result.get_mut(name) {
+                    match option {
+                        RemoteOption::Prune => info.prune = new.parse().ok(),
+                        RemoteOption::Tagopt => info.tagopt = Some(new.clone()),
+                    }
+                }
+            }
+            // Not part of any remote
+            SyncAction::SetInsteadOf { .. } => {}
+            SyncAction::Rename { old_name, name } => {
+                if let Some(info) = result.remove(old_name) {
+                    result.insert(name.clone(), info);
+                }
+            }
+            SyncAction::Remove { name } => {
+                result.remove(name);
+            }
+        }
+    }
+    result
+}
+
+pub fn apply_actions(
+    repo: &git2::Repository,
+    actions: &[SyncAction],
+    backend: Backend,
+) -> Result<(), GemoteError> {
+    apply_actions_with_callback(repo, actions, backend, |_| ActionDecision::Continue)
+}
+
+/// What `apply_actions_with_callback` should do with the next action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionDecision {
+    /// Apply it and move on
+    Continue,
+    /// Leave it out and move on
+    Skip,
+    /// Stop before it; earlier actions stay applied
+    Abort,
+}
+
+/// Apply `actions` in order, asking `callback` about each one first, as
+/// `sync --select` does to let the user veto single actions. URL lists and
+/// remote options are written through `backend`.
+pub fn apply_actions_with_callback(
+    repo: &git2::Repository,
+    actions: &[SyncAction],
+    backend: Backend,
+    mut callback: impl FnMut(&SyncAction) -> ActionDecision,
+) -> Result<(), GemoteError> {
+    let mut config = repo.config()?;
+    for action in actions {
+        match callback(action) {
+            ActionDecision::Continue => match backend {
+                Backend::Git2 => apply_actions_to(repo, &mut config, std::slice::from_ref(action))?,
+                Backend::GitCli => apply_action_git_cli(repo, action)?,
+            },
+            ActionDecision::Skip => {}
+            ActionDecision::Abort => {
+                return Err(match action {
+                    SyncAction::SetInsteadOf { base, .. } => {
+                        GemoteError::InsteadOfAborted(base.clone())
+                    }
+                    _ => GemoteError::ApplyAborted(action.name().to_string()),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Apply one action with its URL lists and remote options written by the
+/// `git` CLI. Adding, updating and removing the remotes themselves still goes
+/// through libgit2.
+fn apply_action_git_cli(repo: &git2::Repository, action: &SyncAction) -> Result<(), GemoteError> {
+    match action {
+        SyncAction::Add {
+            name,
+            url,
+            extra_urls,
+            push_url,
+            extra_push_urls,
+        } => {
+            git::add_remote(repo, name, url, push_url.as_deref())?;
+            if !extra_urls.is_empty() {
+                let all: Vec<String> = std::iter::once(url.clone())
+                    .chain(extra_urls.iter().cloned())
+                    .collect();
+                git::set_remote_urls_cli(repo, name, &all)?;
+            }
+            if !extra_push_urls.is_empty() {
+                let all: Vec<String> = push_url.iter().chain(extra_push_urls).cloned().collect();
+                git::set_remote_push_urls_cli(repo, name, &all)?;
+            }
+        }
+        SyncAction::UpdateUrls { name, new, .. } => {
+            git::set_remote_urls_cli(repo, name, new)?;
+        }
+        SyncAction::UpdatePushUrls { name, new, .. } => {
+            git::set_remote_push_urls_cli(repo, name, new)?;
+        }
+        SyncAction::SetOption {
+            name, option, new, ..
+        } => {
+            git::set_remote_option_cli(repo, name, option.key(), new)?;
+        }
+        _ => apply_actions_to(repo, &mut repo.config()?, std::slice::from_ref(action))?,
+    }
+    Ok(())
+}
+
+/// Apply `actions`, writing URL lists and remote options to `config`
+/// instead of the repo's own config. Adding, updating and removing the
+/// remotes themselves still goes through `repo`.
+pub fn apply_actions_to(
+    repo: &git2::Repository,
+    config: &mut git2::Config,
+    actions: &[SyncAction],
+) -> Result<(), GemoteError> {
     for action in actions {
         match action {
             SyncAction::Add {
                 name,
                 url,
+                extra_urls,
                 push_url,
+                extra_push_urls,
             } => {
                 git::add_remote(repo, name, url, push_url.as_deref())?;
+                if !extra_urls.is_empty() {
+                    let all: Vec<String> = std::iter::once(url.clone())
+                        .chain(extra_urls.iter().cloned())
+                        .collect();
+                    git::set_remote_urls(config, name, &all)?;
+                }
+                if !extra_push_urls.is_empty() {
+                    let all: Vec<String> =
+                        push_url.iter().chain(extra_push_urls).cloned().collect();
+                    git::set_remote_push_urls(config, name, &all)?;
+                }
+            }
+            SyncAction::UpdateUrls { name, new, .. } => {
+                git::set_remote_urls(config, name, new)?;
+            }
+            SyncAction::UpdatePushUrls { name, new, .. } => {
+                git::set_remote_push_urls(config, name, new)?;
             }
             SyncAction::UpdateUrl { name, new_url, .. } => {
                 git::update_remote_url(repo, name, new_url)?;
@@ -147,6 +950,17 @@ pub fn apply_actions(repo: &git2::Repository, actions: &[SyncAction]) -> Result<
             SyncAction::UpdatePushUrl { name, new, .. } => {
                 git::update_remote_push_url(repo, name, new.as_deref())?;
             }
+            SyncAction::SetOption {
+                name, option, new, ..
+            } => {
+                git::set_remote_option(config, name, option.key(), new)?;
+            }
+            SyncAction::SetInsteadOf { base, new, .. } => {
+                git::set_insteadof(config, base, new)?;
+            }
+            SyncAction::Rename { old_name, name } => {
+                git::rename_remote(repo, old_name, name)?;
+            }
             SyncAction::Remove { name } => {
                 git::remove_remote(repo, name)?;
             }
@@ -163,10 +977,10 @@ mod tests {
     fn make_config(extra: ExtraRemotes, remotes: Vec<(&str, &str, Option<&str>)>) -> GemoteConfig {
         let mut cfg = GemoteConfig {
             settings: Settings {
-                extra_remotes: extra,
+                extra_remotes: Some(extra),
+                ..Default::default()
             },
-            remotes: BTreeMap::new(),
-            submodules: BTreeMap::new(),
+            ..Default::default()
         };
         for (name, url, push_url) in remotes {
             cfg.remotes.insert(
@@ -174,6 +988,7 @@ mod tests {
                 RemoteConfig {
                     url: url.into(),
                     push_url: push_url.map(Into::into),
+                    ..Default::default()
                 },
             );
         }
@@ -187,7 +1002,12 @@ mod tests {
                 name.into(),
                 RemoteInfo {
                     url: url.into(),
+                    extra_urls: Vec::new(),
                     push_url: push_url.map(Into::into),
+                    extra_push_urls: Vec::new(),
+                    prune: None,
+                    tagopt: None,
+                    head: None,
                 },
             );
         }
@@ -215,7 +1035,7 @@ mod tests {
         assert_eq!(actions.len(), 1);
         assert!(matches!(
             &actions[0],
-            SyncAction::Add { name, url, push_url }
+            SyncAction::Add { name, url, push_url, .. }
             if name == "origin" && url == "https://example.com/repo.git" && push_url.is_none()
         ));
     }
@@ -332,67 +1152,733 @@ mod tests {
     }
 
     #[test]
-    fn diff_extra_ignore() {
-        let cfg = make_config(ExtraRemotes::Ignore, vec![]);
-        let local = make_local(vec![("extra", "https://extra.com/repo.git", None)]);
+    fn diff_git_suffix_added_in_config() {
+        let cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![("origin", "https://host/repo.git", Some("git@host:repo.git"))],
+        );
+        let local = make_local(vec![("origin", "https://host/repo", Some("git@host:repo"))]);
         assert!(compute_diff(&cfg, &local).is_empty());
     }
 
     #[test]
-    fn diff_extra_warn() {
-        let cfg = make_config(ExtraRemotes::Warn, vec![]);
-        let local = make_local(vec![("extra", "https://extra.com/repo.git", None)]);
-        // Warn produces no actions (only stderr output)
+    fn diff_git_suffix_removed_in_config() {
+        let cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![("origin", "https://host/repo", None)],
+        );
+        let local = make_local(vec![("origin", "https://host/repo.git", None)]);
         assert!(compute_diff(&cfg, &local).is_empty());
     }
 
     #[test]
-    fn diff_extra_remove() {
-        let cfg = make_config(ExtraRemotes::Remove, vec![]);
-        let local = make_local(vec![("extra", "https://extra.com/repo.git", None)]);
+    fn diff_git_suffix_strict() {
+        let mut cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![("origin", "https://host/repo.git", None)],
+        );
+        cfg.settings.strict_suffix = true;
+        let local = make_local(vec![("origin", "https://host/repo", None)]);
         let actions = compute_diff(&cfg, &local);
 
         assert_eq!(actions.len(), 1);
         assert!(matches!(
             &actions[0],
-            SyncAction::Remove { name } if name == "extra"
+            SyncAction::UpdateUrl { new_url, .. } if new_url == "https://host/repo.git"
         ));
     }
 
     #[test]
-    fn diff_complex() {
+    fn diff_ignores_whitespace_and_trailing_slash() {
         let cfg = make_config(
-            ExtraRemotes::Remove,
+            ExtraRemotes::Ignore,
             vec![
-                ("origin", "https://new-origin.com/repo.git", None),
-                ("upstream", "https://upstream.com/repo.git", None),
+                ("origin", " https://example.com/repo.git/\n", None),
+                ("upstream", "https://example.com/up.git ", None),
             ],
         );
-        let local = make_local(vec![
-            ("origin", "https://old-origin.com/repo.git", None), // URL mismatch -> Update
-            ("stale", "https://stale.com/repo.git", None),       // not in config -> Remove
-                                                                 // upstream missing from local -> Add
-        ]);
+        let local = make_local(vec![("origin", "https://example.com/repo.git", None)]);
         let actions = compute_diff(&cfg, &local);
 
-        assert_eq!(actions.len(), 3);
-        assert!(
-            actions
-                .iter()
-                .any(|a| matches!(a, SyncAction::UpdateUrl { name, .. } if name == "origin"))
-        );
-        assert!(
-            actions
-                .iter()
-                .any(|a| matches!(a, SyncAction::Add { name, .. } if name == "upstream"))
-        );
-        assert!(
-            actions
+        // Only the missing remote is added, with the trimmed URL
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(
+            &actions[0],
+            SyncAction::Add { name, url, .. }
+            if name == "upstream" && url == "https://example.com/up.git"
+        ));
+    }
+
+    #[test]
+    fn diff_separate_fetch_and_push_rewrites() {
+        let mut cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![(
+                "origin",
+                "https://github.com/org/repo.git",
+                Some("https://github.com/org/repo.git"),
+            )],
+        );
+        cfg.settings
+            .rewrites
+            .insert("https://github.com/".into(), "https://mirror.com/".into());
+        cfg.settings
+            .push_rewrites
+            .insert("https://github.com/".into(), "git@github.com:".into());
+
+        let actions = compute_diff(&cfg, &make_local(vec![]));
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(
+            &actions[0],
+            SyncAction::Add { url, push_url, .. }
+            if url == "https://mirror.com/org/repo.git"
+              && push_url.as_deref() == Some("git@github.com:org/repo.git")
+        ));
+
+        // Already rewritten locally: nothing to do
+        let local = make_local(vec![(
+            "origin",
+            "https://mirror.com/org/repo.git",
+            Some("git@github.com:org/repo.git"),
+        )]);
+        assert!(compute_diff(&cfg, &local).is_empty());
+    }
+
+    #[test]
+    fn diff_extra_ignore() {
+        let cfg = make_config(ExtraRemotes::Ignore, vec![]);
+        let local = make_local(vec![("extra", "https://extra.com/repo.git", None)]);
+        assert!(compute_diff(&cfg, &local).is_empty());
+    }
+
+    #[test]
+    fn diff_extra_warn() {
+        let cfg = make_config(ExtraRemotes::Warn, vec![]);
+        let local = make_local(vec![("extra", "https://extra.com/repo.git", None)]);
+        // Warn produces no actions; the caller reports the extra remote
+        assert!(compute_diff(&cfg, &local).is_empty());
+        assert_eq!(unmanaged_remotes(&cfg, &local), ["extra"]);
+    }
+
+    #[test]
+    fn diff_extra_remove() {
+        let cfg = make_config(ExtraRemotes::Remove, vec![]);
+        let local = make_local(vec![("extra", "https://extra.com/repo.git", None)]);
+        let actions = compute_diff(&cfg, &local);
+
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(
+            &actions[0],
+            SyncAction::Remove { name } if name == "extra"
+        ));
+    }
+
+    fn rename_input() -> (GemoteConfig, BTreeMap<String, RemoteInfo>) {
+        let cfg = make_config(
+            ExtraRemotes::Remove,
+            vec![(
+                "upstream",
+                "https://example.com/repo",
+                Some("git@example.com:repo.git"),
+            )],
+        );
+        let local = make_local(vec![("old", "https://example.com/repo.git", None)]);
+        (cfg, local)
+    }
+
+    #[test]
+    fn diff_rename_detection_off() {
+        let (cfg, local) = rename_input();
+        let actions = compute_diff_with(&cfg, &local, false);
+
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(&actions[0], SyncAction::Add { name, .. } if name == "upstream"));
+        assert!(matches!(&actions[1], SyncAction::Remove { name } if name == "old"));
+    }
+
+    #[test]
+    fn diff_rename_detection_on() {
+        let (cfg, local) = rename_input();
+        let actions = compute_diff_with(&cfg, &local, true);
+
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(
+            &actions[0],
+            SyncAction::Rename { old_name, name } if old_name == "old" && name == "upstream"
+        ));
+        // The renamed remote is then brought in line with the config
+        assert!(matches!(
+            &actions[1],
+            SyncAction::UpdatePushUrl { name, .. } if name == "upstream"
+        ));
+    }
+
+    #[test]
+    fn diff_rename_detection_from_settings() {
+        let (mut cfg, local) = rename_input();
+        cfg.settings.rename_detection = true;
+        let actions = compute_diff(&cfg, &local);
+        assert!(matches!(&actions[0], SyncAction::Rename { .. }));
+    }
+
+    #[test]
+    fn diff_rename_needs_extra_remove() {
+        let (mut cfg, local) = rename_input();
+        // Without removal the old remote would have been kept, so keep it
+        cfg.settings.extra_remotes = Some(ExtraRemotes::Ignore);
+        let actions = compute_diff_with(&cfg, &local, true);
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(&actions[0], SyncAction::Add { .. }));
+    }
+
+    #[test]
+    fn sort_for_display_honors_priority() {
+        let mut cfg = make_config(
+            ExtraRemotes::Remove,
+            vec![
+                ("alpha-mirror", "https://mirror-a.com/repo.git", None),
+                ("origin", "https://origin.com/repo.git", None),
+                ("upstream", "https://upstream.com/repo.git", None),
+                ("zeta-mirror", "https://mirror-z.com/repo.git", None),
+            ],
+        );
+        cfg.remotes.get_mut("origin").unwrap().priority = Some(1);
+        cfg.remotes.get_mut("upstream").unwrap().priority = Some(2);
+        cfg.remotes.get_mut("origin").unwrap().push_url = Some("git@origin.com:repo.git".into());
+        let local = make_local(vec![
+            ("origin", "https://old.com/repo.git", None),
+            ("beta", "https://beta.com/repo.git", None),
+        ]);
+
+        let mut actions = compute_diff(&cfg, &local);
+        sort_for_display(&mut actions, &cfg);
+
+        let order: Vec<(&str, ActionKind)> = actions.iter().map(|a| (a.name(), a.kind())).collect();
+        assert_eq!(
+            order,
+            [
+                // origin's two updates stay in their original order
+                ("origin", ActionKind::Update),
+                ("origin", ActionKind::Update),
+                ("upstream", ActionKind::Add),
+                ("alpha-mirror", ActionKind::Add),
+                ("beta", ActionKind::Remove),
+                ("zeta-mirror", ActionKind::Add),
+            ]
+        );
+        assert!(matches!(&actions[0], SyncAction::UpdateUrl { .. }));
+        assert!(matches!(&actions[1], SyncAction::UpdatePushUrl { .. }));
+    }
+
+    #[test]
+    fn diff_complex() {
+        let cfg = make_config(
+            ExtraRemotes::Remove,
+            vec![
+                ("origin", "https://new-origin.com/repo.git", None),
+                ("upstream", "https://upstream.com/repo.git", None),
+            ],
+        );
+        let local = make_local(vec![
+            ("origin", "https://old-origin.com/repo.git", None), // URL mismatch -> Update
+            ("stale", "https://stale.com/repo.git", None),       // not in config -> Remove
+                                                                 // upstream missing from local -> Add
+        ]);
+        let actions = compute_diff(&cfg, &local);
+
+        assert_eq!(actions.len(), 3);
+        assert!(
+            actions
+                .iter()
+                .any(|a| matches!(a, SyncAction::UpdateUrl { name, .. } if name == "origin"))
+        );
+        assert!(
+            actions
+                .iter()
+                .any(|a| matches!(a, SyncAction::Add { name, .. } if name == "upstream"))
+        );
+        assert!(
+            actions
                 .iter()
                 .any(|a| matches!(a, SyncAction::Remove { name } if name == "stale"))
         );
     }
 
+    #[test]
+    fn diff_sets_options_on_new_remote() {
+        let mut cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![("origin", "https://a.com", None)],
+        );
+        cfg.remotes.get_mut("origin").unwrap().prune = Some(true);
+        let local = make_local(vec![]);
+        let actions = compute_diff(&cfg, &local);
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(&actions[0], SyncAction::Add { name, .. } if name == "origin"));
+        assert!(matches!(
+            &actions[1],
+            SyncAction::SetOption { option: RemoteOption::Prune, old: None, new, .. } if new == "true"
+        ));
+    }
+
+    #[test]
+    fn diff_updates_changed_option() {
+        let mut cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![("origin", "https://a.com", None)],
+        );
+        cfg.remotes.get_mut("origin").unwrap().tagopt = Some("--no-tags".into());
+        let mut local = make_local(vec![("origin", "https://a.com", None)]);
+        local.get_mut("origin").unwrap().tagopt = Some("--tags".into());
+        let actions = compute_diff(&cfg, &local);
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(
+            &actions[0],
+            SyncAction::SetOption { option: RemoteOption::Tagopt, old: Some(old), new, .. }
+                if old == "--tags" && new == "--no-tags"
+        ));
+        assert_eq!(actions[0].kind(), ActionKind::Update);
+    }
+
+    #[test]
+    fn diff_ignores_unset_options() {
+        let cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![("origin", "https://a.com", None)],
+        );
+        let mut local = make_local(vec![("origin", "https://a.com", None)]);
+        local.get_mut("origin").unwrap().prune = Some(false);
+        assert!(compute_diff(&cfg, &local).is_empty());
+    }
+
+    #[test]
+    fn diff_matching_option_no_action() {
+        let mut cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![("origin", "https://a.com", None)],
+        );
+        cfg.remotes.get_mut("origin").unwrap().prune = Some(true);
+        let mut local = make_local(vec![("origin", "https://a.com", None)]);
+        local.get_mut("origin").unwrap().prune = Some(true);
+        assert!(compute_diff(&cfg, &local).is_empty());
+    }
+
+    #[test]
+    fn diff_uses_remote_defaults_for_unset_options() {
+        let mut cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![
+                ("origin", "https://a.com", None),
+                ("upstream", "https://b.com", None),
+            ],
+        );
+        cfg.settings.remote_defaults.prune = Some(true);
+        cfg.remotes.get_mut("upstream").unwrap().prune = Some(false);
+        let mut local = make_local(vec![
+            ("origin", "https://a.com", None),
+            ("upstream", "https://b.com", None),
+        ]);
+        local.get_mut("upstream").unwrap().prune = Some(false);
+        let actions = compute_diff(&cfg, &local);
+        // A remote's own value wins over the default
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(
+            &actions[0],
+            SyncAction::SetOption { name, option: RemoteOption::Prune, new, .. }
+                if name == "origin" && new == "true"
+        ));
+    }
+
+    fn multi_url_case(order_significant: Option<bool>) -> Vec<SyncAction> {
+        let mut cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![("origin", "https://a.com", None)],
+        );
+        cfg.settings.url_order_significant = order_significant;
+        cfg.remotes.get_mut("origin").unwrap().extra_urls = vec!["https://b.com".into()];
+        let mut local = make_local(vec![("origin", "https://b.com", None)]);
+        local.get_mut("origin").unwrap().extra_urls = vec!["https://a.com".into()];
+        compute_diff(&cfg, &local)
+    }
+
+    #[test]
+    fn diff_multi_url_reordered_is_update_by_default() {
+        let actions = multi_url_case(None);
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(
+            &actions[0],
+            SyncAction::UpdateUrls { old, new, .. }
+                if old == &["https://b.com", "https://a.com"] && new == &["https://a.com", "https://b.com"]
+        ));
+    }
+
+    #[test]
+    fn diff_multi_url_reordered_matches_when_order_not_significant() {
+        assert!(multi_url_case(Some(false)).is_empty());
+        assert_eq!(multi_url_case(Some(true)).len(), 1);
+    }
+
+    #[test]
+    fn diff_adds_extra_url() {
+        let mut cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![("origin", "https://a.com", None)],
+        );
+        cfg.remotes.get_mut("origin").unwrap().extra_urls = vec!["https://b.com".into()];
+        let local = make_local(vec![("origin", "https://a.com", None)]);
+        let actions = compute_diff(&cfg, &local);
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(&actions[0], SyncAction::UpdateUrls { new, .. } if new.len() == 2));
+    }
+
+    #[test]
+    fn diff_push_url_list() {
+        let mut cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![("origin", "https://a.com", Some("git@a.com:r"))],
+        );
+        cfg.remotes.get_mut("origin").unwrap().extra_push_urls = vec!["git@b.com:r".into()];
+        let local = make_local(vec![("origin", "https://a.com", Some("git@a.com:r"))]);
+        let actions = compute_diff(&cfg, &local);
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(
+            &actions[0],
+            SyncAction::UpdatePushUrls { old, new, .. }
+                if old == &["git@a.com:r"] && new == &["git@a.com:r", "git@b.com:r"]
+        ));
+
+        let after = simulate(&local, &actions);
+        assert!(compute_diff(&cfg, &after).is_empty());
+    }
+
+    #[test]
+    fn render_grouped_merges_updates_per_remote() {
+        colored::control::set_override(false);
+        let actions = vec![
+            SyncAction::UpdateUrl {
+                name: "origin".into(),
+                old_url: "https://old.com/r.git".into(),
+                new_url: "https://new.com/r.git".into(),
+            },
+            SyncAction::UpdatePushUrl {
+                name: "origin".into(),
+                old: None,
+                new: Some("git@new.com:r.git".into()),
+            },
+            SyncAction::Remove {
+                name: "stale".into(),
+            },
+        ];
+        assert_eq!(
+            render_grouped(&actions),
+            [
+                "update remote origin",
+                "  url: https://old.com/r.git -> https://new.com/r.git",
+                "  push_url: (none) -> git@new.com:r.git",
+                "remove remote stale",
+            ]
+        );
+    }
+
+    #[test]
+    fn as_git_command_renders_every_action_kind() {
+        let cases = [
+            (
+                SyncAction::Add {
+                    name: "origin".into(),
+                    url: "https://a.com/r.git".into(),
+                    extra_urls: vec!["https://b.com/r.git".into()],
+                    push_url: Some("git@a.com:r.git".into()),
+                    extra_push_urls: vec![],
+                },
+                "git remote add origin https://a.com/r.git \
+                 && git remote set-url --add origin https://b.com/r.git \
+                 && git remote set-url --add --push origin git@a.com:r.git",
+            ),
+            (
+                SyncAction::UpdateUrl {
+                    name: "origin".into(),
+                    old_url: "https://old.com/r.git".into(),
+                    new_url: "https://new.com/r.git".into(),
+                },
+                "git remote set-url origin https://new.com/r.git",
+            ),
+            (
+                SyncAction::UpdatePushUrl {
+                    name: "origin".into(),
+                    old: None,
+                    new: Some("git@new.com:r.git".into()),
+                },
+                "git remote set-url --push origin git@new.com:r.git",
+            ),
+            (
+                SyncAction::UpdatePushUrl {
+                    name: "origin".into(),
+                    old: Some("git@old.com:r.git".into()),
+                    new: None,
+                },
+                "git config --unset remote.origin.pushurl",
+            ),
+            (
+                SyncAction::UpdateUrls {
+                    name: "origin".into(),
+                    old: vec!["https://a.com/r".into()],
+                    new: vec!["https://a.com/r".into(), "https://b.com/r".into()],
+                },
+                "git config --unset-all remote.origin.url \
+                 && git config --add remote.origin.url https://a.com/r \
+                 && git config --add remote.origin.url https://b.com/r",
+            ),
+            (
+                SyncAction::UpdatePushUrls {
+                    name: "origin".into(),
+                    old: vec!["git@a.com:r".into(), "git@b.com:r".into()],
+                    new: vec![],
+                },
+                "git config --unset-all remote.origin.pushurl",
+            ),
+            (
+                SyncAction::SetOption {
+                    name: "origin".into(),
+                    option: RemoteOption::Tagopt,
+                    old: None,
+                    new: "--no-tags".into(),
+                },
+                "git config remote.origin.tagopt --no-tags",
+            ),
+            (
+                SyncAction::SetInsteadOf {
+                    base: "https://github.com/".into(),
+                    old: vec![],
+                    new: "gh:".into(),
+                },
+                "git config --replace-all url.https://github.com/.insteadOf gh:",
+            ),
+            (
+                SyncAction::Rename {
+                    old_name: "upstream".into(),
+                    name: "mirror".into(),
+                },
+                "git remote rename upstream mirror",
+            ),
+            (
+                SyncAction::Remove {
+                    name: "stale".into(),
+                },
+                "git remote remove stale",
+            ),
+        ];
+        for (action, expected) in cases {
+            assert_eq!(action.as_git_command(), expected);
+        }
+    }
+
+    #[test]
+    fn as_git_command_quotes_for_the_shell() {
+        let action = SyncAction::UpdateUrl {
+            name: "origin".into(),
+            old_url: String::new(),
+            new_url: "/srv/my repos/it's.git".into(),
+        };
+        assert_eq!(
+            action.as_git_command(),
+            r"git remote set-url origin '/srv/my repos/it'\''s.git'"
+        );
+    }
+
+    // --- diff_insteadof tests ---
+
+    #[test]
+    fn insteadof_drift() {
+        let mut cfg = GemoteConfig::default();
+        cfg.settings
+            .insteadof
+            .insert("git@github.com:".into(), "https://github.com/".into());
+        cfg.settings
+            .insteadof
+            .insert("git@gitlab.com:".into(), "https://gitlab.com/".into());
+        let current = BTreeMap::from([
+            (
+                "git@github.com:".to_string(),
+                vec!["https://github.com/".to_string()],
+            ),
+            ("git@gitlab.com:".to_string(), vec!["gl:".to_string()]),
+            ("git@other.com:".to_string(), vec!["o:".to_string()]),
+        ]);
+
+        let actions = diff_insteadof(&cfg, &current);
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(
+            &actions[0],
+            SyncAction::SetInsteadOf { base, old, new }
+            if base == "git@gitlab.com:" && old == &["gl:"] && new == "https://gitlab.com/"
+        ));
+        assert!(diff_insteadof(&cfg, &BTreeMap::new()).len() == 2);
+    }
+
+    #[test]
+    fn apply_insteadof() {
+        let (_dir, repo) = test_repo();
+        repo.config()
+            .unwrap()
+            .set_multivar("url.git@github.com:.insteadOf", "^$", "gh:")
+            .unwrap();
+        let actions = vec![SyncAction::SetInsteadOf {
+            base: "git@github.com:".into(),
+            old: vec!["gh:".into()],
+            new: "https://github.com/".into(),
+        }];
+        apply_actions(&repo, &actions, Backend::Git2).unwrap();
+
+        let rules = git::list_insteadof(&repo).unwrap();
+        assert_eq!(rules["git@github.com:"], ["https://github.com/"]);
+    }
+
+    // --- filter_by_url_scope tests ---
+
+    #[test]
+    fn url_scope_push_keeps_push_url_updates() {
+        let actions = filter_by_url_scope(mixed_actions(), UrlScope::Push);
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(
+            &actions[0],
+            SyncAction::UpdatePushUrl { name, new: Some(new), .. }
+            if name == "origin" && new == "git@new.com:repo.git"
+        ));
+    }
+
+    #[test]
+    fn url_scope_fetch_keeps_fetch_urls() {
+        let mut cfg = make_config(
+            ExtraRemotes::Remove,
+            vec![
+                (
+                    "origin",
+                    "https://new.com/repo.git",
+                    Some("git@new.com:repo.git"),
+                ),
+                (
+                    "upstream",
+                    "https://upstream.com/repo.git",
+                    Some("git@upstream.com:repo.git"),
+                ),
+            ],
+        );
+        cfg.remotes.get_mut("origin").unwrap().prune = Some(true);
+        let local = make_local(vec![
+            ("origin", "https://old.com/repo.git", None),
+            ("stale", "https://stale.com/repo.git", None),
+        ]);
+
+        let actions = filter_by_url_scope(compute_diff(&cfg, &local), UrlScope::Fetch);
+        assert_eq!(actions.len(), 2);
+        assert!(actions.iter().any(|a| matches!(
+            a,
+            SyncAction::UpdateUrl { name, new_url, .. }
+            if name == "origin" && new_url == "https://new.com/repo.git"
+        )));
+        assert!(actions.iter().any(|a| matches!(
+            a,
+            SyncAction::Add { name, push_url: None, .. } if name == "upstream"
+        )));
+    }
+
+    // --- filter_by_kind tests ---
+
+    fn mixed_actions() -> Vec<SyncAction> {
+        let cfg = make_config(
+            ExtraRemotes::Remove,
+            vec![
+                (
+                    "origin",
+                    "https://new.com/repo.git",
+                    Some("git@new.com:repo.git"),
+                ),
+                ("upstream", "https://upstream.com/repo.git", None),
+            ],
+        );
+        let local = make_local(vec![
+            ("origin", "https://old.com/repo.git", None),
+            ("stale", "https://stale.com/repo.git", None),
+        ]);
+        compute_diff(&cfg, &local)
+    }
+
+    #[test]
+    fn filter_no_kinds_keeps_all() {
+        let actions = filter_by_kind(mixed_actions(), &[]);
+        assert_eq!(actions.len(), 4);
+    }
+
+    #[test]
+    fn filter_add_only() {
+        let actions = filter_by_kind(mixed_actions(), &[ActionKind::Add]);
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(&actions[0], SyncAction::Add { name, .. } if name == "upstream"));
+    }
+
+    #[test]
+    fn filter_update_only() {
+        let actions = filter_by_kind(mixed_actions(), &[ActionKind::Update]);
+        assert_eq!(actions.len(), 2);
+        assert!(actions.iter().all(|a| a.name() == "origin"));
+        assert!(
+            actions
+                .iter()
+                .any(|a| matches!(a, SyncAction::UpdateUrl { .. }))
+        );
+        assert!(
+            actions
+                .iter()
+                .any(|a| matches!(a, SyncAction::UpdatePushUrl { .. }))
+        );
+    }
+
+    #[test]
+    fn filter_remove_only() {
+        let actions = filter_by_kind(mixed_actions(), &[ActionKind::Remove]);
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(&actions[0], SyncAction::Remove { name } if name == "stale"));
+    }
+
+    #[test]
+    fn filter_multiple_kinds() {
+        let actions = filter_by_kind(mixed_actions(), &[ActionKind::Add, ActionKind::Remove]);
+        assert_eq!(actions.len(), 2);
+        assert!(actions.iter().all(|a| a.kind() != ActionKind::Update));
+    }
+
+    // --- simulate tests ---
+
+    #[test]
+    fn simulate_matches_config() {
+        let cfg = make_config(
+            ExtraRemotes::Remove,
+            vec![
+                (
+                    "origin",
+                    "https://new.com/repo.git",
+                    Some("git@new.com:repo.git"),
+                ),
+                ("upstream", "https://upstream.com/repo.git", None),
+            ],
+        );
+        let local = make_local(vec![
+            ("origin", "https://old.com/repo.git", None),
+            ("stale", "https://stale.com/repo.git", None),
+        ]);
+        let after = simulate(&local, &compute_diff(&cfg, &local));
+
+        assert_eq!(after.len(), 2);
+        assert_eq!(after["origin"].url, "https://new.com/repo.git");
+        assert_eq!(
+            after["origin"].push_url.as_deref(),
+            Some("git@new.com:repo.git")
+        );
+        assert_eq!(after["upstream"].url, "https://upstream.com/repo.git");
+        assert!(!after.contains_key("stale"));
+    }
+
     // --- apply_actions tests ---
 
     fn test_repo() -> (tempfile::TempDir, git2::Repository) {
@@ -404,7 +1890,7 @@ mod tests {
     #[test]
     fn apply_empty() {
         let (_dir, repo) = test_repo();
-        apply_actions(&repo, &[]).unwrap();
+        apply_actions(&repo, &[], Backend::Git2).unwrap();
         assert!(repo.remotes().unwrap().is_empty());
     }
 
@@ -414,23 +1900,94 @@ mod tests {
         let actions = vec![SyncAction::Add {
             name: "origin".into(),
             url: "https://example.com/repo.git".into(),
+            extra_urls: Vec::new(),
             push_url: None,
+            extra_push_urls: Vec::new(),
         }];
-        apply_actions(&repo, &actions).unwrap();
+        apply_actions(&repo, &actions, Backend::Git2).unwrap();
 
         let remote = repo.find_remote("origin").unwrap();
         assert_eq!(remote.url().unwrap(), "https://example.com/repo.git");
     }
 
+    fn add(name: &str) -> SyncAction {
+        SyncAction::Add {
+            name: name.into(),
+            url: format!("https://example.com/{name}.git"),
+            extra_urls: Vec::new(),
+            push_url: None,
+            extra_push_urls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn apply_callback_skips_action() {
+        let (_dir, repo) = test_repo();
+        let actions = vec![add("origin"), add("upstream"), add("fork")];
+        let mut seen = Vec::new();
+        apply_actions_with_callback(&repo, &actions, Backend::Git2, |action| {
+            seen.push(action.name().to_string());
+            if action.name() == "upstream" {
+                ActionDecision::Skip
+            } else {
+                ActionDecision::Continue
+            }
+        })
+        .unwrap();
+
+        assert_eq!(seen, ["origin", "upstream", "fork"]);
+        assert!(repo.find_remote("origin").is_ok());
+        assert!(repo.find_remote("upstream").is_err());
+        assert!(repo.find_remote("fork").is_ok());
+    }
+
+    #[test]
+    fn apply_callback_aborts() {
+        let (_dir, repo) = test_repo();
+        let actions = vec![add("origin"), add("upstream"), add("fork")];
+        let result = apply_actions_with_callback(&repo, &actions, Backend::Git2, |action| {
+            if action.name() == "upstream" {
+                ActionDecision::Abort
+            } else {
+                ActionDecision::Continue
+            }
+        });
+
+        assert!(matches!(result, Err(GemoteError::ApplyAborted(name)) if name == "upstream"));
+        assert!(repo.find_remote("origin").is_ok());
+        assert!(repo.find_remote("upstream").is_err());
+        assert!(repo.find_remote("fork").is_err());
+    }
+
+    #[test]
+    fn apply_callback_abort_names_insteadof_rule() {
+        let (_dir, repo) = test_repo();
+        let actions = vec![SyncAction::SetInsteadOf {
+            base: "git@github.com:".into(),
+            old: Vec::new(),
+            new: "https://github.com/".into(),
+        }];
+        let err =
+            apply_actions_with_callback(&repo, &actions, Backend::Git2, |_| ActionDecision::Abort)
+                .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "sync aborted before changing insteadOf rule 'url.git@github.com:.insteadOf'"
+        );
+    }
+
     #[test]
     fn apply_add_with_push_url() {
         let (_dir, repo) = test_repo();
         let actions = vec![SyncAction::Add {
             name: "origin".into(),
             url: "https://example.com/repo.git".into(),
+            extra_urls: Vec::new(),
             push_url: Some("git@example.com:repo.git".into()),
+            extra_push_urls: Vec::new(),
         }];
-        apply_actions(&repo, &actions).unwrap();
+        apply_actions(&repo, &actions, Backend::Git2).unwrap();
 
         let remote = repo.find_remote("origin").unwrap();
         assert_eq!(remote.url().unwrap(), "https://example.com/repo.git");
@@ -447,7 +2004,7 @@ mod tests {
             old_url: "https://old.com/repo.git".into(),
             new_url: "https://new.com/repo.git".into(),
         }];
-        apply_actions(&repo, &actions).unwrap();
+        apply_actions(&repo, &actions, Backend::Git2).unwrap();
 
         let remote = repo.find_remote("origin").unwrap();
         assert_eq!(remote.url().unwrap(), "https://new.com/repo.git");
@@ -464,7 +2021,7 @@ mod tests {
             old: None,
             new: Some("git@example.com:repo.git".into()),
         }];
-        apply_actions(&repo, &actions).unwrap();
+        apply_actions(&repo, &actions, Backend::Git2).unwrap();
 
         let remote = repo.find_remote("origin").unwrap();
         assert_eq!(remote.pushurl().unwrap(), "git@example.com:repo.git");
@@ -479,8 +2036,183 @@ mod tests {
         let actions = vec![SyncAction::Remove {
             name: "origin".into(),
         }];
-        apply_actions(&repo, &actions).unwrap();
+        apply_actions(&repo, &actions, Backend::Git2).unwrap();
 
         assert!(repo.find_remote("origin").is_err());
     }
+
+    #[test]
+    fn apply_rename() {
+        let (_dir, repo) = test_repo();
+        repo.remote("old", "https://example.com/repo.git").unwrap();
+
+        let actions = vec![SyncAction::Rename {
+            old_name: "old".into(),
+            name: "upstream".into(),
+        }];
+        apply_actions(&repo, &actions, Backend::Git2).unwrap();
+
+        assert!(repo.find_remote("old").is_err());
+        let remote = repo.find_remote("upstream").unwrap();
+        assert_eq!(remote.url().unwrap(), "https://example.com/repo.git");
+        assert_eq!(
+            remote.fetch_refspecs().unwrap().get(0),
+            Some("+refs/heads/*:refs/remotes/upstream/*")
+        );
+    }
+
+    fn git_available() -> bool {
+        std::process::Command::new("git")
+            .arg("--version")
+            .output()
+            .is_ok_and(|o| o.status.success())
+    }
+
+    #[test]
+    fn git_cli_backend_matches_git2() {
+        if !git_available() {
+            return;
+        }
+        let actions = vec![
+            SyncAction::Add {
+                name: "origin".into(),
+                url: "https://example.com/repo.git".into(),
+                extra_urls: vec!["https://mirror.com/repo.git".into()],
+                push_url: Some("git@example.com:repo.git".into()),
+                extra_push_urls: vec!["git@mirror.com:repo.git".into()],
+            },
+            SyncAction::SetOption {
+                name: "origin".into(),
+                option: RemoteOption::Prune,
+                old: None,
+                new: "true".into(),
+            },
+            SyncAction::SetOption {
+                name: "origin".into(),
+                option: RemoteOption::Tagopt,
+                old: None,
+                new: "--no-tags".into(),
+            },
+        ];
+
+        let (_git2_dir, git2_repo) = test_repo();
+        apply_actions(&git2_repo, &actions, Backend::Git2).unwrap();
+        let (_cli_dir, cli_repo) = test_repo();
+        apply_actions(&cli_repo, &actions, Backend::GitCli).unwrap();
+
+        let expected = git::list_remotes(&git2_repo).unwrap();
+        let actual = git::list_remotes(&cli_repo).unwrap();
+        assert_eq!(actual, expected);
+        assert_eq!(
+            actual["origin"].urls(),
+            [
+                "https://example.com/repo.git",
+                "https://mirror.com/repo.git"
+            ]
+        );
+        assert_eq!(actual["origin"].tagopt.as_deref(), Some("--no-tags"));
+
+        // Replacing the list drops the old values
+        let update = vec![SyncAction::UpdateUrls {
+            name: "origin".into(),
+            old: Vec::new(),
+            new: vec!["https://other.com/repo.git".into()],
+        }];
+        apply_actions(&cli_repo, &update, Backend::GitCli).unwrap();
+        let actual = git::list_remotes(&cli_repo).unwrap();
+        assert_eq!(actual["origin"].urls(), ["https://other.com/repo.git"]);
+    }
+
+    #[test]
+    fn apply_settings_to_isolated_config() {
+        let (dir, repo) = test_repo();
+        let path = dir.path().join("isolated.config");
+        let mut config = git2::Config::open(&path).unwrap();
+
+        let actions = vec![
+            SyncAction::UpdateUrls {
+                name: "origin".into(),
+                old: Vec::new(),
+                new: vec![
+                    "https://example.com/repo.git".into(),
+                    "https://mirror.com/repo.git".into(),
+                ],
+            },
+            SyncAction::SetOption {
+                name: "origin".into(),
+                option: RemoteOption::Prune,
+                old: None,
+                new: "true".into(),
+            },
+            SyncAction::SetOption {
+                name: "origin".into(),
+                option: RemoteOption::Tagopt,
+                old: None,
+                new: "--no-tags".into(),
+            },
+        ];
+        apply_actions_to(&repo, &mut config, &actions).unwrap();
+
+        let written = git2::Config::open(&path).unwrap();
+        let mut urls = Vec::new();
+        written
+            .multivar("remote.origin.url", None)
+            .unwrap()
+            .for_each(|entry| urls.push(entry.value().unwrap().to_string()))
+            .unwrap();
+        assert_eq!(
+            urls,
+            [
+                "https://example.com/repo.git",
+                "https://mirror.com/repo.git"
+            ]
+        );
+        assert!(written.get_bool("remote.origin.prune").unwrap());
+        assert_eq!(
+            written.get_string("remote.origin.tagopt").unwrap(),
+            "--no-tags"
+        );
+
+        // The repo's own config is untouched
+        assert!(repo.find_remote("origin").is_err());
+        assert!(
+            repo.config()
+                .unwrap()
+                .get_entry("remote.origin.prune")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn rewrite_remote_names_skips_collisions() {
+        let mut cfg = make_config(
+            ExtraRemotes::Ignore,
+            vec![("old-a", "https://example.com/a.git", None)],
+        );
+        let mut local = make_local(vec![
+            ("old-a", "https://example.com/a.git", None),
+            ("old-b", "https://example.com/b.git", None),
+            ("old-c", "https://example.com/c.git", None),
+            ("new-c", "https://example.com/other.git", None),
+            ("origin", "https://example.com/repo.git", None),
+        ]);
+        let rule: NameRewrite = "old-(.*)=new-$1".parse().unwrap();
+
+        let (renames, skipped) = rewrite_remote_names(&mut cfg, &mut local, &rule);
+        let pairs: Vec<_> = renames
+            .iter()
+            .map(|a| match a {
+                SyncAction::Rename { old_name, name } => (old_name.as_str(), name.as_str()),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(pairs, [("old-a", "new-a"), ("old-b", "new-b")]);
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].contains("'old-c' to 'new-c'"));
+        // The config entry follows the rename, so the diff has nothing to add
+        assert!(cfg.remotes.contains_key("new-a"));
+        assert!(compute_diff(&cfg, &local).is_empty());
+        let names: Vec<_> = local.keys().map(String::as_str).collect();
+        assert_eq!(names, ["new-a", "new-b", "new-c", "old-c", "origin"]);
+    }
 }
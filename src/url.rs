@@ -0,0 +1,520 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::config::Settings;
+
+/// Host and path of a git remote URL, split out for templating and comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrlParts {
+    pub host: String,
+    pub path: String,
+}
+
+impl UrlParts {
+    /// Path segments, ignoring empty ones from leading/trailing slashes.
+    pub fn segments(&self) -> Vec<&str> {
+        self.path.split('/').filter(|s| !s.is_empty()).collect()
+    }
+
+    /// The final path segment without a `.git` suffix.
+    pub fn repo(&self) -> Option<&str> {
+        self.segments()
+            .last()
+            .map(|s| s.strip_suffix(".git").unwrap_or(s))
+    }
+
+    /// Every path segment before the repo, joined with `/`.
+    pub fn org(&self) -> Option<String> {
+        let segments = self.segments();
+        match segments.len() {
+            0 | 1 => None,
+            n => Some(segments[..n - 1].join("/")),
+        }
+    }
+}
+
+/// Split a remote URL into host and path. Understands `scheme://` URLs
+/// (with optional user and port) and scp-like `user@host:path`. Returns
+/// `None` for local paths and anything else without a host.
+pub fn parse_url(url: &str) -> Option<UrlParts> {
+    if let Some((_, rest)) = url.split_once("://") {
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+        let host = host.split_once(':').map_or(host, |(h, _)| h);
+        if host.is_empty() {
+            return None;
+        }
+        return Some(UrlParts {
+            host: host.to_string(),
+            path: path.to_string(),
+        });
+    }
+
+    // scp-like syntax: the colon must come before any slash
+    let (authority, path) = url.split_once(':')?;
+    if authority.is_empty() || authority.contains('/') {
+        return None;
+    }
+    let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    Some(UrlParts {
+        host: host.to_string(),
+        path: path.to_string(),
+    })
+}
+
+/// Reduce a URL to the form used for comparison. The result is only ever
+/// compared, never written back, so config values are applied verbatim.
+///
+/// A `file:///abs/path` URL becomes the bare path, and an absolute local
+/// path that exists is canonicalized, so both spellings of one directory
+/// compare equal. Paths that don't exist are left as they are. Surrounding
+/// whitespace and a single trailing slash are dropped, as both tend to come
+/// from copy-paste.
+///
+/// With `settings.url_path_case_insensitive`, the path part is lowercased;
+/// the scheme, user and host are left alone, as are URLs without a host.
+/// A trailing `.git` is then dropped unless `settings.strict_suffix` is set.
+pub fn normalize_url(url: &str, settings: &Settings) -> String {
+    let url = url.trim();
+    let mut url = match url.strip_prefix("file://") {
+        // Only host-less file URLs map to a local path
+        Some(path) if path.starts_with('/') => path.to_string(),
+        _ => url.to_string(),
+    };
+    if url.len() > 1
+        && !url.ends_with("://")
+        && let Some(stripped) = url.strip_suffix('/')
+    {
+        url.truncate(stripped.len());
+    }
+    if Path::new(&url).is_absolute()
+        && let Ok(canonical) = Path::new(&url).canonicalize()
+        && let Some(canonical) = canonical.to_str()
+    {
+        url = canonical.to_string();
+    }
+    if settings.url_path_case_insensitive
+        && let Some(parts) = parse_url(&url)
+    {
+        // The path is always the tail of the URL in both supported forms
+        let path_start = url.len() - parts.path.len();
+        url.replace_range(path_start.., &parts.path.to_lowercase());
+    }
+    if !settings.strict_suffix
+        && let Some(stripped) = url.strip_suffix(".git")
+    {
+        url.truncate(stripped.len());
+    }
+    url
+}
+
+/// Whether two URLs are equivalent under `settings`.
+pub fn urls_match(a: &str, b: &str, settings: &Settings) -> bool {
+    normalize_url(a, settings) == normalize_url(b, settings)
+}
+
+/// Whether two URL lists are equivalent under `settings`. They are compared
+/// in order unless `settings.url_order_significant` is `false`, in which
+/// case they only need to hold the same URLs.
+pub fn url_lists_match(a: &[String], b: &[String], settings: &Settings) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    if settings.url_order_significant.unwrap_or(true) {
+        return a.iter().zip(b).all(|(x, y)| urls_match(x, y, settings));
+    }
+    let sorted = |urls: &[String]| {
+        let mut normalized: Vec<String> = urls.iter().map(|u| normalize_url(u, settings)).collect();
+        normalized.sort();
+        normalized
+    };
+    sorted(a) == sorted(b)
+}
+
+/// Rewrite the longest prefix of `url` that has a rule, like git's
+/// `url.<base>.insteadOf`. Returns `None` if no rule matches.
+fn apply_rewrites(url: &str, rules: &BTreeMap<String, String>) -> Option<String> {
+    rules
+        .iter()
+        .filter(|(prefix, _)| url.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(prefix, replacement)| format!("{replacement}{}", &url[prefix.len()..]))
+}
+
+/// The fetch URL to use for a configured `url`, after `settings.rewrites`.
+/// Surrounding whitespace is dropped; it is never part of a URL.
+pub fn rewrite_fetch_url(url: &str, settings: &Settings) -> String {
+    let url = url.trim();
+    apply_rewrites(url, &settings.rewrites).unwrap_or_else(|| url.to_string())
+}
+
+/// The push URL to use for a configured `push_url`. Matching
+/// `settings.push_rewrites` take precedence; `settings.rewrites` only apply
+/// when no push rule matches, mirroring git's `pushInsteadOf`. Surrounding
+/// whitespace is dropped.
+pub fn rewrite_push_url(url: &str, settings: &Settings) -> String {
+    let url = url.trim();
+    apply_rewrites(url, &settings.push_rewrites)
+        .or_else(|| apply_rewrites(url, &settings.rewrites))
+        .unwrap_or_else(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strict() -> Settings {
+        Settings {
+            strict_suffix: true,
+            ..Default::default()
+        }
+    }
+
+    fn path_case_insensitive() -> Settings {
+        Settings {
+            url_path_case_insensitive: true,
+            ..Default::default()
+        }
+    }
+
+    fn urls(list: &[&str]) -> Vec<String> {
+        list.iter().map(|u| u.to_string()).collect()
+    }
+
+    #[test]
+    fn url_lists_ordered_by_default() {
+        let settings = Settings::default();
+        let a = urls(&["https://a.com/r.git", "https://b.com/r.git"]);
+        let b = urls(&["https://b.com/r.git", "https://a.com/r.git"]);
+        assert!(url_lists_match(&a, &a, &settings));
+        assert!(!url_lists_match(&a, &b, &settings));
+    }
+
+    #[test]
+    fn url_lists_unordered_when_order_not_significant() {
+        let settings = Settings {
+            url_order_significant: Some(false),
+            ..Default::default()
+        };
+        let a = urls(&["https://a.com/r.git", "https://b.com/r"]);
+        let b = urls(&["https://b.com/r.git", "https://a.com/r"]);
+        assert!(url_lists_match(&a, &b, &settings));
+        // Still the same URLs, not just the same count
+        let c = urls(&["https://a.com/r.git", "https://c.com/r.git"]);
+        assert!(!url_lists_match(&a, &c, &settings));
+    }
+
+    #[test]
+    fn url_lists_differ_in_length() {
+        let settings = Settings {
+            url_order_significant: Some(false),
+            ..Default::default()
+        };
+        let a = urls(&["https://a.com/r.git"]);
+        let b = urls(&["https://a.com/r.git", "https://a.com/r.git"]);
+        assert!(!url_lists_match(&a, &b, &settings));
+    }
+
+    #[test]
+    fn case_only_path_difference_is_drift_by_default() {
+        let settings = Settings::default();
+        assert!(!urls_match(
+            "https://host/Org/Repo.git",
+            "https://host/org/repo.git",
+            &settings
+        ));
+        assert!(!urls_match(
+            "git@host:Org/Repo",
+            "git@host:org/repo",
+            &settings
+        ));
+    }
+
+    #[test]
+    fn case_only_path_difference_matches_when_insensitive() {
+        let settings = path_case_insensitive();
+        assert!(urls_match(
+            "https://host/Org/Repo.git",
+            "https://host/org/repo.git",
+            &settings
+        ));
+        assert!(urls_match(
+            "git@host:Org/Repo",
+            "git@host:org/repo",
+            &settings
+        ));
+        // still combines with suffix stripping
+        assert!(urls_match(
+            "https://host/Org/Repo",
+            "https://host/org/repo.git",
+            &settings
+        ));
+    }
+
+    #[test]
+    fn case_insensitive_paths_keep_host_and_user_case() {
+        let settings = path_case_insensitive();
+        assert_eq!(
+            normalize_url("ssh://Git@Host.example/Org/Repo.git", &settings),
+            "ssh://Git@Host.example/org/repo"
+        );
+        assert_eq!(
+            normalize_url("Git@Host:Org/Repo.git", &settings),
+            "Git@Host:org/repo"
+        );
+        // Local paths have no host and are compared as-is
+        assert_eq!(normalize_url("/srv/Git/Repo", &settings), "/srv/Git/Repo");
+    }
+
+    #[test]
+    fn normalize_strips_git_suffix() {
+        let settings = Settings::default();
+        assert_eq!(
+            normalize_url("https://host/repo.git", &settings),
+            "https://host/repo"
+        );
+        assert_eq!(
+            normalize_url("git@host:repo.git", &settings),
+            "git@host:repo"
+        );
+    }
+
+    #[test]
+    fn normalize_ignores_whitespace_and_trailing_slash() {
+        let settings = Settings::default();
+        assert!(urls_match(
+            "https://host/org/repo.git/",
+            "https://host/org/repo.git",
+            &settings
+        ));
+        assert!(urls_match(
+            "  git@host:org/repo.git\t\n",
+            "git@host:org/repo.git",
+            &settings
+        ));
+        assert!(urls_match(
+            " https://host/org/repo/ ",
+            "https://host/org/repo",
+            &strict()
+        ));
+        // Only the end is trimmed; the path itself still counts
+        assert!(!urls_match(
+            "https://host/org/repo/sub/",
+            "https://host/org/repo",
+            &settings
+        ));
+        // At most one slash goes
+        assert!(!urls_match(
+            "https://host/org/repo//",
+            "https://host/org/repo",
+            &settings
+        ));
+        assert_eq!(normalize_url(" / ", &settings), "/");
+        assert_eq!(normalize_url("https://", &settings), "https://");
+    }
+
+    #[test]
+    fn rewrite_trims_config_url() {
+        let settings = Settings::default();
+        assert_eq!(
+            rewrite_fetch_url(" https://host/repo.git\n", &settings),
+            "https://host/repo.git"
+        );
+        assert_eq!(
+            rewrite_push_url("\tgit@host:repo.git ", &settings),
+            "git@host:repo.git"
+        );
+    }
+
+    #[test]
+    fn git_transport_normalizes_like_https() {
+        let settings = Settings::default();
+        assert_eq!(
+            normalize_url("git://host/org/repo.git/", &settings),
+            "git://host/org/repo"
+        );
+        assert!(urls_match(
+            "git://host/org/repo",
+            "git://host/org/repo.git",
+            &settings
+        ));
+        // A different transport is still a different URL
+        assert!(!urls_match(
+            "git://host/org/repo",
+            "https://host/org/repo",
+            &settings
+        ));
+    }
+
+    #[test]
+    fn file_url_matches_bare_path() {
+        let settings = Settings::default();
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = dir.path().join("repo.git");
+        std::fs::create_dir(&repo).unwrap();
+        let path = repo.to_str().unwrap();
+
+        assert!(urls_match(&format!("file://{path}"), path, &settings));
+        assert!(urls_match(&format!("file://{path}/"), path, &settings));
+        assert!(urls_match(
+            &format!("file://{path}"),
+            &format!("{}/../repo.git", path),
+            &settings
+        ));
+    }
+
+    #[test]
+    fn missing_local_paths_are_not_canonicalized() {
+        let settings = Settings::default();
+        assert_eq!(
+            normalize_url("file:///no/such/dir/../repo.git", &settings),
+            "/no/such/dir/../repo"
+        );
+        assert!(!urls_match(
+            "/no/such/dir/../repo",
+            "/no/such/repo",
+            &settings
+        ));
+        // file://host/path names another machine, not a local path
+        assert_eq!(
+            normalize_url("file://server/srv/repo", &settings),
+            "file://server/srv/repo"
+        );
+    }
+
+    #[test]
+    fn suffix_only_difference_matches() {
+        let settings = Settings::default();
+        assert!(urls_match(
+            "https://host/repo",
+            "https://host/repo.git",
+            &settings
+        ));
+        assert!(urls_match(
+            "https://host/repo.git",
+            "https://host/repo",
+            &settings
+        ));
+    }
+
+    #[test]
+    fn strict_suffix_keeps_difference() {
+        assert!(!urls_match(
+            "https://host/repo",
+            "https://host/repo.git",
+            &strict()
+        ));
+        assert!(urls_match(
+            "https://host/repo.git",
+            "https://host/repo.git",
+            &strict()
+        ));
+    }
+
+    fn rewrite_settings() -> Settings {
+        let mut settings = Settings::default();
+        settings
+            .rewrites
+            .insert("https://github.com/".into(), "https://mirror.com/".into());
+        settings.rewrites.insert(
+            "https://github.com/org/".into(),
+            "https://org-mirror.com/".into(),
+        );
+        settings
+            .push_rewrites
+            .insert("https://github.com/".into(), "git@github.com:".into());
+        settings
+    }
+
+    #[test]
+    fn rewrite_fetch_longest_prefix_wins() {
+        let settings = rewrite_settings();
+        assert_eq!(
+            rewrite_fetch_url("https://github.com/org/repo.git", &settings),
+            "https://org-mirror.com/repo.git"
+        );
+        assert_eq!(
+            rewrite_fetch_url("https://github.com/other/repo.git", &settings),
+            "https://mirror.com/other/repo.git"
+        );
+    }
+
+    #[test]
+    fn rewrite_fetch_ignores_push_rules() {
+        let mut settings = Settings::default();
+        settings
+            .push_rewrites
+            .insert("https://github.com/".into(), "git@github.com:".into());
+        assert_eq!(
+            rewrite_fetch_url("https://github.com/org/repo.git", &settings),
+            "https://github.com/org/repo.git"
+        );
+    }
+
+    #[test]
+    fn rewrite_push_prefers_push_rules() {
+        let settings = rewrite_settings();
+        assert_eq!(
+            rewrite_push_url("https://github.com/org/repo.git", &settings),
+            "git@github.com:org/repo.git"
+        );
+    }
+
+    #[test]
+    fn rewrite_push_falls_back_to_fetch_rules() {
+        let mut settings = Settings::default();
+        settings
+            .rewrites
+            .insert("https://github.com/".into(), "https://mirror.com/".into());
+        assert_eq!(
+            rewrite_push_url("https://github.com/org/repo.git", &settings),
+            "https://mirror.com/org/repo.git"
+        );
+    }
+
+    #[test]
+    fn rewrite_no_match() {
+        let settings = rewrite_settings();
+        assert_eq!(
+            rewrite_fetch_url("https://gitlab.com/org/repo.git", &settings),
+            "https://gitlab.com/org/repo.git"
+        );
+    }
+
+    #[test]
+    fn parse_https() {
+        let parts = parse_url("https://github.com/org/repo.git").unwrap();
+        assert_eq!(parts.host, "github.com");
+        assert_eq!(parts.org().as_deref(), Some("org"));
+        assert_eq!(parts.repo(), Some("repo"));
+    }
+
+    #[test]
+    fn parse_scp_like() {
+        let parts = parse_url("git@github.com:org/repo.git").unwrap();
+        assert_eq!(parts.host, "github.com");
+        assert_eq!(parts.org().as_deref(), Some("org"));
+        assert_eq!(parts.repo(), Some("repo"));
+    }
+
+    #[test]
+    fn parse_ssh_with_port() {
+        let parts = parse_url("ssh://git@host.example:2222/group/sub/repo").unwrap();
+        assert_eq!(parts.host, "host.example");
+        assert_eq!(parts.org().as_deref(), Some("group/sub"));
+        assert_eq!(parts.repo(), Some("repo"));
+    }
+
+    #[test]
+    fn parse_no_org() {
+        let parts = parse_url("https://example.com/repo.git").unwrap();
+        assert!(parts.org().is_none());
+        assert_eq!(parts.repo(), Some("repo"));
+    }
+
+    #[test]
+    fn parse_local_path() {
+        assert!(parse_url("/srv/git/repo.git").is_none());
+        assert!(parse_url("./relative/repo").is_none());
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// What a warning is about, so callers can count or escalate by category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// A `[submodules."..."]` section with no repo on disk
+    OrphanedSection,
+    /// A discovered repo with no config section
+    UnconfiguredRepo,
+    /// A local remote missing from the config, with `extra_remotes = "warn"`
+    ExtraRemote,
+    /// A submodule that isn't checked out
+    UninitializedSubmodule,
+    /// A nested `.git` that couldn't be opened
+    UnreadableRepo,
+    /// A `--remote-name-template` that couldn't be rendered for a URL
+    NameTemplate,
+    /// A repo whose last sync looks interrupted
+    PartialSync,
+    /// A remote with no fetch refspec, so fetching it gets nothing
+    MissingFetchRefspec,
+    /// A `refs/remotes/<name>/HEAD` that points at a branch that isn't there
+    DanglingRemoteHead,
+    /// A remote HEAD that `--refresh-head` couldn't update
+    RemoteHeadRefresh,
+    /// A submodule section key that had to be rewritten to match repo paths
+    SubmodulePath,
+    /// Config remotes whose names differ only by case
+    RemoteNameCase,
+    /// Remotes changed while a merge, rebase or similar was in progress
+    OperationInProgress,
+    /// A `--remote-name-rewrite` rename that was left out
+    NameRewrite,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// Path of the repo or config section the warning is about
+    pub context: String,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Warnings collected over a run, in the order they were raised.
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct Warnings(Vec<Warning>);
+
+impl Warnings {
+    pub fn push(
+        &mut self,
+        kind: WarningKind,
+        context: impl Into<String>,
+        message: impl Into<String>,
+    ) {
+        self.0.push(Warning {
+            kind,
+            context: context.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// How many warnings of `kind` were raised.
+    #[cfg(test)]
+    pub fn count(&self, kind: WarningKind) -> usize {
+        self.0.iter().filter(|w| w.kind == kind).count()
+    }
+}
+
+impl IntoIterator for Warnings {
+    type Item = Warning;
+    type IntoIter = std::vec::IntoIter<Warning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_by_kind() {
+        let mut warnings = Warnings::default();
+        warnings.push(WarningKind::ExtraRemote, "", "remote 'a' not in config");
+        warnings.push(
+            WarningKind::ExtraRemote,
+            "libs/core",
+            "remote 'b' not in config",
+        );
+        warnings.push(WarningKind::OrphanedSection, "libs/gone", "no repo");
+
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(warnings.count(WarningKind::ExtraRemote), 2);
+        assert_eq!(warnings.count(WarningKind::OrphanedSection), 1);
+        assert_eq!(warnings.count(WarningKind::NameTemplate), 0);
+    }
+
+    #[test]
+    fn serializes_as_list() {
+        let mut warnings = Warnings::default();
+        warnings.push(
+            WarningKind::UnconfiguredRepo,
+            "vendor/lib",
+            "no config section",
+        );
+
+        let json = serde_json::to_value(&warnings).unwrap();
+        assert_eq!(json[0]["kind"], "unconfigured_repo");
+        assert_eq!(json[0]["context"], "vendor/lib");
+        assert_eq!(json[0]["message"], "no config section");
+    }
+}
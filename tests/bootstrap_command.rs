@@ -0,0 +1,122 @@
+mod common;
+
+use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{create_test_repo, get_remote_url, write_config};
+use predicates::prelude::*;
+use tempfile::TempDir;
+
+fn gemote() -> Command {
+    cargo_bin_cmd!("gemote")
+}
+
+const CONFIG: &str = r#"
+[remotes.upstream]
+url = "https://upstream.com/tool.git"
+push_url = "git@upstream.com:tool.git"
+"#;
+
+/// A bare repo with one commit holding `config` as its `.gemote`.
+fn bare_repo_with_config(config: &str) -> TempDir {
+    let (src_dir, src) = create_test_repo();
+    write_config(src_dir.path(), config);
+    let mut index = src.index().unwrap();
+    index.add_path(std::path::Path::new(".gemote")).unwrap();
+    let tree = src.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = git2::Signature::now("test", "test@example.com").unwrap();
+    src.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+        .unwrap();
+
+    let bare_dir = TempDir::new().unwrap();
+    git2::build::RepoBuilder::new()
+        .bare(true)
+        .clone(
+            src_dir.path().to_str().unwrap(),
+            &bare_dir.path().join("tool.git"),
+        )
+        .unwrap();
+    bare_dir
+}
+
+#[test]
+fn bootstrap_clones_and_syncs() {
+    let bare = bare_repo_with_config(CONFIG);
+    let url = bare.path().join("tool.git");
+    let work = TempDir::new().unwrap();
+
+    gemote()
+        .current_dir(work.path())
+        .args(["bootstrap", url.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Cloned"))
+        .stdout(predicate::str::contains("Sync complete."));
+
+    // Cloned into a directory named after the repo, with the clone's own
+    // .gemote applied and origin kept
+    let repo = git2::Repository::open(work.path().join("tool")).unwrap();
+    let (origin, _) = get_remote_url(&repo, "origin");
+    assert_eq!(origin, url.to_str().unwrap());
+    let (upstream, push) = get_remote_url(&repo, "upstream");
+    assert_eq!(upstream, "https://upstream.com/tool.git");
+    assert_eq!(push.as_deref(), Some("git@upstream.com:tool.git"));
+}
+
+#[test]
+fn bootstrap_with_external_config() {
+    let bare = bare_repo_with_config("");
+    let url = bare.path().join("tool.git");
+    let work = TempDir::new().unwrap();
+    let config = write_config(work.path(), CONFIG);
+    let dest = work.path().join("checkout");
+
+    gemote()
+        .args([
+            "--config",
+            config.to_str().unwrap(),
+            "bootstrap",
+            url.to_str().unwrap(),
+            dest.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let repo = git2::Repository::open(&dest).unwrap();
+    assert!(repo.find_remote("upstream").is_ok());
+}
+
+#[test]
+fn bootstrap_dry_run_does_not_clone() {
+    let work = TempDir::new().unwrap();
+    let config = write_config(work.path(), CONFIG);
+    let dest = work.path().join("tool");
+
+    gemote()
+        .args([
+            "--config",
+            config.to_str().unwrap(),
+            "bootstrap",
+            "https://example.com/org/tool.git",
+            dest.to_str().unwrap(),
+            "--dry-run",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Would clone"))
+        .stdout(predicate::str::contains("add remote upstream"));
+    assert!(!dest.exists());
+}
+
+#[test]
+fn bootstrap_refuses_non_empty_dir() {
+    let work = TempDir::new().unwrap();
+    std::fs::create_dir(work.path().join("tool")).unwrap();
+    std::fs::write(work.path().join("tool/file"), "").unwrap();
+
+    gemote()
+        .current_dir(work.path())
+        .args(["bootstrap", "https://example.com/org/tool.git"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists and is not empty"));
+}
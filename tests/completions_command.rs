@@ -41,3 +41,50 @@ fn completions_invalid_shell() {
         .failure()
         .stderr(predicate::str::contains("invalid value"));
 }
+
+#[test]
+fn completions_install_bash() {
+    let home = tempfile::TempDir::new().unwrap();
+    let path = home
+        .path()
+        .join(".local/share/bash-completion/completions/gemote");
+
+    gemote()
+        .env("HOME", home.path())
+        .env_remove("XDG_DATA_HOME")
+        .args(["completions", "bash", "--install"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Installed completions to"));
+    let script = std::fs::read_to_string(&path).unwrap();
+    assert!(script.contains("gemote"));
+
+    // A second install needs --force
+    gemote()
+        .env("HOME", home.path())
+        .env_remove("XDG_DATA_HOME")
+        .args(["completions", "bash", "--install"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--force"));
+    gemote()
+        .env("HOME", home.path())
+        .env_remove("XDG_DATA_HOME")
+        .args(["completions", "bash", "--install", "--force"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn completions_install_unsupported_shell() {
+    let home = tempfile::TempDir::new().unwrap();
+
+    gemote()
+        .env("HOME", home.path())
+        .args(["completions", "powershell", "--install"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "no per-user completions directory",
+        ));
+}
@@ -0,0 +1,201 @@
+mod common;
+
+use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{add_test_remote, create_test_repo, write_config};
+use predicates::prelude::*;
+
+fn gemote() -> Command {
+    cargo_bin_cmd!("gemote")
+}
+
+fn synced_repo() -> (tempfile::TempDir, git2::Repository) {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://old.com/repo.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://new.com/repo.git"
+
+[remotes.upstream]
+url = "https://upstream.com/repo.git"
+"#,
+    );
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success();
+    (dir, repo)
+}
+
+#[test]
+fn doctor_without_snapshot() {
+    let (dir, _repo) = create_test_repo();
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "doctor"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No sync recorded"));
+}
+
+#[test]
+fn doctor_after_full_sync() {
+    let (dir, _repo) = synced_repo();
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "doctor"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("fully applied"));
+}
+
+#[test]
+fn doctor_detects_partial_sync() {
+    let (dir, repo) = synced_repo();
+    // Simulate an apply that stopped before adding upstream
+    repo.remote_delete("upstream").unwrap();
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "doctor"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("repo appears partially synced"))
+        .stderr(predicate::str::contains("'upstream'"));
+}
+
+#[test]
+fn doctor_detects_unapplied_sync() {
+    let (dir, repo) = synced_repo();
+    repo.remote_delete("upstream").unwrap();
+    repo.remote_set_url("origin", "https://old.com/repo.git")
+        .unwrap();
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "doctor"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("last sync was not applied"));
+}
+
+#[test]
+fn doctor_ignores_manual_changes() {
+    let (dir, repo) = synced_repo();
+    repo.remote_set_url("origin", "https://elsewhere.com/repo.git")
+        .unwrap();
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "doctor"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("changed since the last sync"));
+}
+
+#[test]
+fn doctor_detects_missing_fetch_refspec() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    repo.config()
+        .unwrap()
+        .remove_multivar("remote.origin.fetch", ".*")
+        .unwrap();
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "doctor"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "remote 'origin' has no fetch refspec",
+        ));
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "doctor", "--fix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("fetch refspec to 'origin'"));
+
+    let remote = repo.find_remote("origin").unwrap();
+    let refspecs = remote.fetch_refspecs().unwrap();
+    assert_eq!(
+        refspecs.iter().flatten().collect::<Vec<_>>(),
+        ["+refs/heads/*:refs/remotes/origin/*"]
+    );
+}
+
+/// Give `remote` a `main` branch and point its HEAD at `head`.
+fn track_branch(repo: &git2::Repository, remote: &str, head: &str) {
+    let sig = git2::Signature::now("test", "test@example.com").unwrap();
+    let tree = repo
+        .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+        .unwrap();
+    let commit = repo.commit(None, &sig, &sig, "init", &tree, &[]).unwrap();
+    repo.reference(&format!("refs/remotes/{remote}/main"), commit, true, "test")
+        .unwrap();
+    repo.reference_symbolic(
+        &format!("refs/remotes/{remote}/HEAD"),
+        &format!("refs/remotes/{remote}/{head}"),
+        true,
+        "test",
+    )
+    .unwrap();
+}
+
+#[test]
+fn doctor_fix_repairs_everything_it_can() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    repo.config()
+        .unwrap()
+        .remove_multivar("remote.origin.fetch", ".*")
+        .unwrap();
+    add_test_remote(&repo, "upstream", "https://example.com/up.git", None);
+    track_branch(&repo, "upstream", "master");
+    add_test_remote(&repo, "fork", "https://example.com/fork.git", None);
+    repo.reference_symbolic(
+        "refs/remotes/fork/HEAD",
+        "refs/remotes/fork/trunk",
+        true,
+        "test",
+    )
+    .unwrap();
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "doctor"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "upstream/HEAD points at upstream/master, which doesn't exist",
+        ))
+        .stderr(predicate::str::contains("doctor found 3 problems"));
+
+    // fork has no main or master to fall back on, so it's left for git
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "doctor",
+            "--fix",
+            "--yes",
+        ])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("fetch refspec to 'origin'"))
+        .stdout(predicate::str::contains("upstream/HEAD at upstream/main"))
+        .stdout(predicate::str::contains("Fixed 2 problems."))
+        .stderr(predicate::str::contains(
+            "git remote set-head <name> --auto",
+        ))
+        .stderr(predicate::str::contains("doctor found 1 problem"));
+
+    assert_eq!(
+        repo.find_remote("origin")
+            .unwrap()
+            .fetch_refspecs()
+            .unwrap()
+            .len(),
+        1
+    );
+    let head = repo.find_reference("refs/remotes/upstream/HEAD").unwrap();
+    assert_eq!(head.symbolic_target(), Some("refs/remotes/upstream/main"));
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{add_test_remote, create_test_repo, write_config};
+use predicates::prelude::*;
+
+fn gemote() -> Command {
+    cargo_bin_cmd!("gemote")
+}
+
+#[test]
+fn inspect_text_output() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(
+        &repo,
+        "origin",
+        "https://example.com/repo.git",
+        Some("git@example.com:repo.git"),
+    );
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+push_url = "git@example.com:repo.git"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "inspect", "origin"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("https://example.com/repo.git"))
+        .stdout(predicate::str::contains("git@example.com:repo.git"))
+        .stdout(predicate::str::contains(
+            "+refs/heads/*:refs/remotes/origin/*",
+        ))
+        .stdout(predicate::str::contains("in sync"));
+}
+
+#[test]
+fn inspect_json_fields() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    let mut config = repo.config().unwrap();
+    config.set_str("remote.origin.tagopt", "--no-tags").unwrap();
+    config.set_bool("remote.origin.prune", true).unwrap();
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/other.git"
+"#,
+    );
+
+    let output = gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "inspect",
+            "origin",
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["name"], "origin");
+    assert_eq!(
+        report["urls"],
+        serde_json::json!(["https://example.com/repo.git"])
+    );
+    assert_eq!(report["push_urls"], serde_json::json!([]));
+    assert_eq!(
+        report["fetch_refspecs"],
+        serde_json::json!(["+refs/heads/*:refs/remotes/origin/*"])
+    );
+    assert_eq!(report["tagopt"], "--no-tags");
+    assert_eq!(report["prune"], true);
+    assert!(report["mirror"].is_null());
+    assert_eq!(report["config"], "drift");
+}
+
+#[test]
+fn inspect_not_in_config() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "extra", "https://example.com/repo.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "inspect", "extra"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("not in config"));
+}
+
+#[test]
+fn inspect_without_config_file() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "inspect", "origin"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("no config file"));
+}
+
+#[test]
+fn inspect_missing_remote() {
+    let (dir, _repo) = create_test_repo();
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "inspect", "nope"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("remote 'nope' not found"));
+}
+
+#[test]
+fn inspect_shows_remote_head() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    repo.reference_symbolic(
+        "refs/remotes/origin/HEAD",
+        "refs/remotes/origin/develop",
+        true,
+        "test",
+    )
+    .unwrap();
+
+    let output = gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "inspect",
+            "origin",
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["head"], "develop");
+}
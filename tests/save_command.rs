@@ -162,6 +162,79 @@ fn save_then_sync_roundtrip() {
     assert_eq!(push_url.as_deref(), Some("git@upstream.com:repo.git"));
 }
 
+/// Every `remote.<name>.<key>` value, in order.
+fn multivar(repo: &git2::Repository, key: &str) -> Vec<String> {
+    let config = repo.config().unwrap().snapshot().unwrap();
+    let mut values = Vec::new();
+    config
+        .multivar(key, None)
+        .unwrap()
+        .for_each(|entry| values.push(entry.value().unwrap().to_string()))
+        .unwrap();
+    values
+}
+
+#[test]
+fn save_then_sync_roundtrip_asymmetric_urls() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(
+        &repo,
+        "origin",
+        "https://example.com/repo.git",
+        Some("git@example.com:repo.git"),
+    );
+    let mut config = repo.config().unwrap();
+    config
+        .set_multivar("remote.origin.url", "^$", "https://mirror.com/repo.git")
+        .unwrap();
+    add_test_remote(&repo, "backup", "https://backup.com/repo.git", None);
+    for push in ["git@a.com:repo.git", "git@b.com:repo.git"] {
+        config
+            .set_multivar("remote.backup.pushurl", "^$", push)
+            .unwrap();
+    }
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "save"])
+        .assert()
+        .success();
+
+    repo.remote_delete("origin").unwrap();
+    repo.remote_delete("backup").unwrap();
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        multivar(&repo, "remote.origin.url"),
+        [
+            "https://example.com/repo.git",
+            "https://mirror.com/repo.git"
+        ]
+    );
+    assert_eq!(
+        multivar(&repo, "remote.origin.pushurl"),
+        ["git@example.com:repo.git"]
+    );
+    assert_eq!(
+        multivar(&repo, "remote.backup.url"),
+        ["https://backup.com/repo.git"]
+    );
+    assert_eq!(
+        multivar(&repo, "remote.backup.pushurl"),
+        ["git@a.com:repo.git", "git@b.com:repo.git"]
+    );
+
+    // The restored remotes match the config exactly
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Already in sync"));
+}
+
 #[test]
 fn save_recursive_with_nested_repo() {
     let (dir, _repo) = create_test_repo();
@@ -231,6 +304,90 @@ fn save_recursive_deeply_nested() {
     assert!(content.contains("https://example.com/inner.git"));
 }
 
+#[test]
+fn save_dry_run_prints_toml() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "save", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[remotes.origin]"))
+        .stdout(predicate::str::contains("https://example.com/repo.git"));
+
+    assert!(!dir.path().join(".gemote").exists());
+}
+
+#[test]
+fn save_dry_run_json() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(
+        &repo,
+        "origin",
+        "https://example.com/repo.git",
+        Some("git@example.com:repo.git"),
+    );
+    let nested = create_nested_repo(dir.path(), "libs/core");
+    nested
+        .remote("origin", "https://example.com/core.git")
+        .unwrap();
+    // An existing config is left alone and doesn't need --force
+    write_config(dir.path(), "# keep me\n");
+
+    let output = gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "-r",
+            "--dry-run",
+            "--format",
+            "json",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let cfg: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(
+        cfg["remotes"]["origin"]["url"],
+        "https://example.com/repo.git"
+    );
+    assert_eq!(
+        cfg["remotes"]["origin"]["push_url"],
+        "git@example.com:repo.git"
+    );
+    assert_eq!(
+        cfg["submodules"]["libs/core"]["remotes"]["origin"]["url"],
+        "https://example.com/core.git"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join(".gemote")).unwrap(),
+        "# keep me\n"
+    );
+}
+
+#[test]
+fn save_recursive_stops_at_max_depth() {
+    let (dir, _repo) = create_test_repo();
+    // One more level than the recursion guard allows
+    let mut path = dir.path().to_path_buf();
+    for level in 1..=17 {
+        create_nested_repo(&path, &format!("l{level}"));
+        path = path.join(format!("l{level}"));
+    }
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "save", "-r"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("nested more than 16 levels deep"));
+    assert!(!dir.path().join(".gemote").exists());
+}
+
 #[test]
 fn save_then_sync_deeply_nested_roundtrip() {
     let (dir, repo) = create_test_repo();
@@ -299,3 +456,374 @@ fn save_then_sync_recursive_roundtrip() {
     let (url, _) = get_remote_url(&nested, "upstream");
     assert_eq!(url, "https://upstream.com/core.git");
 }
+
+#[test]
+fn save_remote_name_template() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "git@github.com:acme/tool.git", None);
+    add_test_remote(
+        &repo,
+        "upstream",
+        "https://gitlab.com/upstream/tool.git",
+        None,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "--remote-name-template",
+            "{host}-{org}",
+        ])
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(dir.path().join(".gemote")).unwrap();
+    assert!(content.contains("[remotes.\"github.com-acme\"]"));
+    assert!(content.contains("[remotes.\"gitlab.com-upstream\"]"));
+    assert!(!content.contains("[remotes.origin]"));
+}
+
+#[test]
+fn save_remote_name_template_collision() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "a", "https://github.com/acme/one.git", None);
+    add_test_remote(&repo, "b", "https://github.com/acme/two.git", None);
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "--remote-name-template",
+            "{org}",
+        ])
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(dir.path().join(".gemote")).unwrap();
+    assert!(content.contains("[remotes.acme]"));
+    assert!(content.contains("[remotes.acme-2]"));
+}
+
+#[test]
+fn save_remote_name_template_keeps_name_on_failure() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "local", "/srv/git/repo.git", None);
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "--remote-name-template",
+            "{org}",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("keeping 'local'"));
+
+    let content = std::fs::read_to_string(dir.path().join(".gemote")).unwrap();
+    assert!(content.contains("[remotes.local]"));
+}
+
+#[test]
+fn save_recursive_no_discover_skips_nested() {
+    let (dir, _repo) = create_test_repo();
+    let nested = create_nested_repo(dir.path(), "libs/core");
+    nested
+        .remote("origin", "https://example.com/core.git")
+        .unwrap();
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "-r",
+            "--no-discover",
+        ])
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(dir.path().join(".gemote")).unwrap();
+    assert!(!content.contains("submodules"));
+}
+
+#[test]
+fn save_recursive_repo_order_leaf_first() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    let nested = create_nested_repo(dir.path(), "libs/core");
+    nested
+        .remote("origin", "https://example.com/core.git")
+        .unwrap();
+    let deep = create_nested_repo(dir.path().join("libs/core").as_path(), "inner");
+    deep.remote("origin", "https://example.com/inner.git")
+        .unwrap();
+
+    let output = gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "-r",
+            "--repo-order",
+            "leaf-first",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let submodules: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("Submodule: "))
+        .collect();
+    assert_eq!(submodules, ["libs/core/inner", "libs/core"]);
+
+    // The nesting in the saved file doesn't depend on the order
+    let content = std::fs::read_to_string(dir.path().join(".gemote")).unwrap();
+    assert!(content.contains("[submodules.\"libs/core\".submodules.inner.remotes.origin]"));
+    assert!(content.contains("[submodules.\"libs/core\".remotes.origin]"));
+}
+
+#[test]
+fn save_remote_updates_only_named_entry() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/new.git", None);
+    add_test_remote(
+        &repo,
+        "upstream",
+        "https://example.com/live-upstream.git",
+        None,
+    );
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/old.git"
+
+[remotes.upstream]
+url = "https://example.com/upstream.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "--remote",
+            "origin",
+        ])
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(dir.path().join(".gemote")).unwrap();
+    assert!(content.contains("https://example.com/new.git"));
+    assert!(!content.contains("https://example.com/old.git"));
+    // upstream keeps its config entry, not the live URL
+    assert!(content.contains("https://example.com/upstream.git"));
+    assert!(!content.contains("live-upstream"));
+}
+
+#[test]
+fn save_remote_leaves_other_sections_unchanged() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/new.git", None);
+    add_test_remote(&repo, "upstream", "https://example.com/live.git", None);
+    let settings = "[settings.remote_defaults]\nprune = true\n";
+    let upstream = "[remotes.upstream]\nurl = \"https://example.com/upstream.git\"\n";
+    write_config(
+        dir.path(),
+        &format!(
+            "{settings}\n[remotes.origin]\nurl = \"https://example.com/old.git\"\n\n{upstream}"
+        ),
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "--remote",
+            "origin",
+        ])
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(dir.path().join(".gemote")).unwrap();
+    assert!(content.contains(settings));
+    // The default isn't copied into the remote it applies to
+    assert!(content.contains(&format!("\n{upstream}")));
+}
+
+#[test]
+fn save_remote_leaves_submodule_sections_unchanged() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    let submodules =
+        "[submodules.\"libs/core\".remotes.origin]\nurl = \"https://example.com/core.git\"\n";
+    write_config(
+        dir.path(),
+        &format!("[settings.submodule_defaults]\nextra_remotes = \"remove\"\n\n{submodules}"),
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "--remote",
+            "origin",
+        ])
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(dir.path().join(".gemote")).unwrap();
+    // The inherited mode isn't written into the section
+    assert!(content.contains(&format!("\n{submodules}")));
+    assert!(!content.contains("[submodules.\"libs/core\".settings"));
+    assert_eq!(content.matches("extra_remotes").count(), 1);
+}
+
+#[test]
+fn save_remote_keeps_comments_and_remote_list() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/new.git", None);
+    add_test_remote(&repo, "upstream", "https://example.com/live.git", None);
+    let rest = r#"
+[[remote]]
+name = "upstream" # upstream project
+url = "https://example.com/upstream.git"
+priority = 1
+"#;
+    write_config(
+        dir.path(),
+        &format!(
+            "# Our remotes\n\n[remotes.origin]\nurl = \"https://example.com/old.git\"\n{rest}"
+        ),
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "--remote",
+            "origin",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(other entries unchanged)"));
+
+    let content = std::fs::read_to_string(dir.path().join(".gemote")).unwrap();
+    assert_eq!(
+        content,
+        format!("# Our remotes\n\n[remotes.origin]\nurl = \"https://example.com/new.git\"\n{rest}")
+    );
+}
+
+#[test]
+fn save_remote_updates_remote_list_entry() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "upstream", "https://example.com/live.git", None);
+    write_config(
+        dir.path(),
+        r#"[[remote]]
+name = "upstream"
+url = "https://example.com/upstream.git"
+priority = 1
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "--remote",
+            "upstream",
+        ])
+        .assert()
+        .success();
+
+    // Still a `[[remote]]` entry, with its priority kept
+    let content = std::fs::read_to_string(dir.path().join(".gemote")).unwrap();
+    assert_eq!(
+        content,
+        r#"[[remote]]
+name = "upstream"
+url = "https://example.com/live.git"
+priority = 1
+"#
+    );
+}
+
+#[test]
+fn save_remote_creates_config_if_missing() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    add_test_remote(&repo, "upstream", "https://example.com/upstream.git", None);
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "--remote",
+            "origin",
+        ])
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(dir.path().join(".gemote")).unwrap();
+    assert!(content.contains("[remotes.origin]"));
+    assert!(!content.contains("upstream"));
+}
+
+#[test]
+fn save_remote_missing_locally_fails() {
+    let (dir, _repo) = create_test_repo();
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "save",
+            "--remote",
+            "origin",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("remote 'origin' not found"));
+
+    assert!(!dir.path().join(".gemote").exists());
+}
+
+#[test]
+fn save_json_config() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    let config = dir.path().join("remotes.json");
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "--config",
+            config.to_str().unwrap(),
+            "save",
+        ])
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(&config).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(
+        json["remotes"]["origin"]["url"],
+        "https://example.com/repo.git"
+    );
+}
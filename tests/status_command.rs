@@ -0,0 +1,157 @@
+mod common;
+
+use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{add_test_remote, create_test_repo, write_config};
+use predicates::prelude::*;
+
+fn gemote() -> Command {
+    cargo_bin_cmd!("gemote")
+}
+
+const CONFIG: &str = r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[remotes.upstream]
+url = "https://upstream.com/repo.git"
+"#;
+
+#[test]
+fn status_in_sync() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    add_test_remote(&repo, "upstream", "https://upstream.com/repo.git", None);
+    add_test_remote(&repo, "mirror", "https://mirror.com/repo.git", None);
+    write_config(
+        dir.path(),
+        &format!(
+            "{CONFIG}priority = 1\n\n[remotes.mirror]\nurl = \"https://mirror.com/repo.git\"\n"
+        ),
+    );
+
+    // Prioritized remotes first, then the rest by name
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "status"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "  upstream: in sync\n  mirror: in sync\n  origin: in sync\n",
+        ))
+        .stdout(predicate::str::contains("Remotes match the config."));
+}
+
+#[test]
+fn status_fails_on_drift() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://old.com/repo.git", None);
+    add_test_remote(&repo, "stray", "https://stray.com/repo.git", None);
+    write_config(dir.path(), CONFIG);
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "status"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("origin: drift"))
+        .stdout(predicate::str::contains("upstream: missing"))
+        // extra_remotes defaults to "ignore", so sync leaves it alone
+        .stdout(predicate::str::contains("stray: not in config"))
+        .stderr(predicate::str::contains("2 remotes out of sync"));
+}
+
+#[test]
+fn status_counts_extras_sync_would_remove() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    add_test_remote(&repo, "upstream", "https://upstream.com/repo.git", None);
+    add_test_remote(&repo, "stray", "https://stray.com/repo.git", None);
+    write_config(
+        dir.path(),
+        &format!("[settings]\nextra_remotes = \"remove\"\n{CONFIG}"),
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "status"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("stray: extra"))
+        .stderr(predicate::str::contains("1 remote out of sync"));
+}
+
+#[test]
+fn status_exit_zero_with_drift() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://old.com/repo.git", None);
+    write_config(dir.path(), CONFIG);
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "status",
+            "--exit-zero",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("origin: drift"))
+        .stdout(predicate::str::contains("upstream: missing"))
+        .stdout(predicate::str::contains("2 remotes out of sync"));
+}
+
+#[test]
+fn status_prefix_limits_report() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://old.com/repo.git", None);
+    add_test_remote(&repo, "pr-1", "https://example.com/pr-1.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[remotes.pr-1]
+url = "https://example.com/pr-1.git"
+"#,
+    );
+
+    // origin has drifted, but it's outside the prefix
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "status",
+            "--prefix",
+            "pr-",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("pr-1: in sync"))
+        .stdout(predicate::str::contains("origin").not());
+}
+
+#[test]
+fn status_only_config_needs_no_repo() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_config(dir.path(), CONFIG);
+
+    gemote()
+        .current_dir(dir.path())
+        .args(["status", "--only-config"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "add remote origin (url: https://example.com/repo.git)",
+        ))
+        .stdout(predicate::str::contains("add remote upstream"))
+        .stdout(predicate::str::contains("2 remotes would be added"));
+
+    // A repo's own remotes are ignored
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    let config = write_config(dir.path(), CONFIG);
+    gemote()
+        .args(["--config", config.to_str().unwrap(), "status", "--no-repo"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("add remote origin"));
+}
@@ -9,6 +9,12 @@ fn gemote() -> Command {
     cargo_bin_cmd!("gemote")
 }
 
+/// Leave a `config.lock` behind in the repo at `root`, so git refuses to
+/// write its config.
+fn lock_git_config(root: &std::path::Path) {
+    std::fs::write(root.join(".git/config.lock"), "").unwrap();
+}
+
 #[test]
 fn sync_no_config() {
     let (dir, _repo) = create_test_repo();
@@ -107,6 +113,158 @@ push_url = "git@example.com:repo.git"
     assert_eq!(push_url.as_deref(), Some("git@example.com:repo.git"));
 }
 
+#[test]
+fn sync_clears_push_url_missing_from_config() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(
+        &repo,
+        "origin",
+        "https://example.com/repo.git",
+        Some("git@example.com:repo.git"),
+    );
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("push_url"));
+
+    let (url, push_url) = get_remote_url(&repo, "origin");
+    assert_eq!(url, "https://example.com/repo.git");
+    assert_eq!(push_url, None);
+}
+
+#[test]
+fn sync_remote_name_rewrite() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    add_test_remote(&repo, "old-alice", "https://example.com/alice.git", None);
+    add_test_remote(&repo, "old-bob", "https://example.com/bob.git", None);
+    add_test_remote(&repo, "old-carol", "https://example.com/carol.git", None);
+    add_test_remote(&repo, "new-carol", "https://example.com/c2.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--remote-name-rewrite",
+            "old-(.*)=new-$1",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("rename"))
+        .stderr(predicate::str::contains(
+            "not renaming remote 'old-carol' to 'new-carol'",
+        ));
+
+    let mut names: Vec<String> = repo
+        .remotes()
+        .unwrap()
+        .iter()
+        .flatten()
+        .map(String::from)
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        ["new-alice", "new-bob", "new-carol", "old-carol", "origin"]
+    );
+    let (url, _) = get_remote_url(&repo, "new-alice");
+    assert_eq!(url, "https://example.com/alice.git");
+}
+
+/// Commit an empty tree in `repo` and point each of `refs` at it.
+fn commit_to(repo: &git2::Repository, refs: &[&str]) {
+    let sig = git2::Signature::now("test", "test@example.com").unwrap();
+    let tree = repo
+        .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+        .unwrap();
+    let commit = repo.commit(None, &sig, &sig, "init", &tree, &[]).unwrap();
+    for name in refs {
+        repo.reference(name, commit, true, "test").unwrap();
+    }
+}
+
+#[test]
+fn sync_refresh_head_after_url_change() {
+    let (dir, repo) = create_test_repo();
+    // The moved repo's default branch is `trunk`
+    let moved = dir.path().join("moved.git");
+    let bare = git2::Repository::init_bare(&moved).unwrap();
+    commit_to(&bare, &["refs/heads/trunk"]);
+    bare.set_head("refs/heads/trunk").unwrap();
+
+    add_test_remote(&repo, "origin", "https://old.example.com/repo.git", None);
+    commit_to(
+        &repo,
+        &["refs/remotes/origin/main", "refs/remotes/origin/trunk"],
+    );
+    repo.reference_symbolic(
+        "refs/remotes/origin/HEAD",
+        "refs/remotes/origin/main",
+        true,
+        "test",
+    )
+    .unwrap();
+    write_config(
+        dir.path(),
+        &format!(
+            "[remotes.origin]\nurl = \"{}\"\n",
+            moved.to_str().unwrap().replace('\\', "/")
+        ),
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--refresh-head",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("origin/HEAD -> origin/trunk"));
+
+    let head = repo.find_reference("refs/remotes/origin/HEAD").unwrap();
+    assert_eq!(head.symbolic_target(), Some("refs/remotes/origin/trunk"));
+}
+
+#[test]
+fn sync_select_needs_terminal() {
+    let (dir, repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "--select"])
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("interactive terminal"));
+
+    assert!(repo.remotes().unwrap().is_empty());
+}
+
 #[test]
 fn sync_already_in_sync() {
     let (dir, repo) = create_test_repo();
@@ -126,6 +284,57 @@ url = "https://example.com/repo.git"
         .stdout(predicate::str::contains("Already in sync"));
 }
 
+#[test]
+fn sync_dry_run_already_in_sync() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(dry run) Already in sync"));
+}
+
+#[test]
+fn sync_dry_run_verbose_prints_git_commands() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://old.com/repo.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://new.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--dry-run",
+            "-v",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "$ git remote set-url origin https://new.com/repo.git",
+        ));
+
+    assert_eq!(
+        get_remote_url(&repo, "origin").0,
+        "https://old.com/repo.git"
+    );
+}
+
 #[test]
 fn sync_dry_run_no_apply() {
     let (dir, repo) = create_test_repo();
@@ -213,6 +422,242 @@ extra_remotes = "remove"
     assert!(repo.find_remote("extra").is_err());
 }
 
+fn renamed_remote_setup() -> (tempfile::TempDir, git2::Repository) {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "old", "https://example.com/repo.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[settings]
+extra_remotes = "remove"
+
+[remotes.upstream]
+url = "https://example.com/repo.git"
+"#,
+    );
+    (dir, repo)
+}
+
+#[test]
+fn sync_rename_detect_off_removes_and_adds() {
+    let (dir, repo) = renamed_remote_setup();
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("remove"))
+        .stdout(predicate::str::contains("rename").not());
+
+    assert!(repo.find_remote("old").is_err());
+    let (url, _) = get_remote_url(&repo, "upstream");
+    assert_eq!(url, "https://example.com/repo.git");
+}
+
+#[test]
+fn sync_rename_detect_renames() {
+    let (dir, repo) = renamed_remote_setup();
+    repo.config()
+        .unwrap()
+        .set_str("remote.old.fetch", "+refs/heads/main:refs/remotes/old/main")
+        .unwrap();
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--rename-detect",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("rename"))
+        .stdout(predicate::str::contains("remove").not());
+
+    assert!(repo.find_remote("old").is_err());
+    let remote = repo.find_remote("upstream").unwrap();
+    assert_eq!(remote.url().unwrap(), "https://example.com/repo.git");
+    // A custom refspec survives the rename as written; remove + add would
+    // have replaced it with the default one
+    assert_eq!(
+        remote.fetch_refspecs().unwrap().get(0),
+        Some("+refs/heads/main:refs/remotes/old/main")
+    );
+}
+
+#[test]
+fn sync_remotes_from_url_list() {
+    let (dir, repo) = create_test_repo();
+    let list = dir.path().join("urls.txt");
+    std::fs::write(
+        &list,
+        "# team forks\n\
+         https://github.com/alice/tool.git\n\
+         \n\
+         git@gitlab.com:bob/tool.git\n",
+    )
+    .unwrap();
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--remotes-from",
+            list.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("add"));
+
+    let (url, _) = get_remote_url(&repo, "alice");
+    assert_eq!(url, "https://github.com/alice/tool.git");
+    let (url, _) = get_remote_url(&repo, "bob");
+    assert_eq!(url, "git@gitlab.com:bob/tool.git");
+    assert_eq!(repo.remotes().unwrap().len(), 2);
+}
+
+#[test]
+fn sync_remotes_from_with_template() {
+    let (dir, repo) = create_test_repo();
+    let list = dir.path().join("urls.txt");
+    std::fs::write(
+        &list,
+        "https://github.com/alice/tool.git\nhttps://gitlab.com/alice/tool.git\n",
+    )
+    .unwrap();
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--remotes-from",
+            list.to_str().unwrap(),
+            "--remote-name-template",
+            "{host}",
+        ])
+        .assert()
+        .success();
+
+    assert!(repo.find_remote("github.com").is_ok());
+    assert!(repo.find_remote("gitlab.com").is_ok());
+}
+
+#[test]
+fn sync_expands_repo_name_per_repo() {
+    let config = r#"
+[remotes.origin]
+url = "git@github.com:org/{repo_name}.git"
+"#;
+    let parent = tempfile::TempDir::new().unwrap();
+    for name in ["alpha", "beta"] {
+        let path = parent.path().join(name);
+        let repo = git2::Repository::init(&path).unwrap();
+        write_config(&path, config);
+
+        gemote()
+            .args(["--repo", path.to_str().unwrap(), "sync"])
+            .assert()
+            .success();
+
+        let (url, _) = get_remote_url(&repo, "origin");
+        assert_eq!(url, format!("git@github.com:org/{name}.git"));
+    }
+}
+
+#[test]
+fn sync_repo_name_override() {
+    let (dir, repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "git@github.com:org/{repo_name}.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--repo-name",
+            "tool",
+        ])
+        .assert()
+        .success();
+
+    let (url, _) = get_remote_url(&repo, "origin");
+    assert_eq!(url, "git@github.com:org/tool.git");
+}
+
+#[test]
+fn sync_lists_changes_by_priority() {
+    let (dir, _repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[remotes.a-mirror]
+url = "https://mirror.com/repo.git"
+
+[remotes.origin]
+url = "https://origin.com/repo.git"
+priority = 1
+
+[remotes.upstream]
+url = "https://upstream.com/repo.git"
+priority = 2
+"#,
+    );
+
+    let output = gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "--dry-run"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+
+    let origin = stdout.find("origin.com").unwrap();
+    let upstream = stdout.find("upstream.com").unwrap();
+    let mirror = stdout.find("mirror.com").unwrap();
+    assert!(origin < upstream);
+    assert!(upstream < mirror);
+}
+
+#[test]
+fn sync_recursive_respect_gitignore() {
+    let (dir, _repo) = create_test_repo();
+    std::fs::write(dir.path().join(".gitignore"), "build/\n").unwrap();
+    create_nested_repo(dir.path(), "build/tool");
+    let config = |respect: bool| {
+        format!(
+            r#"
+[settings]
+discovery.respect_gitignore = {respect}
+"#
+        )
+    };
+
+    write_config(dir.path(), &config(false));
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "-r"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "'build/tool' has no config section",
+        ));
+
+    write_config(dir.path(), &config(true));
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "-r"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("build/tool").not());
+}
+
 #[test]
 fn sync_custom_config_path() {
     let (dir, repo) = create_test_repo();
@@ -277,18 +722,167 @@ url = "https://example.com/core.git"
 }
 
 #[test]
-fn sync_recursive_dry_run() {
-    let (dir, _repo) = create_test_repo();
+fn sync_remote_url_map_across_repos() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://old.example.com/app.git", None);
     let nested = create_nested_repo(dir.path(), "libs/core");
+    add_test_remote(
+        &nested,
+        "origin",
+        "https://example.com/core.git",
+        Some("git@old.example.com:core.git"),
+    );
+    add_test_remote(&nested, "mirror", "https://old.example.com/core.git", None);
 
     write_config(
         dir.path(),
         r#"
+[remotes.origin]
+url = "https://old.example.com/app.git"
+
 [submodules."libs/core".remotes.origin]
 url = "https://example.com/core.git"
+push_url = "git@old.example.com:core.git"
 "#,
     );
-
+    let map = dir.path().join("moves.csv");
+    std::fs::write(
+        &map,
+        "https://old.example.com/app.git,https://new.example.com/app.git\n\
+         git@old.example.com:core.git,git@new.example.com:core.git\n",
+    )
+    .unwrap();
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--remote-url-map",
+            map.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let (url, _) = get_remote_url(&repo, "origin");
+    assert_eq!(url, "https://new.example.com/app.git");
+    let (url, push_url) = get_remote_url(&nested, "origin");
+    assert_eq!(url, "https://example.com/core.git");
+    assert_eq!(push_url.as_deref(), Some("git@new.example.com:core.git"));
+    // Not in the map, and not in the config, so left alone
+    let (url, _) = get_remote_url(&nested, "mirror");
+    assert_eq!(url, "https://old.example.com/core.git");
+    // The config itself isn't touched
+    let config = std::fs::read_to_string(dir.path().join(".gemote")).unwrap();
+    assert!(config.contains("https://old.example.com/app.git"));
+}
+
+#[test]
+fn sync_remote_url_map_updates_unmanaged_remote() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    add_test_remote(&repo, "fork", "https://old.example.com/fork.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+    let map = dir.path().join("moves.csv");
+    std::fs::write(
+        &map,
+        "https://old.example.com/fork.git,https://new.example.com/fork.git\n",
+    )
+    .unwrap();
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--remote-url-map",
+            "moves.csv",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("fork"));
+
+    let (url, _) = get_remote_url(&repo, "fork");
+    assert_eq!(url, "https://new.example.com/fork.git");
+}
+
+#[test]
+fn sync_ndjson_one_line_per_repo() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    let nested = create_nested_repo(dir.path(), "libs/core");
+    add_test_remote(&nested, "origin", "https://old.example.com/core.git", None);
+    add_test_remote(&nested, "stale", "https://example.com/stale.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[submodules."libs/core".settings]
+extra_remotes = "remove"
+
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+
+[submodules."libs/core".remotes.upstream]
+url = "https://example.com/upstream.git"
+"#,
+    );
+
+    let output = gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--ndjson",
+            "--dry-run",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let lines: Vec<serde_json::Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        lines[0],
+        serde_json::json!({"path": "", "status": "in_sync", "adds": 0, "updates": 0, "removes": 0})
+    );
+    assert_eq!(
+        lines[1],
+        serde_json::json!({"path": "libs/core", "status": "planned", "adds": 1, "updates": 1, "removes": 1})
+    );
+    // A dry run changes nothing
+    assert!(nested.find_remote("stale").is_ok());
+}
+
+#[test]
+fn sync_recursive_dry_run() {
+    let (dir, _repo) = create_test_repo();
+    let nested = create_nested_repo(dir.path(), "libs/core");
+
+    write_config(
+        dir.path(),
+        r#"
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+"#,
+    );
+
     gemote()
         .args([
             "--repo",
@@ -424,3 +1018,1700 @@ url = "https://example.com/core.git"
     // Nested repo should NOT have the remote
     assert!(nested.find_remote("origin").is_err());
 }
+
+#[test]
+fn sync_strict_paths_rejects_orphaned_section() {
+    let (dir, repo) = create_test_repo();
+    let _nested = create_nested_repo(dir.path(), "libs/core");
+
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+
+[submodules."nonexistent".remotes.origin]
+url = "https://example.com/missing.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--strict-paths",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'nonexistent'"));
+
+    // Nothing should have been applied
+    assert!(repo.find_remote("origin").is_err());
+}
+
+#[test]
+fn sync_strict_paths_allows_configless_repo() {
+    let (dir, _repo) = create_test_repo();
+    let _nested = create_nested_repo(dir.path(), "libs/core");
+
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--strict-paths",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("has no config section"));
+}
+
+#[test]
+fn sync_ignores_config_above_repo_root() {
+    let outer = tempfile::TempDir::new().unwrap();
+    write_config(
+        outer.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/unrelated.git"
+"#,
+    );
+    let repo_dir = outer.path().join("repo");
+    let repo = git2::Repository::init(&repo_dir).unwrap();
+
+    gemote()
+        .args(["--repo", repo_dir.to_str().unwrap(), "sync"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("config file not found"));
+
+    assert!(repo.find_remote("origin").is_err());
+}
+
+#[test]
+fn sync_config_search_stop_finds_parent_config() {
+    let outer = tempfile::TempDir::new().unwrap();
+    write_config(
+        outer.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/shared.git"
+"#,
+    );
+    let repo_dir = outer.path().join("repo");
+    let repo = git2::Repository::init(&repo_dir).unwrap();
+
+    gemote()
+        .args([
+            "--repo",
+            repo_dir.to_str().unwrap(),
+            "--config-search-stop",
+            outer.path().to_str().unwrap(),
+            "sync",
+        ])
+        .assert()
+        .success();
+
+    let (url, _) = get_remote_url(&repo, "origin");
+    assert_eq!(url, "https://example.com/shared.git");
+}
+
+#[test]
+fn sync_config_search_stop_must_be_ancestor() {
+    let (dir, _repo) = create_test_repo();
+    let elsewhere = tempfile::TempDir::new().unwrap();
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "--config-search-stop",
+            elsewhere.path().to_str().unwrap(),
+            "sync",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not an ancestor"));
+}
+
+#[test]
+fn sync_action_kind_add_skips_removes() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "stale", "https://stale.com/repo.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[settings]
+extra_remotes = "remove"
+
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--action-kind",
+            "add",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 other changes skipped"));
+
+    assert!(repo.find_remote("origin").is_ok());
+    assert!(repo.find_remote("stale").is_ok());
+}
+
+#[test]
+fn sync_action_kind_no_matching_changes() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://old.com/repo.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://new.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--action-kind",
+            "remove",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No changes match --action-kind"));
+
+    let (url, _) = get_remote_url(&repo, "origin");
+    assert_eq!(url, "https://old.com/repo.git");
+}
+
+#[test]
+fn sync_recursive_no_discover_skips_nested() {
+    let (dir, _repo) = create_test_repo();
+    let nested = create_nested_repo(dir.path(), "libs/core");
+
+    write_config(
+        dir.path(),
+        r#"
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--no-discover",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("no matching repo found"));
+
+    // Nested repo is not a submodule, so it must not be touched
+    assert!(nested.find_remote("origin").is_err());
+}
+
+fn sync_order(order: &str) -> Vec<String> {
+    let (dir, _repo) = create_test_repo();
+    create_nested_repo(dir.path(), "libs/core");
+    create_nested_repo(dir.path().join("libs/core").as_path(), "inner");
+    create_nested_repo(dir.path(), "vendor/deps/util");
+
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+
+[submodules."libs/core".submodules.inner.remotes.origin]
+url = "https://example.com/inner.git"
+
+[submodules."vendor/deps/util".remotes.origin]
+url = "https://example.com/util.git"
+"#,
+    );
+
+    let output = gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--repo-order",
+            order,
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.strip_suffix("Sync complete."))
+        .map(|prefix| {
+            prefix
+                .trim_start_matches('[')
+                .trim_end_matches("] ")
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn sync_repo_order_path() {
+    assert_eq!(
+        sync_order("path"),
+        ["", "libs/core", "libs/core/inner", "vendor/deps/util"]
+    );
+}
+
+#[test]
+fn sync_repo_order_depth_first() {
+    assert_eq!(
+        sync_order("depth-first"),
+        ["libs/core/inner", "vendor/deps/util", "libs/core", ""]
+    );
+}
+
+#[test]
+fn sync_repo_order_leaf_first() {
+    assert_eq!(
+        sync_order("leaf-first"),
+        ["libs/core/inner", "libs/core", "vendor/deps/util", ""]
+    );
+}
+
+#[test]
+fn sync_repo_order_requires_recursive() {
+    let (dir, _repo) = create_test_repo();
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--repo-order",
+            "leaf-first",
+        ])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn sync_recursive_error_names_submodule() {
+    let (dir, _repo) = create_test_repo();
+    create_nested_repo(dir.path(), "libs/core");
+    create_nested_repo(dir.path().join("libs/core").as_path(), "inner");
+
+    write_config(
+        dir.path(),
+        r#"
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+
+[submodules."libs/core".submodules.inner.remotes.origin]
+url = "https://example.com/inner.git"
+"#,
+    );
+    // A stale lock on the inner repo's config makes writing to it fail
+    lock_git_config(&dir.path().join("libs/core/inner"));
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "-r"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("in submodule 'libs/core/inner'"))
+        .stderr(predicate::str::contains("Failed to apply sync actions"));
+}
+
+#[test]
+fn sync_json_plan_and_summary() {
+    let (dir, _repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[submodules."libs/missing".remotes.origin]
+url = "https://example.com/missing.git"
+"#,
+    );
+
+    let output = gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["dry_run"], false);
+    assert_eq!(report["repos"][0]["path"], "");
+    assert_eq!(report["repos"][0]["actions"][0]["action"], "add");
+    assert_eq!(report["repos"][0]["actions"][0]["name"], "origin");
+    assert_eq!(report["repos"][0]["applied"], true);
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let summary: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(summary["status"], "ok");
+    assert_eq!(summary["exit_code"], 0);
+    assert!(summary["meaning"].is_string());
+    assert_eq!(summary["repos"], 1);
+    assert_eq!(summary["changes"], 1);
+    assert_eq!(summary["applied"], 1);
+    assert_eq!(summary["skipped"], 0);
+    let warnings = summary["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0]["kind"], "orphaned_section");
+    assert!(
+        warnings[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("libs/missing")
+    );
+    assert!(summary.get("error").is_none());
+}
+
+#[test]
+fn sync_json_summary_reports_error() {
+    let (dir, _repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+    lock_git_config(dir.path());
+
+    let output = gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.lines().count(), 1);
+    let summary: serde_json::Value = serde_json::from_str(&stderr).unwrap();
+    assert_eq!(summary["status"], "error");
+    assert_eq!(summary["exit_code"], 1);
+    assert_eq!(summary["applied"], 0);
+    assert!(
+        summary["error"]
+            .as_str()
+            .unwrap()
+            .contains("Failed to apply sync actions")
+    );
+}
+
+#[test]
+fn sync_json_dry_run_applies_nothing() {
+    let (dir, repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+
+    let output = gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--dry-run",
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["dry_run"], true);
+    assert_eq!(report["repos"][0]["applied"], false);
+    let summary: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(summary["changes"], 1);
+    assert_eq!(summary["applied"], 0);
+    assert!(repo.find_remote("origin").is_err());
+}
+
+/// Commit a .gitmodules entry for a submodule that is never checked out.
+fn add_uninitialized_submodule(dir: &std::path::Path, repo: &git2::Repository) {
+    std::fs::write(
+        dir.join(".gitmodules"),
+        "[submodule \"missing\"]\n\tpath = missing\n\turl = https://example.com/missing.git\n",
+    )
+    .unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(std::path::Path::new(".gitmodules")).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = git2::Signature::now("test", "test@test.com").unwrap();
+    repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+        .unwrap();
+}
+
+#[test]
+fn sync_recursive_warns_uninitialized_submodule() {
+    let (dir, repo) = create_test_repo();
+    add_uninitialized_submodule(dir.path(), &repo);
+    write_config(dir.path(), "");
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "-r"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "uninitialized submodule 'missing'",
+        ));
+}
+
+#[test]
+fn sync_no_submodule_init_warn_flag() {
+    let (dir, repo) = create_test_repo();
+    add_uninitialized_submodule(dir.path(), &repo);
+    write_config(dir.path(), "");
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--no-submodule-init-warn",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("uninitialized").not());
+}
+
+#[test]
+fn sync_no_submodule_init_warn_setting() {
+    let (dir, repo) = create_test_repo();
+    add_uninitialized_submodule(dir.path(), &repo);
+    write_config(
+        dir.path(),
+        r#"
+[settings]
+warn_uninitialized_submodules = false
+
+[submodules."libs/missing".remotes.origin]
+url = "https://example.com/missing.git"
+"#,
+    );
+
+    // Other warnings are still shown
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "-r"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("uninitialized").not())
+        .stderr(predicate::str::contains("libs/missing"));
+}
+
+#[test]
+fn sync_applies_remote_defaults() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[settings.remote_defaults]
+prune = true
+tagopt = "--no-tags"
+
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[remotes.upstream]
+url = "https://example.com/upstream.git"
+tagopt = "--tags"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("prune: (unset) -> true"));
+
+    let config = repo.config().unwrap().snapshot().unwrap();
+    assert!(config.get_bool("remote.origin.prune").unwrap());
+    assert_eq!(config.get_str("remote.origin.tagopt").unwrap(), "--no-tags");
+    assert!(config.get_bool("remote.upstream.prune").unwrap());
+    assert_eq!(config.get_str("remote.upstream.tagopt").unwrap(), "--tags");
+
+    // Nothing left to do on a second run
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Already in sync"));
+}
+
+#[test]
+fn sync_multiple_urls() {
+    let (dir, repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+extra_urls = ["https://mirror.example.com/repo.git"]
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success();
+
+    let config = repo.config().unwrap();
+    let mut urls = Vec::new();
+    config
+        .multivar("remote.origin.url", None)
+        .unwrap()
+        .for_each(|entry| urls.push(entry.value().unwrap().to_string()))
+        .unwrap();
+    assert_eq!(
+        urls,
+        [
+            "https://example.com/repo.git",
+            "https://mirror.example.com/repo.git"
+        ]
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Already in sync"));
+}
+
+#[test]
+fn sync_chdir_from_unrelated_cwd() {
+    let (dir, repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+    let elsewhere = tempfile::TempDir::new().unwrap();
+
+    gemote()
+        .current_dir(elsewhere.path())
+        .args(["-C", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success();
+
+    let (url, _) = get_remote_url(&repo, "origin");
+    assert_eq!(url, "https://example.com/repo.git");
+}
+
+#[test]
+fn sync_chdir_resolves_relative_paths() {
+    let (dir, repo) = create_test_repo();
+    std::fs::create_dir(dir.path().join("conf")).unwrap();
+    std::fs::write(
+        dir.path().join("conf/remotes.toml"),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    )
+    .unwrap();
+    let elsewhere = tempfile::TempDir::new().unwrap();
+
+    gemote()
+        .current_dir(elsewhere.path())
+        .args([
+            "-C",
+            dir.path().to_str().unwrap(),
+            "--repo",
+            ".",
+            "--config",
+            "conf/remotes.toml",
+            "sync",
+        ])
+        .assert()
+        .success();
+
+    let (url, _) = get_remote_url(&repo, "origin");
+    assert_eq!(url, "https://example.com/repo.git");
+}
+
+#[test]
+fn sync_chdir_missing_dir_fails() {
+    gemote()
+        .args(["-C", "/nonexistent/gemote-test-dir", "sync"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to change directory"));
+}
+
+#[test]
+fn sync_jobs_json_order_is_stable() {
+    let (dir, _repo) = create_test_repo();
+    let paths = ["vendor/d", "libs/c", "libs/a", "libs/b"];
+    let mut config = String::from(
+        r#"
+[remotes.origin]
+url = "https://example.com/root.git"
+"#,
+    );
+    for path in paths {
+        create_nested_repo(dir.path(), path);
+        config.push_str(&format!(
+            "\n[submodules.\"{path}\".remotes.origin]\nurl = \"https://example.com/{path}.git\"\n"
+        ));
+    }
+    write_config(dir.path(), &config);
+
+    let run = || {
+        let output = gemote()
+            .args([
+                "--repo",
+                dir.path().to_str().unwrap(),
+                "sync",
+                "-r",
+                "--jobs",
+                "4",
+                "--dry-run",
+                "--format",
+                "json",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        output.stdout
+    };
+
+    let first = run();
+    let report: serde_json::Value = serde_json::from_slice(&first).unwrap();
+    let order: Vec<&str> = report["repos"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|repo| repo["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(order, ["", "libs/a", "libs/b", "libs/c", "vendor/d"]);
+    for repo in report["repos"].as_array().unwrap() {
+        assert_eq!(repo["actions"][0]["action"], "add");
+    }
+
+    for _ in 0..3 {
+        assert_eq!(run(), first);
+    }
+}
+
+#[test]
+fn sync_jobs_applies_every_repo() {
+    let (dir, repo) = create_test_repo();
+    let nested =
+        ["libs/a", "libs/b", "libs/c"].map(|path| (path, create_nested_repo(dir.path(), path)));
+    let mut config = String::from("[remotes.origin]\nurl = \"https://example.com/root.git\"\n");
+    for (path, _) in &nested {
+        config.push_str(&format!(
+            "\n[submodules.\"{path}\".remotes.origin]\nurl = \"https://example.com/{path}.git\"\n"
+        ));
+    }
+    write_config(dir.path(), &config);
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "-j",
+            "2",
+        ])
+        .assert()
+        .success();
+
+    let (url, _) = get_remote_url(&repo, "origin");
+    assert_eq!(url, "https://example.com/root.git");
+    for (path, sub) in &nested {
+        let (url, _) = get_remote_url(sub, "origin");
+        assert_eq!(url, format!("https://example.com/{path}.git"));
+    }
+}
+
+#[test]
+fn sync_forced_config_format() {
+    let (dir, repo) = create_test_repo();
+    let config = dir.path().join("remotes.txt");
+    std::fs::write(
+        &config,
+        r#"{"remotes": {"origin": {"url": "https://example.com/repo.git"}}}"#,
+    )
+    .unwrap();
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "--config",
+            config.to_str().unwrap(),
+            "--config-format",
+            "json",
+            "sync",
+        ])
+        .assert()
+        .success();
+
+    let (url, _) = get_remote_url(&repo, "origin");
+    assert_eq!(url, "https://example.com/repo.git");
+}
+
+#[test]
+fn sync_json_update_has_old_and_new() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(
+        &repo,
+        "origin",
+        "https://old.com/repo.git",
+        Some("git@old.com:repo.git"),
+    );
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://new.com/repo.git"
+push_url = "git@new.com:repo.git"
+
+[remotes.upstream]
+url = "https://upstream.com/repo.git"
+push_url = "git@upstream.com:repo.git"
+"#,
+    );
+
+    let output = gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--dry-run",
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let actions = report["repos"][0]["actions"].as_array().unwrap();
+    let find = |kind: &str| {
+        actions
+            .iter()
+            .find(|a| a["action"] == kind)
+            .unwrap_or_else(|| panic!("no {kind} action in {actions:?}"))
+    };
+
+    let update = find("update_url");
+    assert_eq!(update["old"], "https://old.com/repo.git");
+    assert_eq!(update["new"], "https://new.com/repo.git");
+    let update = find("update_push_url");
+    assert_eq!(update["old"], "git@old.com:repo.git");
+    assert_eq!(update["new"], "git@new.com:repo.git");
+    let add = find("add");
+    assert_eq!(add["push_url"], "git@upstream.com:repo.git");
+}
+
+const SCOPE_CONFIG: &str = r#"
+[remotes.origin]
+url = "https://new.com/repo.git"
+push_url = "git@new.com:repo.git"
+"#;
+
+#[test]
+fn sync_only_push_url() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(
+        &repo,
+        "origin",
+        "https://old.com/repo.git",
+        Some("git@old.com:repo.git"),
+    );
+    write_config(dir.path(), SCOPE_CONFIG);
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--only-push-url",
+        ])
+        .assert()
+        .success();
+
+    let (url, push_url) = get_remote_url(&repo, "origin");
+    assert_eq!(url, "https://old.com/repo.git");
+    assert_eq!(push_url.as_deref(), Some("git@new.com:repo.git"));
+}
+
+#[test]
+fn sync_only_push_url_names_itself_when_skipping() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://old.com/repo.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://new.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--only-push-url",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "No changes match --only-push-url (1 skipped).",
+        ))
+        .stdout(predicate::str::contains("--action-kind").not());
+}
+
+#[test]
+fn sync_only_fetch_url() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(
+        &repo,
+        "origin",
+        "https://old.com/repo.git",
+        Some("git@old.com:repo.git"),
+    );
+    write_config(dir.path(), SCOPE_CONFIG);
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--only-fetch-url",
+        ])
+        .assert()
+        .success();
+
+    let (url, push_url) = get_remote_url(&repo, "origin");
+    assert_eq!(url, "https://new.com/repo.git");
+    assert_eq!(push_url.as_deref(), Some("git@old.com:repo.git"));
+}
+
+#[test]
+fn sync_rejects_invalid_remote_name_at_load() {
+    let (dir, repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[remotes."not a valid name"]
+url = "https://example.com/other.git"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "'not a valid name' is not a valid git remote name",
+        ));
+    // Nothing was applied
+    assert!(repo.find_remote("origin").is_err());
+}
+
+#[test]
+fn sync_dotted_remote_name() {
+    let (dir, repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[remotes."origin.backup"]
+url = "https://backup.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success();
+    let (url, _) = get_remote_url(&repo, "origin.backup");
+    assert_eq!(url, "https://backup.com/repo.git");
+}
+
+const ORPHAN_CONFIG: &str = r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+
+[submodules."libs/gone".remotes.origin]
+url = "https://example.com/gone.git"
+"#;
+
+#[test]
+fn sync_prune_config_sections() {
+    let (dir, repo) = create_test_repo();
+    let core = create_nested_repo(dir.path(), "libs/core");
+    let config = write_config(dir.path(), ORPHAN_CONFIG);
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--prune-config-sections",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Removed config section 'libs/gone'",
+        ));
+
+    let (url, _) = get_remote_url(&repo, "origin");
+    assert_eq!(url, "https://example.com/repo.git");
+    let (url, _) = get_remote_url(&core, "origin");
+    assert_eq!(url, "https://example.com/core.git");
+
+    let contents = std::fs::read_to_string(&config).unwrap();
+    assert!(contents.contains("libs/core"));
+    assert!(!contents.contains("libs/gone"));
+}
+
+#[test]
+fn sync_prune_config_sections_dry_run() {
+    let (dir, _repo) = create_test_repo();
+    create_nested_repo(dir.path(), "libs/core");
+    let config = write_config(dir.path(), ORPHAN_CONFIG);
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--dry-run",
+            "--prune-config-sections",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Would remove config section 'libs/gone'",
+        ));
+
+    assert_eq!(std::fs::read_to_string(&config).unwrap(), ORPHAN_CONFIG);
+}
+
+#[test]
+fn sync_remotes_from_relative_to_config_root() {
+    let (dir, repo) = create_test_repo();
+    let team = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        team.path().join("urls.txt"),
+        "https://github.com/alice/tool.git\n",
+    )
+    .unwrap();
+    // A decoy with the same name next to the config, which is the default root
+    std::fs::write(
+        dir.path().join("urls.txt"),
+        "https://github.com/mallory/tool.git\n",
+    )
+    .unwrap();
+
+    gemote()
+        .current_dir(dir.path())
+        .args([
+            "--config-root",
+            team.path().to_str().unwrap(),
+            "sync",
+            "--remotes-from",
+            "urls.txt",
+        ])
+        .assert()
+        .success();
+
+    assert!(repo.find_remote("alice").is_ok());
+    assert!(repo.find_remote("mallory").is_err());
+}
+
+#[test]
+fn sync_remotes_from_relative_to_config_dir() {
+    let (dir, repo) = create_test_repo();
+    std::fs::write(
+        dir.path().join("urls.txt"),
+        "https://github.com/alice/tool.git\n",
+    )
+    .unwrap();
+    let elsewhere = tempfile::TempDir::new().unwrap();
+
+    gemote()
+        .current_dir(elsewhere.path())
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--remotes-from",
+            "urls.txt",
+        ])
+        .assert()
+        .success();
+
+    assert!(repo.find_remote("alice").is_ok());
+}
+
+#[test]
+fn sync_prefix_prunes_only_matching_extras() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    add_test_remote(&repo, "pr-1", "https://example.com/pr-1.git", None);
+    add_test_remote(&repo, "pr-2", "https://example.com/pr-2.git", None);
+    add_test_remote(&repo, "fork", "https://example.com/fork.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[settings]
+extra_remotes = "remove"
+
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[remotes.pr-2]
+url = "https://example.com/pr-2.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--prefix",
+            "pr-",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("pr-1"))
+        .stdout(predicate::str::contains("fork").not());
+
+    assert!(repo.find_remote("pr-1").is_err());
+    assert!(repo.find_remote("pr-2").is_ok());
+    assert!(repo.find_remote("fork").is_ok());
+    assert!(repo.find_remote("origin").is_ok());
+}
+
+const BACKSLASH_CONFIG: &str = r#"
+[submodules."libs\\core".remotes.origin]
+url = "https://example.com/core.git"
+"#;
+
+#[test]
+fn sync_normalizes_backslash_submodule_key() {
+    let (dir, _repo) = create_test_repo();
+    let core = create_nested_repo(dir.path(), "libs/core");
+    write_config(dir.path(), BACKSLASH_CONFIG);
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "-r"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "submodule section 'libs\\core' uses backslashes",
+        ))
+        .stderr(predicate::str::contains("no matching repo").not());
+
+    let (url, _) = get_remote_url(&core, "origin");
+    assert_eq!(url, "https://example.com/core.git");
+}
+
+#[test]
+fn sync_strict_rejects_backslash_submodule_key() {
+    let (dir, _repo) = create_test_repo();
+    let core = create_nested_repo(dir.path(), "libs/core");
+    write_config(dir.path(), BACKSLASH_CONFIG);
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--strict",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("config has 1 problem (--strict)"));
+
+    assert!(core.find_remote("origin").is_err());
+}
+
+#[test]
+fn sync_use_git_cli() {
+    let (dir, repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+extra_urls = ["https://mirror.com/repo.git"]
+prune = true
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--use-git-cli",
+        ])
+        .assert()
+        .success();
+
+    let config = repo.config().unwrap().snapshot().unwrap();
+    assert!(config.get_bool("remote.origin.prune").unwrap());
+    let mut urls = Vec::new();
+    config
+        .multivar("remote.origin.url", None)
+        .unwrap()
+        .for_each(|entry| urls.push(entry.value().unwrap().to_string()))
+        .unwrap();
+    assert_eq!(
+        urls,
+        [
+            "https://example.com/repo.git",
+            "https://mirror.com/repo.git"
+        ]
+    );
+}
+
+const CASE_CLASH_CONFIG: &str = r#"
+[remotes.Origin]
+url = "https://example.com/repo.git"
+
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#;
+
+#[test]
+fn sync_warns_on_remote_names_differing_by_case() {
+    let (dir, _repo) = create_test_repo();
+    write_config(dir.path(), CASE_CLASH_CONFIG);
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "--dry-run"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "remotes 'Origin', 'origin' differ only by case",
+        ));
+}
+
+#[test]
+fn sync_strict_rejects_remote_names_differing_by_case() {
+    let (dir, repo) = create_test_repo();
+    write_config(dir.path(), CASE_CLASH_CONFIG);
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "--strict"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("differ only by case"));
+    assert!(repo.remotes().unwrap().is_empty());
+}
+
+#[test]
+fn sync_writes_insteadof_rules() {
+    let (dir, repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[settings.insteadof]
+"git@github.com:" = "https://github.com/"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("url.git@github.com:.insteadOf"));
+
+    let config = repo.config().unwrap().snapshot().unwrap();
+    assert_eq!(
+        config.get_string("url.git@github.com:.insteadof").unwrap(),
+        "https://github.com/"
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("insteadOf").not());
+}
+
+#[test]
+fn sync_prefix_leaves_insteadof_rules_alone() {
+    let (dir, repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[settings.insteadof]
+"git@github.com:" = "https://github.com/"
+
+[remotes.pr-1]
+url = "https://example.com/pr-1.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--prefix",
+            "pr-",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("pr-1"))
+        .stdout(predicate::str::contains("insteadOf").not());
+
+    let config = repo.config().unwrap().snapshot().unwrap();
+    assert!(config.get_string("url.git@github.com:.insteadof").is_err());
+    assert!(repo.find_remote("pr-1").is_ok());
+}
+
+#[test]
+fn sync_max_removals_per_repo_names_the_repo() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "stale", "https://example.com/stale.git", None);
+    let sub = create_nested_repo(dir.path(), "libs/core");
+    for name in ["a", "b", "c"] {
+        add_test_remote(&sub, name, &format!("https://example.com/{name}.git"), None);
+    }
+    write_config(
+        dir.path(),
+        r#"
+[settings]
+extra_remotes = "remove"
+
+[submodules."libs/core".settings]
+extra_remotes = "remove"
+"#,
+    );
+
+    // Four removals in total, but only libs/core goes over the cap
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--max-removals-per-repo",
+            "2",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "'libs/core' would remove 3 remotes, more than --max-removals-per-repo 2",
+        ));
+    assert!(repo.find_remote("stale").is_err());
+    assert_eq!(sub.remotes().unwrap().len(), 3);
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--max-removals-per-repo",
+            "3",
+        ])
+        .assert()
+        .success();
+    assert!(sub.remotes().unwrap().is_empty());
+}
+
+#[test]
+fn sync_writes_junit_report() {
+    let (dir, _repo) = create_test_repo();
+    create_nested_repo(dir.path(), "libs/core");
+    create_nested_repo(dir.path(), "libs/util");
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/root.git"
+
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+
+[submodules."libs/util".remotes.origin]
+url = "https://example.com/util.git"
+"#,
+    );
+    lock_git_config(&dir.path().join("libs/util"));
+    let report = dir.path().join("report.xml");
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--report-format",
+            "junit",
+            "--report",
+            report.to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+
+    let xml = std::fs::read_to_string(&report).unwrap();
+    assert!(xml.starts_with("<?xml version=\"1.0\""));
+    assert!(xml.contains("<testsuite name=\"gemote sync\" tests=\"3\" failures=\"1\""));
+    assert!(xml.contains("<testcase name=\".\""));
+    assert!(xml.contains("<testcase name=\"libs/core\""));
+    assert!(xml.contains("<property name=\"changes\" value=\"1\"/>"));
+    assert!(xml.contains("<property name=\"applied\" value=\"true\"/>"));
+    let failed = xml.split("<testcase name=\"libs/util\"").nth(1).unwrap();
+    assert!(failed.contains("<failure message=\"in submodule &apos;libs/util&apos;"));
+}
+
+#[test]
+fn sync_rejects_reserved_remote_name() {
+    let (dir, repo) = create_test_repo();
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[remotes.HEAD]
+url = "https://example.com/other.git"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "remote name 'HEAD' is reserved by git for a special ref",
+        ));
+    assert!(repo.remotes().unwrap().is_empty());
+}
+
+#[test]
+fn sync_show_unmanaged_lists_extras_on_stdout() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    add_test_remote(&repo, "scratch", "https://example.com/scratch.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--show-unmanaged",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("scratch (unmanaged)"))
+        .stdout(predicate::str::contains("Already in sync"))
+        .stderr(predicate::str::is_empty());
+    assert!(repo.find_remote("scratch").is_ok());
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("unmanaged").not());
+}
+
+#[test]
+fn sync_preserve_unknown_remotes_overrides_remove() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/old.git", None);
+    add_test_remote(&repo, "scratch", "https://example.com/scratch.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[settings]
+extra_remotes = "remove"
+
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--preserve-unknown-remotes",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("scratch (unmanaged)"))
+        .stdout(predicate::str::contains("remove remote").not())
+        .stdout(predicate::str::contains("Sync complete"));
+    assert!(repo.find_remote("scratch").is_ok());
+    assert_eq!(
+        get_remote_url(&repo, "origin").0,
+        "https://example.com/repo.git"
+    );
+}
+
+#[test]
+fn sync_submodule_inherits_default_extra_remotes() {
+    let (dir, _repo) = create_test_repo();
+    let core = create_nested_repo(dir.path(), "libs/core");
+    add_test_remote(&core, "stale", "https://example.com/stale.git", None);
+    let inner = create_nested_repo(&dir.path().join("libs/core"), "inner");
+    add_test_remote(&inner, "stale", "https://example.com/stale.git", None);
+    let util = create_nested_repo(dir.path(), "libs/util");
+    add_test_remote(&util, "local", "https://example.com/local.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[settings.submodule_defaults]
+extra_remotes = "remove"
+
+[submodules."libs/core".remotes.origin]
+url = "https://example.com/core.git"
+
+[submodules."libs/core".submodules."inner".remotes.origin]
+url = "https://example.com/inner.git"
+
+[submodules."libs/util".settings]
+extra_remotes = "ignore"
+
+[submodules."libs/util".remotes.origin]
+url = "https://example.com/util.git"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "-r"])
+        .assert()
+        .success();
+
+    // libs/core and its own sub-repo inherit "remove"; libs/util overrides it
+    assert!(core.find_remote("stale").is_err());
+    assert!(inner.find_remote("stale").is_err());
+    assert!(util.find_remote("local").is_ok());
+}
+
+#[test]
+fn sync_dry_run_groups_updates_per_remote() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(
+        &repo,
+        "origin",
+        "https://old.com/repo.git",
+        Some("git@old.com:repo.git"),
+    );
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://new.com/repo.git"
+push_url = "git@new.com:repo.git"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "  update remote origin\n    url: https://old.com/repo.git -> https://new.com/repo.git\n    push_url: git@old.com:repo.git -> git@new.com:repo.git\n",
+        ));
+}
+
+#[test]
+fn sync_warnings_as_json() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    create_nested_repo(dir.path(), "vendor/lib");
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[submodules."libs/gone".remotes.origin]
+url = "https://example.com/gone.git"
+"#,
+    );
+
+    let output = gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "-r",
+            "--warnings-as-json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let warnings: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    let warnings = warnings.as_array().unwrap();
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings.iter().any(|w| w["kind"] == "orphaned_section"
+        && w["context"] == "libs/gone"
+        && w["message"].as_str().unwrap().contains("no matching repo")));
+    assert!(
+        warnings
+            .iter()
+            .any(|w| w["kind"] == "unconfigured_repo" && w["context"] == "vendor/lib")
+    );
+
+    // Nothing extra without warnings
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#,
+    );
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--warnings-as-json",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+}
+
+const MERGE_CONFIG: &str = r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+"#;
+
+/// Make `repo` look like it is in the middle of a merge.
+fn start_merge(repo: &git2::Repository) {
+    std::fs::write(
+        repo.path().join("MERGE_HEAD"),
+        "0000000000000000000000000000000000000000\n",
+    )
+    .unwrap();
+    assert_eq!(repo.state(), git2::RepositoryState::Merge);
+}
+
+#[test]
+fn sync_warns_during_merge() {
+    let (dir, repo) = create_test_repo();
+    write_config(dir.path(), MERGE_CONFIG);
+    start_merge(&repo);
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "changing remotes of the repo during a merge",
+        ));
+    assert!(repo.find_remote("origin").is_ok());
+}
+
+#[test]
+fn sync_refuses_during_merge() {
+    let (dir, repo) = create_test_repo();
+    write_config(dir.path(), MERGE_CONFIG);
+    start_merge(&repo);
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--refuse-during-operation",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the repo is in the middle of a merge",
+        ));
+    assert!(repo.find_remote("origin").is_err());
+
+    // The setting does the same
+    write_config(
+        dir.path(),
+        &format!("[settings]\nrefuse_during_operation = true\n{MERGE_CONFIG}"),
+    );
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .failure();
+    assert!(repo.find_remote("origin").is_err());
+}
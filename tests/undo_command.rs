@@ -0,0 +1,186 @@
+mod common;
+
+use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{add_test_remote, create_test_repo, get_remote_url, write_config};
+use predicates::prelude::*;
+
+fn gemote() -> Command {
+    cargo_bin_cmd!("gemote")
+}
+
+#[test]
+fn undo_restores_previous_remotes() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(
+        &repo,
+        "origin",
+        "https://old.com/repo.git",
+        Some("git@old.com:repo.git"),
+    );
+    add_test_remote(&repo, "stale", "https://stale.com/repo.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[settings]
+extra_remotes = "remove"
+
+[remotes.origin]
+url = "https://new.com/repo.git"
+
+[remotes.upstream]
+url = "https://upstream.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success();
+    assert_eq!(
+        get_remote_url(&repo, "origin"),
+        ("https://new.com/repo.git".to_string(), None)
+    );
+    assert!(repo.find_remote("stale").is_err());
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "undo"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Sync complete"));
+
+    assert_eq!(
+        get_remote_url(&repo, "origin"),
+        (
+            "https://old.com/repo.git".to_string(),
+            Some("git@old.com:repo.git".to_string())
+        )
+    );
+    assert_eq!(
+        get_remote_url(&repo, "stale").0,
+        "https://stale.com/repo.git"
+    );
+    assert!(repo.find_remote("upstream").is_err());
+}
+
+#[test]
+fn undo_after_prefix_sync_keeps_other_remotes() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    add_test_remote(&repo, "fork", "https://example.com/fork.git", None);
+    add_test_remote(&repo, "pr-1", "https://example.com/pr-1.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[settings]
+extra_remotes = "remove"
+
+[remotes.origin]
+url = "https://example.com/repo.git"
+
+[remotes.fork]
+url = "https://example.com/fork.git"
+"#,
+    );
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "sync",
+            "--prefix",
+            "pr-",
+        ])
+        .assert()
+        .success();
+    assert!(repo.find_remote("pr-1").is_err());
+
+    // Only the remote the prefixed sync removed comes back
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "undo"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("remove remote").not());
+
+    let mut names: Vec<_> = repo
+        .remotes()
+        .unwrap()
+        .iter()
+        .flatten()
+        .map(String::from)
+        .collect();
+    names.sort();
+    assert_eq!(names, ["fork", "origin", "pr-1"]);
+}
+
+#[test]
+fn undo_dry_run_no_apply() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://old.com/repo.git", None);
+    write_config(
+        dir.path(),
+        r#"
+[remotes.origin]
+url = "https://new.com/repo.git"
+"#,
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "sync"])
+        .assert()
+        .success();
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "undo", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("https://old.com/repo.git"))
+        .stdout(predicate::str::contains("dry run"));
+
+    assert_eq!(
+        get_remote_url(&repo, "origin").0,
+        "https://new.com/repo.git"
+    );
+}
+
+#[test]
+fn undo_from_backup() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://current.com/repo.git", None);
+    let backup = dir.path().join("backup.toml");
+    std::fs::write(
+        &backup,
+        r#"
+[before.origin]
+url = "https://backup.com/repo.git"
+"#,
+    )
+    .unwrap();
+
+    gemote()
+        .args([
+            "--repo",
+            dir.path().to_str().unwrap(),
+            "undo",
+            "--from-backup",
+            backup.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    assert_eq!(
+        get_remote_url(&repo, "origin").0,
+        "https://backup.com/repo.git"
+    );
+}
+
+#[test]
+fn undo_without_snapshot() {
+    let (dir, _repo) = create_test_repo();
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "undo"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no sync snapshot found"));
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{add_test_remote, create_test_repo, write_config};
+use predicates::prelude::*;
+
+fn gemote() -> Command {
+    cargo_bin_cmd!("gemote")
+}
+
+const CONFIG: &str = r#"
+[remotes.origin]
+url = "https://example.com/repo.git"
+push_url = "git@example.com:repo.git"
+"#;
+
+#[test]
+fn verify_exact_match() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(
+        &repo,
+        "origin",
+        "https://example.com/repo.git",
+        Some("git@example.com:repo.git"),
+    );
+    write_config(dir.path(), CONFIG);
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "verify"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("match the config exactly"));
+}
+
+#[test]
+fn verify_missing_remote() {
+    let (dir, _repo) = create_test_repo();
+    write_config(dir.path(), CONFIG);
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "verify"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("missing"))
+        .stderr(predicate::str::contains(
+            "do not match the config (1 difference)",
+        ));
+}
+
+#[test]
+fn verify_extra_remote_even_when_ignored() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(
+        &repo,
+        "origin",
+        "https://example.com/repo.git",
+        Some("git@example.com:repo.git"),
+    );
+    add_test_remote(&repo, "stray", "https://stray.com/repo.git", None);
+    write_config(
+        dir.path(),
+        &format!("[settings]\nextra_remotes = \"ignore\"\n{CONFIG}"),
+    );
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "verify"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("extra"))
+        .stdout(predicate::str::contains("stray"));
+}
+
+#[test]
+fn verify_drifted_url() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(
+        &repo,
+        "origin",
+        "https://other.com/repo.git",
+        Some("git@example.com:repo.git"),
+    );
+    write_config(dir.path(), CONFIG);
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "verify"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("drift"))
+        .stdout(predicate::str::contains("https://other.com/repo.git"));
+}
+
+#[test]
+fn verify_drifted_push_url() {
+    let (dir, repo) = create_test_repo();
+    add_test_remote(&repo, "origin", "https://example.com/repo.git", None);
+    write_config(dir.path(), CONFIG);
+
+    gemote()
+        .args(["--repo", dir.path().to_str().unwrap(), "verify"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("drift"))
+        .stdout(predicate::str::contains("push_url"));
+}